@@ -1,20 +1,60 @@
-use lmang_lib::{builtins::Builtins, env::Env, expr::Expr, system, val::Val};
+use lmang_lib::{
+    builtins::Builtins,
+    env::Env,
+    error::Traced,
+    expr::{class::show, Expr},
+    system,
+    val::Val,
+    vm::Program,
+};
 
 #[cfg(feature = "mimalloc")]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 fn main() {
-    let path = std::env::args().nth(1).expect("no file given");
-    let code = std::fs::read_to_string(path).unwrap();
+    let mut args = std::env::args().skip(1).peekable();
+    let use_vm = args.next_if_eq("--vm").is_some();
+    let path = args.next().expect("no file given");
+    let code = std::fs::read_to_string(&path).unwrap();
 
-    let (_, expr) = Expr::new(&code).unwrap();
+    let mut expr = match Expr::parse(&code) {
+        Ok((_, expr)) => expr,
+        Err(e) => {
+            eprintln!("{}", e.render(&code));
+            std::process::exit(1);
+        }
+    };
 
     let mut env = Env::new();
-    env.eval(&Builtins::new(system::Native::new(2))).unwrap();
-    let val = env.eval(&expr).unwrap();
+    env.set_main_file(&path);
+    if let Some(lib_dir) = std::env::var_os("LMANG_LIB") {
+        env.set_lib_dir(lib_dir);
+    }
+    let skip_n = if use_vm { 3 } else { 2 };
+    env.eval(&Builtins::new(system::Native::new(skip_n)))
+        .unwrap();
+    if let Err(e) = expr.resolve(&env) {
+        eprintln!("{}", e.render(&code));
+        std::process::exit(1);
+    }
 
-    if val != Val::Unit {
-        println!("{}", val);
+    let result = if use_vm {
+        env.eval(&Program::compile(&expr))
+    } else {
+        env.eval(&expr)
+    };
+    let shown = result.and_then(|val| match val {
+        Val::Unit => Ok(None),
+        val => show(&val, &mut env).map(Some),
+    });
+    match shown {
+        Ok(Some(shown)) => println!("{}", shown),
+        Ok(None) => {}
+        Err(error) => {
+            let trace = env.take_trace();
+            eprintln!("{}", Traced { error, trace }.report());
+            std::process::exit(1);
+        }
     }
 }
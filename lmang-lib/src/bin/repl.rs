@@ -1,11 +1,20 @@
 use std::io::BufRead;
 use std::io::Write;
 
-use lmang_lib::{builtins::Builtins, env::Env, expr::Expr, system, val::Val};
+use lmang_lib::{
+    builtins::Builtins,
+    env::Env,
+    expr::{class::show, Expr},
+    system,
+    val::Val,
+};
 
 fn main() -> Result<(), String> {
     let mut env = Env::new();
     env.eval(&Builtins::new(system::Native::new(1))).unwrap();
+    if let Some(lib_dir) = std::env::var_os("LMANG_LIB") {
+        env.set_lib_dir(lib_dir);
+    }
 
     let mut prompt = "✅";
     let mut input = String::new();
@@ -22,7 +31,7 @@ fn main() -> Result<(), String> {
             break Ok(());
         }
 
-        let maybe_res: Option<Result<_, Val>> = if !line.trim().is_empty() {
+        let maybe_res: Option<Result<_, String>> = if !line.trim().is_empty() {
             if !input.is_empty() {
                 input.push_str(&line);
 
@@ -31,13 +40,13 @@ fn main() -> Result<(), String> {
                         let res = env.eval(&expr);
                         input.clear();
 
-                        Some(res.map_err(|err| err.into()))
+                        Some(res.map_err(|err| err.to_string()))
                     }
                     Err(_) => None,
                 }
             } else {
                 match Expr::new(&line[..]) {
-                    Ok((_, expr)) => Some(env.eval(&expr).map_err(|err| err.into())),
+                    Ok((_, expr)) => Some(env.eval(&expr).map_err(|err| err.to_string())),
                     Err(_) => {
                         input.push_str(&line);
 
@@ -46,33 +55,37 @@ fn main() -> Result<(), String> {
                 }
             }
         } else {
-            match Expr::new(&input[..]) {
+            match Expr::parse(&input[..]) {
                 Ok((_, expr)) => {
                     let res = env.eval(&expr);
                     input.clear();
 
-                    Some(res.map_err(|err| err.into()))
+                    Some(res.map_err(|err| err.to_string()))
                 }
                 Err(e) => {
+                    let rendered = e.render(&input);
                     input.clear();
-                    Some(Err(e.into()))
+                    Some(Err(rendered))
                 }
             }
         };
 
         if let Some(res) = maybe_res {
-            if let Ok(Val::Unit) = res {
-                prompt = "✅";
-            } else {
-                match res {
-                    Ok(v) => {
-                        prompt = "✅";
-                        println!("{}", v);
-                    }
-                    Err(e) => {
-                        prompt = "❌";
-                        println!("{}", e);
-                    }
+            let shown = res.and_then(|v| match v {
+                Val::Unit => Ok(None),
+                v => show(&v, &mut env).map(Some).map_err(|err| err.to_string()),
+            });
+            // frames left by uncaught errors only concern this input.
+            env.take_trace();
+            match shown {
+                Ok(None) => prompt = "✅",
+                Ok(Some(shown)) => {
+                    prompt = "✅";
+                    println!("{}", shown);
+                }
+                Err(e) => {
+                    prompt = "❌";
+                    println!("{}", e);
                 }
             }
         } else {
@@ -1,6 +1,7 @@
 use super::{FnState, RustFn};
 use crate::env::{Env, Eval};
 use crate::error::RuntimeError;
+use crate::expr::class::show;
 use crate::val::{
     view::{self, test_consumed, view1},
     Val,
@@ -9,10 +10,13 @@ use std::cell::RefCell;
 use std::fmt::Write as _;
 use std::rc::Rc;
 
+/// Name of the builtin evaluating code, which sees the locals of its caller.
+pub(crate) const EVAL: &str = "🪞";
+
 pub(crate) type PrintImpl = Box<dyn FnMut(String) -> Result<(), RuntimeError>>;
 pub(crate) type ReadImpl = Box<dyn FnMut() -> Result<String, RuntimeError>>;
 
-fn print(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
+fn print(args: &mut [Val], env: &mut Env, state: FnState) -> Result<Val, Val> {
     let mut borrow = state.0.borrow_mut();
     let print_impl: &mut PrintImpl = borrow.downcast_mut().unwrap();
 
@@ -30,9 +34,9 @@ fn print(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
         }) {
             args = &args[1..];
             if name == "sep" {
-                sep = Some(format!("{}", val));
+                sep = Some(show(&val, env)?);
             } else if name == "end" {
-                end = Some(format!("{}", val));
+                end = Some(show(&val, env)?);
             }
         }
 
@@ -41,12 +45,14 @@ fn print(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
         let end = end.unwrap_or_else(|| "\n".to_string());
 
         for arg in &args[0..args.len() - 1] {
+            let arg = show(arg, env)?;
             write!(&mut buf, "{}{}", arg, sep).map_err(|e| RuntimeError::IoError {
                 file: "stdout".into(),
                 reason: e.to_string(),
             })?;
         }
-        write!(&mut buf, "{}{}", args.last().unwrap(), end).map_err(|e| RuntimeError::IoError {
+        let last = show(args.last().unwrap(), env)?;
+        write!(&mut buf, "{}{}", last, end).map_err(|e| RuntimeError::IoError {
             file: "stdout".into(),
             reason: e.to_string(),
         })?;
@@ -67,23 +73,14 @@ fn read(_args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
     let read_impl: &mut ReadImpl = borrow.downcast_mut().unwrap();
 
     let line = read_impl()?;
-    let deque = line
-        .strip_suffix('\n')
-        .unwrap_or(&line[..])
-        .chars()
-        .map(Val::Char)
-        .collect();
-    Ok(Val::Deque(Box::new(deque)))
+    Ok(Val::from(line.strip_suffix('\n').unwrap_or(&line[..])))
 }
 
 fn eval(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
     let (code, tail) = view1::<view::AnyRef<view::String>, _, _>(args, |s| Ok(s.clone()))?;
     test_consumed(tail)?;
 
-    let (_, expr) = crate::expr::Expr::new(&code).map_err(|_e| RuntimeError::CastError {
-        from: "string".to_string(),
-        to: "code".to_string(),
-    })?;
+    let (_, expr) = crate::expr::Expr::parse(&code)?;
 
     env.eval(&expr)
 }
@@ -92,10 +89,7 @@ fn fmt(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
     let (code, tail) = view1::<view::AnyRef<view::String>, _, _>(args, |s| Ok(s.clone()))?;
     test_consumed(tail)?;
 
-    let (_, expr) = crate::expr::Expr::new(&code).map_err(|_e| RuntimeError::CastError {
-        from: "string".to_string(),
-        to: "code".to_string(),
-    })?;
+    let (_, expr) = crate::expr::Expr::parse(&code)?;
 
     let formatted = format!("{}", crate::expr::Display(&expr));
 
@@ -126,7 +120,7 @@ impl Eval for BuiltinFns {
             "👂".to_string(),
             RustFn::stateful("read", read, &self.read_impl).into_val(),
         );
-        env.store_binding("🪞".to_string(), RustFn::new("eval", eval).into_val());
+        env.store_binding(EVAL.to_string(), RustFn::new("eval", eval).into_val());
         env.store_binding("🔏".to_string(), RustFn::new("fmt", fmt).into_val());
 
         Ok(Val::Unit)
@@ -5,7 +5,9 @@ mod rustfn;
 use crate::env::{Env, Eval};
 use crate::system::System;
 use crate::val::Val;
-use rustfn::{FnState, RustFn};
+pub(crate) use fns::EVAL;
+pub(crate) use objects::RustObj;
+pub(crate) use rustfn::{FnState, RustFn};
 
 pub struct Builtins<S: System> {
     system: S,
@@ -1,13 +1,20 @@
 use crate::builtins::objects::rustobj::RustObj;
 use crate::builtins::rustfn::{FnState, RustFn};
 use crate::env::Env;
-use crate::val::view::{self, foreach, take_n, test_consumed, view1, view2, view3, DequeExt as _};
+use crate::error::{RuntimeError, TraceFrame};
+use crate::expr::{call, for_expr, Op};
+use crate::val::view::{
+    self, foreach, relative_bound, take_n, test_consumed, view1, view2, view3, DequeExt as _,
+    View as _,
+};
 use crate::val::Val;
 use std::collections::VecDeque;
 
 fn len(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
-    let (val, tail) =
-        view1::<view::AnyRef<view::Deque>, _, _>(args, |dq| Ok(Val::Number(dq.len() as i32)))?;
+    let (val, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| match v {
+        Val::Str(s) => Ok(Val::Number(s.chars().count() as i32)),
+        _ => Ok(Val::Number(v.as_deque()?.len() as i32)),
+    })?;
     test_consumed(tail)?;
 
     Ok(val)
@@ -25,7 +32,7 @@ fn append(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val>
 
 fn concat(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
     let (res, tail) =
-        view2::<view::Ref<view::Deque>, view::AnyRef<view::Deque>, _, _>(args, |dq1, dq2| {
+        view2::<view::Ref<view::Deque>, view::AnyRef<view::Items>, _, _>(args, |dq1, dq2| {
             dq1.extend(dq2.iter().cloned());
             Ok(Val::Unit)
         })?;
@@ -35,9 +42,11 @@ fn concat(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val>
 }
 
 fn at(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
-    let (res, tail) = view2::<view::AnyRef<view::Deque>, view::Number, _, _>(args, |dq, idx| {
-        Ok(dq.try_get(*idx)?.clone())
-    })?;
+    let (res, tail) =
+        view2::<view::AnyRef<view::Bottom>, view::Number, _, _>(args, |v, idx| match v {
+            Val::Str(s) => Ok(Val::Char(view::char_at(s, *idx)?)),
+            _ => Ok(v.as_deque_mut()?.try_get(*idx)?.clone()),
+        })?;
     test_consumed(tail)?;
 
     Ok(res)
@@ -67,7 +76,7 @@ fn flatten(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val
         let mut wrapped = [v.clone()];
 
         let recurrence_res =
-            foreach::<view::AnyRef<view::Deque>, view::Bottom, _, _>(&mut wrapped, |v| {
+            foreach::<view::AnyRef<view::Items>, view::Bottom, _, _>(&mut wrapped, |v| {
                 flatten_impl(v, res);
                 Ok(())
             });
@@ -99,6 +108,329 @@ fn replace(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val
     Ok(res)
 }
 
+/// Values of an iterator, or items of a deque or string, in a new deque.
+fn collect(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([val], tail) = take_n::<1>(args)?;
+    let mut iter = for_expr::items(val)?;
+    test_consumed(tail)?;
+
+    let mut res = VecDeque::new();
+    while let Some(val) = iter.next(env)? {
+        res.push_back(val);
+    }
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Up to `n` values taken from the front of an iterator, deque or string, in a
+/// new deque. Iterators are left at the value after the last one taken.
+fn take(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([val, n], tail) = take_n::<2>(args)?;
+    let n = n.apply_to_root(|v| v.as_number().copied())??;
+    let mut iter = for_expr::items(val)?;
+    test_consumed(tail)?;
+
+    let mut res = VecDeque::new();
+    while res.len() < n.max(0) as usize {
+        match iter.next(env)? {
+            Some(val) => res.push_back(val),
+            None => break,
+        }
+    }
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Calls `func`, user-defined or builtin, for the builtin `name`.
+fn callback(name: &str, func: &Val, args: &mut [Val], env: &mut Env) -> Result<Val, Val> {
+    call::call_val(func, args, env, || TraceFrame {
+        name: name.to_string(),
+        span: None,
+    })
+}
+
+/// Whether the predicate `func` holds for `args`.
+fn holds(name: &str, func: &Val, args: &mut [Val], env: &mut Env) -> Result<bool, Val> {
+    let res = callback(name, func, args, env)?;
+    Ok(res.apply_to_root(|v| v.as_bool().copied())??)
+}
+
+/// Results of a function for every value of an iterator, deque or string.
+fn map(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq, func], tail) = take_n::<2>(args)?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    let mut res = VecDeque::new();
+    while let Some(val) = items.next(env)? {
+        res.push_back(callback("map", func, &mut [val], env)?);
+    }
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Values for which a predicate holds.
+fn filter(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq, func], tail) = take_n::<2>(args)?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    let mut res = VecDeque::new();
+    while let Some(val) = items.next(env)? {
+        if holds("filter", func, &mut [val.clone()], env)? {
+            res.push_back(val);
+        }
+    }
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Combines values from the front, starting from `init`, with a function
+/// taking the result so far and the next value.
+fn fold(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq, init, func], tail) = take_n::<3>(args)?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    let mut acc = init.clone();
+    while let Some(val) = items.next(env)? {
+        acc = callback("fold", func, &mut [acc, val], env)?;
+    }
+
+    Ok(acc)
+}
+
+fn any(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq, func], tail) = take_n::<2>(args)?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    while let Some(val) = items.next(env)? {
+        if holds("any", func, &mut [val], env)? {
+            return Ok(Val::Bool(true));
+        }
+    }
+
+    Ok(Val::Bool(false))
+}
+
+fn all(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq, func], tail) = take_n::<2>(args)?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    while let Some(val) = items.next(env)? {
+        if !holds("all", func, &mut [val], env)? {
+            return Ok(Val::Bool(false));
+        }
+    }
+
+    Ok(Val::Bool(true))
+}
+
+/// First value for which a predicate holds, `📦🧑‍🦲` if there's none.
+fn find(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq, func], tail) = take_n::<2>(args)?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    while let Some(val) = items.next(env)? {
+        if holds("find", func, &mut [val.clone()], env)? {
+            return Ok(val);
+        }
+    }
+
+    Ok(Val::Unit)
+}
+
+/// Natural order of values: numbers by value, characters and strings by
+/// codepoints, and objects by their `lt` member.
+fn natural_less(lhs: &Val, rhs: &Val, env: &mut Env) -> Result<bool, Val> {
+    let lhs = lhs.apply_to_root(Val::clone)?;
+    let rhs = rhs.apply_to_root(Val::clone)?;
+
+    match (&lhs, &rhs) {
+        (Val::Char(c1), Val::Char(c2)) => Ok(c1 < c2),
+        (Val::Str(s1), Val::Str(s2)) => Ok(s1 < s2),
+        _ => {
+            let res = Op::Less.eval(&lhs, &rhs, env)?;
+            Ok(res.apply_to_root(|v| v.as_bool().copied())??)
+        }
+    }
+}
+
+/// Stable merge sort which, unlike the one in `std`, stops at the first error
+/// and copes with comparators that aren't a total order.
+fn merge_sort(
+    mut vals: Vec<Val>,
+    less: &mut dyn FnMut(&Val, &Val) -> Result<bool, Val>,
+) -> Result<Vec<Val>, Val> {
+    if vals.len() <= 1 {
+        return Ok(vals);
+    }
+
+    let back = vals.split_off(vals.len() / 2);
+    let mut front = merge_sort(vals, less)?.into_iter().peekable();
+    let mut back = merge_sort(back, less)?.into_iter().peekable();
+
+    let mut merged = Vec::with_capacity(front.len() + back.len());
+    while let (Some(f), Some(b)) = (front.peek(), back.peek()) {
+        // ties are taken from the front half to keep the order stable.
+        let next = if less(b, f)? { &mut back } else { &mut front };
+        merged.extend(next.next());
+    }
+    merged.extend(front);
+    merged.extend(back);
+
+    Ok(merged)
+}
+
+/// Sorts a deque in place, in natural order or by a comparator telling
+/// whether its first argument goes before the second.
+fn sort(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([dq], tail) = take_n::<1>(args)?;
+    let (cmp, tail) = match tail.split_first_mut() {
+        Some((cmp, tail)) => (Some(cmp.clone()), tail),
+        None => (None, tail),
+    };
+    test_consumed(tail)?;
+
+    // comparators could look at the deque, so it's left as is until sorted.
+    let items = view::Ref::<view::Deque>::view(dq, |dq| Ok(dq.iter().cloned().collect()))?;
+    let sorted = merge_sort(items, &mut |lhs, rhs| match &cmp {
+        Some(cmp) => holds("sort", cmp, &mut [lhs.clone(), rhs.clone()], env),
+        None => natural_less(lhs, rhs, env),
+    })?;
+
+    let mut sorted = VecDeque::from(sorted);
+    view::Ref::<view::Deque>::view(dq, |dq| {
+        std::mem::swap(dq, &mut sorted);
+        Ok(())
+    })?;
+
+    Ok(Val::Unit)
+}
+
+fn reverse(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::Ref<view::Deque>, _, _>(args, |dq| {
+        dq.make_contiguous().reverse();
+        Ok(Val::Unit)
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// Part of a deque or string from `start` up to, but not including, `end`.
+/// Negative bounds count from the back.
+fn slice(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let bounds = |start: i32, end: i32, len: usize| -> Result<_, RuntimeError> {
+        let start = relative_bound(start, len)?;
+        let end = relative_bound(end, len)?;
+        Ok(start..end.max(start))
+    };
+
+    let (res, tail) = view3::<view::AnyRef<view::Bottom>, view::Number, view::Number, _, _>(
+        args,
+        |v, start, end| match v {
+            Val::Str(s) => {
+                let chars: Vec<_> = s.chars().collect();
+                let range = bounds(*start, *end, chars.len())?;
+                Ok(Val::from(chars[range].iter().collect::<String>().as_str()))
+            }
+            _ => {
+                let dq = v.as_deque()?;
+                let range = bounds(*start, *end, dq.len())?;
+                Ok(Val::Deque(Box::new(dq.range(range).cloned().collect())))
+            }
+        },
+    )?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// Inserts a value so that it ends up at the given index.
+fn insert(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) =
+        view3::<view::Ref<view::Deque>, view::Number, view::Bottom, _, _>(args, |dq, idx, val| {
+            dq.try_insert(*idx, val.clone())?;
+            Ok(Val::Unit)
+        })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn push_front(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<view::Ref<view::Deque>, view::Bottom, _, _>(args, |dq, new_val| {
+        dq.push_front(new_val.clone());
+        Ok(Val::Unit)
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn pop_front(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::Ref<view::Deque>, _, _>(args, |dq| dq.try_remove(0))?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn pop_back(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::Ref<view::Deque>, _, _>(args, |dq| dq.try_remove(-1))?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// Pairs of values at the same positions, as long as both have values left.
+fn zip(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq1, seq2], tail) = take_n::<2>(args)?;
+    test_consumed(tail)?;
+
+    let mut items1 = for_expr::items(seq1)?;
+    let mut items2 = for_expr::items(seq2)?;
+    let mut res = VecDeque::new();
+    while let Some(val1) = items1.next(env)? {
+        match items2.next(env)? {
+            Some(val2) => res.push_back(Val::Deque(Box::new(VecDeque::from([val1, val2])))),
+            None => break,
+        }
+    }
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Values paired with their indices, index first.
+fn enumerate(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq], tail) = take_n::<1>(args)?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    let mut res = VecDeque::new();
+    while let Some(val) = items.next(env)? {
+        let idx = Val::Number(res.len() as i32);
+        res.push_back(Val::Deque(Box::new(VecDeque::from([idx, val]))));
+    }
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Numbers from `start` up to, but not including, `end`.
+fn range(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<view::Number, view::Number, _, _>(args, |start, end| {
+        Ok(Val::Deque(Box::new(
+            (*start..*end).map(Val::Number).collect(),
+        )))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
 pub(crate) fn make_deque_builtin() -> RustObj {
     RustObj::new(
         "deque",
@@ -111,6 +443,24 @@ pub(crate) fn make_deque_builtin() -> RustObj {
             RustFn::new("remove", remove),
             RustFn::new("flatten", flatten),
             RustFn::new("replace", replace),
+            RustFn::new("collect", collect),
+            RustFn::new("take", take),
+            RustFn::new("map", map),
+            RustFn::new("filter", filter),
+            RustFn::new("fold", fold),
+            RustFn::new("any", any),
+            RustFn::new("all", all),
+            RustFn::new("find", find),
+            RustFn::new("sort", sort),
+            RustFn::new("reverse", reverse),
+            RustFn::new("slice", slice),
+            RustFn::new("insert", insert),
+            RustFn::new("pushFront", push_front),
+            RustFn::new("popFront", pop_front),
+            RustFn::new("popBack", pop_back),
+            RustFn::new("zip", zip),
+            RustFn::new("enumerate", enumerate),
+            RustFn::new("range", range),
         ],
     )
 }
@@ -120,6 +470,7 @@ mod tests {
     use super::*;
     use crate::error::RuntimeError;
     use crate::expr::Expr;
+    use crate::test_utils::eval_in;
     use std::cell::RefCell;
     use std::collections::VecDeque;
     use std::rc::Rc;
@@ -143,6 +494,10 @@ mod tests {
         Val::Deque(Box::new(vd))
     }
 
+    fn numbers(ns: &[i32]) -> Val {
+        Val::Deque(Box::new(ns.iter().copied().map(Val::Number).collect()))
+    }
+
     fn deque_test_env() -> Env {
         let mut env = Env::test();
         env.store_binding("d".to_string(), deque_123_val());
@@ -304,12 +659,54 @@ mod tests {
         let (_, remove_e) = Expr::new("📞 d_test🪆remove 🔖d 0 🧵extra_arg🧵").unwrap();
         let (_, flatten_e) = Expr::new("📞 d_test🪆flatten d 🧵extra_arg🧵").unwrap();
 
-        assert_eq!(env.eval(&len_e), Err(RuntimeError::WrongArgsN.into()));
-        assert_eq!(env.eval(&append_e), Err(RuntimeError::WrongArgsN.into()));
-        assert_eq!(env.eval(&at_e), Err(RuntimeError::WrongArgsN.into()));
-        assert_eq!(env.eval(&at_mut_e), Err(RuntimeError::WrongArgsN.into()));
-        assert_eq!(env.eval(&remove_e), Err(RuntimeError::WrongArgsN.into()));
-        assert_eq!(env.eval(&flatten_e), Err(RuntimeError::WrongArgsN.into()));
+        assert_eq!(
+            env.eval(&len_e),
+            Err(RuntimeError::WrongArgsN {
+                expected: 1,
+                received: 2,
+            }
+            .into())
+        );
+        assert_eq!(
+            env.eval(&append_e),
+            Err(RuntimeError::WrongArgsN {
+                expected: 2,
+                received: 3,
+            }
+            .into())
+        );
+        assert_eq!(
+            env.eval(&at_e),
+            Err(RuntimeError::WrongArgsN {
+                expected: 2,
+                received: 3,
+            }
+            .into())
+        );
+        assert_eq!(
+            env.eval(&at_mut_e),
+            Err(RuntimeError::WrongArgsN {
+                expected: 2,
+                received: 3,
+            }
+            .into())
+        );
+        assert_eq!(
+            env.eval(&remove_e),
+            Err(RuntimeError::WrongArgsN {
+                expected: 2,
+                received: 3,
+            }
+            .into())
+        );
+        assert_eq!(
+            env.eval(&flatten_e),
+            Err(RuntimeError::WrongArgsN {
+                expected: 1,
+                received: 2,
+            }
+            .into())
+        );
 
         let expected_dq = {
             let mut dq = VecDeque::new();
@@ -324,6 +721,51 @@ mod tests {
         assert_eq!(env.get_binding("d"), Ok(expected_val));
     }
 
+    #[test]
+    fn test_str() {
+        let mut env = deque_test_env();
+        env.store_binding("s".to_string(), Val::from("żółw"));
+
+        let cases = [
+            ("📞 d_test🪆len s", Val::Number(4)),
+            ("📞 d_test🪆len 🔖s", Val::Number(4)),
+            ("📞 d_test🪆at s 1", Val::Char('ó')),
+            ("📞 d_test🪆at 🔖s 📦0-1🧑‍🦲", Val::Char('w')),
+        ];
+        for (input, expected) in cases {
+            let (_, expr) = Expr::new(input).unwrap();
+            assert_eq!(env.eval(&expr), Ok(expected), "{}", input);
+        }
+
+        let (_, concat_e) = Expr::new("📞 d_test🪆concat 🔖d 🔖s").unwrap();
+        env.eval(&concat_e).unwrap();
+        assert_eq!(
+            env.get_binding("s").unwrap().apply_to_root(Val::clone),
+            Ok(Val::from("żółw"))
+        );
+
+        let (_, len_e) = Expr::new("📞 d_test🪆len d").unwrap();
+        assert_eq!(env.eval(&len_e), Ok(Val::Number(7)));
+    }
+
+    #[test]
+    fn test_str_becomes_deque() {
+        let mut env = deque_test_env();
+        let (_, append_e) =
+            Expr::new("📦 👶 s = 🧵ab🧵 💪 📞 d_test🪆append 🔖s 1 💪 📞 d_test🪆mut 🔖s 0 🧑‍🦲")
+                .unwrap();
+
+        let result = env.eval(&append_e);
+        assert_eq!(result, Ok(Val::Ref(Rc::new(RefCell::new(Val::Char('a'))))));
+
+        let (_, s_e) = Expr::new("📦 👶 s = 🧵ab🧵 💪 📞 d_test🪆append 🔖s 1 💪 s 🧑‍🦲").unwrap();
+        let expected = vec![Val::Char('a'), Val::Char('b'), Val::Number(1)];
+        assert_eq!(
+            env.eval(&s_e).unwrap().apply_to_root(Val::clone),
+            Ok(Val::Deque(Box::new(expected.into_iter().collect())))
+        );
+    }
+
     #[test]
     fn replace() {
         let mut env = deque_test_env();
@@ -343,4 +785,220 @@ mod tests {
         let expected_val = Val::Ref(Rc::new(RefCell::new(expected_dq_val)));
         assert_eq!(env.get_binding("d"), Ok(expected_val));
     }
+
+    #[test]
+    fn collect() {
+        let mut env = deque_test_env();
+        let (_, collect_e) = Expr::new(
+            "📦
+                👶 gen = 🧰 n ➡️ 🔂 i 📥 1 ⏩ n 🐣 i 🧑‍🦲 🧑‍🦲 🧑‍🦲 💪
+                👶 g = 📞 gen 5 💪
+                📞 d_test🪆collect g
+            🧑‍🦲",
+        )
+        .unwrap();
+        assert_eq!(env.eval(&collect_e), Ok(deque_1234_val()));
+
+        let (_, collect_e) = Expr::new("📞 d_test🪆collect 🔖d").unwrap();
+        assert_eq!(env.eval(&collect_e), Ok(deque_123_val()));
+    }
+
+    #[test]
+    fn take_lazily() {
+        let mut env = deque_test_env();
+        let (_, take_e) = Expr::new(
+            "📦
+                👶 naturals = 🧰 ➡️ 👶 i = 0 💪 🔁 ♻️ i = i + 1 💪 🐣 i 🧑‍🦲 🧑‍🦲 🧑‍🦲 💪
+                👶 g = 📞 naturals 💪
+                👶 first = 📞 d_test🪆take g 3 💪
+                📞 d_test🪆append 🔖first 📞 g🪆next 💪
+                first
+            🧑‍🦲",
+        )
+        .unwrap();
+        let result = env
+            .eval(&take_e)
+            .map(|v| v.apply_to_root(Val::clone).unwrap());
+        assert_eq!(result, Ok(deque_1234_val()));
+
+        let (_, take_e) = Expr::new("📞 d_test🪆take d 5").unwrap();
+        assert_eq!(env.eval(&take_e), Ok(deque_123_val()));
+    }
+
+    #[test]
+    fn map_filter_fold() {
+        let mut env = deque_test_env();
+
+        let res = eval_in(&mut env, "📞 d_test🪆map d 🧰 x ➡️ x * 2 🧑‍🦲");
+        assert_eq!(res, Ok(numbers(&[2, 4, 6])));
+
+        let res = eval_in(&mut env, "📞 d_test🪆filter d 🧰 x ➡️ x > 1 🧑‍🦲");
+        assert_eq!(res, Ok(numbers(&[2, 3])));
+
+        let res = eval_in(&mut env, "📞 d_test🪆fold d 10 🧰 acc x ➡️ acc + x 🧑‍🦲");
+        assert_eq!(res, Ok(Val::Number(16)));
+
+        let res = eval_in(
+            &mut env,
+            "📦
+                👶 nested = 📞 d_test🪆map d 🧰 n ➡️ 📞 d_test🪆range 0 n 🧑‍🦲 💪
+                📞 d_test🪆map nested d_test🪆len
+            🧑‍🦲",
+        );
+        assert_eq!(res, Ok(numbers(&[1, 2, 3])));
+
+        let res = eval_in(&mut env, "📞 d_test🪆filter d 🧰 x ➡️ x 🧑‍🦲");
+        let expected = RuntimeError::CastError {
+            from: "🔢".into(),
+            to: "😵‍💫".into(),
+        };
+        assert_eq!(res, Err(expected.into()));
+    }
+
+    #[test]
+    fn any_all_find() {
+        let mut env = deque_test_env();
+
+        let res = eval_in(&mut env, "📞 d_test🪆any d 🧰 x ➡️ x > 2 🧑‍🦲");
+        assert_eq!(res, Ok(Val::Bool(true)));
+
+        let res = eval_in(&mut env, "📞 d_test🪆all d 🧰 x ➡️ x > 2 🧑‍🦲");
+        assert_eq!(res, Ok(Val::Bool(false)));
+
+        let res = eval_in(&mut env, "📞 d_test🪆find d 🧰 x ➡️ x > 1 🧑‍🦲");
+        assert_eq!(res, Ok(Val::Number(2)));
+
+        let res = eval_in(&mut env, "📞 d_test🪆find d 🧰 x ➡️ x > 5 🧑‍🦲");
+        assert_eq!(res, Ok(Val::Unit));
+    }
+
+    #[test]
+    fn sort() {
+        let mut env = deque_test_env();
+
+        let res = eval_in(
+            &mut env,
+            "📦
+                👶 s = 📞 d_test🪆range 0 5 💪
+                📞 d_test🪆reverse 🔖s 💪
+                📞 d_test🪆sort 🔖s 💪
+                s
+            🧑‍🦲",
+        );
+        assert_eq!(res, Ok(numbers(&[0, 1, 2, 3, 4])));
+
+        eval_in(&mut env, "📞 d_test🪆sort 🔖d 🧰 a b ➡️ a > b 🧑‍🦲").unwrap();
+        assert_eq!(eval_in(&mut env, "d"), Ok(numbers(&[3, 2, 1])));
+
+        let res = eval_in(
+            &mut env,
+            "📦
+                👶 s = 🧵cab🧵 💪
+                📞 d_test🪆sort 🔖s 💪
+                s
+            🧑‍🦲",
+        );
+        let expected = ['a', 'b', 'c'].into_iter().map(Val::Char).collect();
+        assert_eq!(res, Ok(Val::Deque(Box::new(expected))));
+
+        // pairs equal by the comparator keep their order.
+        let res = eval_in(
+            &mut env,
+            "📦
+                👶 s = 📞 d_test🪆enumerate 🧵bab🧵 💪
+                📞 d_test🪆sort 🔖s 🧰 a b ➡️
+                    👶 ca = 📞 d_test🪆at a 1 💪
+                    👶 cb = 📞 d_test🪆at b 1 💪
+                    ❓ ca == cb 🙅‍♀️ 🧑‍🦲 😡 ca == 🔡a🔡 🧑‍🦲
+                🧑‍🦲 💪
+                📞 d_test🪆map s 🧰 p ➡️ 📞 d_test🪆at p 0 🧑‍🦲
+            🧑‍🦲",
+        );
+        assert_eq!(res, Ok(numbers(&[1, 0, 2])));
+
+        let res = eval_in(
+            &mut env,
+            "📦 👶 s = 🧵ab🧵 💪 📞 d_test🪆append 🔖s 1 💪 📞 d_test🪆sort 🔖s 🧑‍🦲",
+        );
+        let expected = RuntimeError::InvalidOp {
+            lhs: "🔢".into(),
+            op: "<".into(),
+            rhs: "🔡".into(),
+        };
+        assert_eq!(res, Err(expected.into()));
+    }
+
+    #[test]
+    fn reverse_slice_insert() {
+        let mut env = deque_test_env();
+
+        eval_in(&mut env, "📞 d_test🪆reverse 🔖d").unwrap();
+        assert_eq!(eval_in(&mut env, "d"), Ok(numbers(&[3, 2, 1])));
+
+        let res = eval_in(&mut env, "📞 d_test🪆slice d 1 3");
+        assert_eq!(res, Ok(numbers(&[2, 1])));
+
+        let res = eval_in(&mut env, "📞 d_test🪆slice 🧵żółw🧵 📦0-3🧑‍🦲 📦0-1🧑‍🦲");
+        assert_eq!(res, Ok(Val::from("ół")));
+
+        let res = eval_in(&mut env, "📞 d_test🪆slice d 2 1");
+        assert_eq!(res, Ok(numbers(&[])));
+
+        let res = eval_in(&mut env, "📞 d_test🪆slice d 0 4");
+        let expected = RuntimeError::OutOfBounds { idx: 4, len: 3 };
+        assert_eq!(res, Err(expected.into()));
+
+        eval_in(&mut env, "📞 d_test🪆insert 🔖d 3 0").unwrap();
+        eval_in(&mut env, "📞 d_test🪆insert 🔖d 📦0-4🧑‍🦲 4").unwrap();
+        assert_eq!(eval_in(&mut env, "d"), Ok(numbers(&[4, 3, 2, 1, 0])));
+    }
+
+    #[test]
+    fn push_pop() {
+        let mut env = deque_test_env();
+
+        eval_in(&mut env, "📞 d_test🪆pushFront 🔖d 0").unwrap();
+        assert_eq!(
+            eval_in(&mut env, "📞 d_test🪆popBack 🔖d"),
+            Ok(Val::Number(3))
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 d_test🪆popFront 🔖d"),
+            Ok(Val::Number(0))
+        );
+        assert_eq!(eval_in(&mut env, "d"), Ok(numbers(&[1, 2])));
+
+        let res = eval_in(&mut env, "📦 👶 e = 🧵🧵 💪 📞 d_test🪆popFront 🔖e 🧑‍🦲");
+        let expected = RuntimeError::OutOfBounds { idx: 0, len: 0 };
+        assert_eq!(res, Err(expected.into()));
+    }
+
+    #[test]
+    fn zip_enumerate_range() {
+        let mut env = deque_test_env();
+        let pair = |a, b| Val::Deque(Box::new(VecDeque::from([a, b])));
+
+        let res = eval_in(&mut env, "📞 d_test🪆zip d 🧵ab🧵");
+        let expected = [
+            pair(Val::Number(1), Val::Char('a')),
+            pair(Val::Number(2), Val::Char('b')),
+        ];
+        assert_eq!(
+            res,
+            Ok(Val::Deque(Box::new(expected.into_iter().collect())))
+        );
+
+        let res = eval_in(&mut env, "📞 d_test🪆enumerate 🧵ab🧵");
+        let expected = [
+            pair(Val::Number(0), Val::Char('a')),
+            pair(Val::Number(1), Val::Char('b')),
+        ];
+        assert_eq!(
+            res,
+            Ok(Val::Deque(Box::new(expected.into_iter().collect())))
+        );
+
+        let res = eval_in(&mut env, "📞 d_test🪆range 📦0-2🧑‍🦲 2");
+        assert_eq!(res, Ok(numbers(&[-2, -1, 0, 1])));
+    }
 }
@@ -2,12 +2,13 @@ use crate::builtins::objects::rustobj::RustObj;
 use crate::builtins::rustfn::{FnState, RustFn};
 use crate::env::Env;
 use crate::error::RuntimeError;
+use crate::expr::for_expr::{done, NEXT};
 use crate::val::view::{self, test_consumed, view1};
 use crate::val::Val;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::rc::Rc;
 
 #[derive(Clone, Debug)]
@@ -17,13 +18,7 @@ pub struct FileState {
 }
 
 fn open(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
-    let (fname, tail) = view1::<view::AnyRef<view::Deque>, _, _>(args, |dq| {
-        let string = dq
-            .iter()
-            .map(|v| v.as_char().map(|c| *c))
-            .collect::<Result<String, _>>()?;
-        Ok(string)
-    })?;
+    let (fname, tail) = view1::<view::AnyRef<view::String>, _, _>(args, |s| Ok(s.clone()))?;
     test_consumed(tail)?;
 
     let file = File::open(&fname).map_err(|e| RuntimeError::IoError {
@@ -63,6 +58,52 @@ fn read(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
     Ok(Val::from(buf.as_ref()))
 }
 
+/// Iterator over the lines of a file, reading each one as it's needed.
+fn lines(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
+    let (id, tail) = view1::<view::Number, _, _>(args, |n| Ok(*n))?;
+    test_consumed(tail)?;
+
+    let borrow = state.0.borrow();
+    let fstate: &FileState = borrow.downcast_ref::<FileState>().unwrap();
+
+    let files = fstate.files.borrow();
+    let file = files.get(&id).ok_or(RuntimeError::NoHandle(id))?;
+    let reader = file.try_clone().map_err(|e| RuntimeError::IoError {
+        file: format!("handle({})", id),
+        reason: e.to_string(),
+    })?;
+
+    let line_state = Rc::new(RefCell::new((id, BufReader::new(reader))));
+    let lines = RustObj::new(
+        "lines",
+        vec![RustFn::stateful(NEXT, next_line, &line_state)],
+    );
+
+    Ok(Val::from_obj(lines))
+}
+
+fn next_line(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
+    test_consumed(args)?;
+
+    let mut borrow = state.0.borrow_mut();
+    let (id, reader) = borrow.downcast_mut::<(i32, BufReader<File>)>().unwrap();
+
+    let mut line = String::new();
+    let read = reader
+        .read_line(&mut line)
+        .map_err(|e| RuntimeError::IoError {
+            file: format!("handle({})", id),
+            reason: e.to_string(),
+        })?;
+    if read == 0 {
+        return Ok(done());
+    }
+
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    Ok(Val::from(line))
+}
+
 pub(crate) fn make_file_builtin() -> RustObj {
     let files = Rc::new(RefCell::new(Default::default()));
     let state = Rc::new(RefCell::new(FileState { files, cnt: 0 }));
@@ -72,6 +113,41 @@ pub(crate) fn make_file_builtin() -> RustObj {
         vec![
             RustFn::stateful("open", open, &state),
             RustFn::stateful("read", read, &state),
+            RustFn::stateful("lines", lines, &state),
         ],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expr;
+
+    #[test]
+    fn lines_read_lazily() {
+        let path = std::env::temp_dir().join("lmang-file-lines.txt");
+        std::fs::write(&path, "first\r\nsecond\n\nlast").unwrap();
+
+        let mut env = Env::test();
+        env.store_binding("file".to_string(), Val::from_obj(make_file_builtin()));
+        env.store_binding("path".to_string(), Val::from(path.to_str().unwrap()));
+
+        let input = "📦
+                👶 lines = 📞 file🪆lines 📞 file🪆open path 💪
+                👶 n = 0 💪
+                🔂 line 📥 lines ♻️ n = n + 1 🧑‍🦲 💪
+                n
+            🧑‍🦲";
+        let (_, expr) = Expr::new(input).unwrap();
+        assert_eq!(env.eval(&expr), Ok(Val::Number(4)));
+
+        let input = "📦
+                👶 lines = 📞 file🪆lines 📞 file🪆open path 💪
+                👶 first = 📞 lines🪆next 💪
+                👶 second = 📞 lines🪆next 💪
+                ❓ first == 🧵first🧵 second 🧑‍🦲 😡 first 🧑‍🦲
+            🧑‍🦲";
+        let (_, expr) = Expr::new(input).unwrap();
+        assert_eq!(env.eval(&expr), Ok(Val::from("second")));
+    }
+}
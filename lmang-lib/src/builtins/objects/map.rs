@@ -0,0 +1,202 @@
+use crate::builtins::objects::rustobj::RustObj;
+use crate::builtins::rustfn::{FnState, RustFn};
+use crate::env::Env;
+use crate::error::RuntimeError;
+use crate::val::view::{self, test_consumed, view1, view2, view3};
+use crate::val::{sorted_entries, Key, Val};
+use std::collections::VecDeque;
+
+fn len(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (val, tail) =
+        view1::<view::AnyRef<view::Map>, _, _>(args, |map| Ok(Val::Number(map.len() as i32)))?;
+    test_consumed(tail)?;
+
+    Ok(val)
+}
+
+fn get(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<view::AnyRef<view::Map>, view::Bottom, _, _>(args, |map, key| {
+        let key = Key::try_from(&*key)?;
+        match map.get(&key) {
+            Some(val) => Ok(val.clone()),
+            None => Err(RuntimeError::NoKey(key.to_string())),
+        }
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn set(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) =
+        view3::<view::Ref<view::Map>, view::Bottom, view::Bottom, _, _>(args, |map, key, val| {
+            map.insert(Key::try_from(&*key)?, val.clone());
+            Ok(Val::Unit)
+        })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn remove(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<view::Ref<view::Map>, view::Bottom, _, _>(args, |map, key| {
+        let key = Key::try_from(&*key)?;
+        match map.remove(&key) {
+            Some(val) => Ok(val),
+            None => Err(RuntimeError::NoKey(key.to_string())),
+        }
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn has(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<view::AnyRef<view::Map>, view::Bottom, _, _>(args, |map, key| {
+        Ok(Val::Bool(map.contains_key(&Key::try_from(&*key)?)))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn keys(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Map>, _, _>(args, |map| {
+        let keys = sorted_entries(map)
+            .into_iter()
+            .map(|(k, _)| Val::from(k.clone()))
+            .collect();
+        Ok(Val::Deque(Box::new(keys)))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn values(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Map>, _, _>(args, |map| {
+        let values = sorted_entries(map)
+            .into_iter()
+            .map(|(_, v)| v.clone())
+            .collect();
+        Ok(Val::Deque(Box::new(values)))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// Deque of key-value pairs, ordered by key.
+fn entries(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Map>, _, _>(args, |map| {
+        let entries = sorted_entries(map)
+            .into_iter()
+            .map(|(k, v)| {
+                let pair: VecDeque<_> = [Val::from(k.clone()), v.clone()].into();
+                Val::Deque(Box::new(pair))
+            })
+            .collect();
+        Ok(Val::Deque(Box::new(entries)))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+pub(crate) fn make_map_builtin() -> RustObj {
+    RustObj::new(
+        "map",
+        vec![
+            RustFn::new("len", len),
+            RustFn::new("get", get),
+            RustFn::new("set", set),
+            RustFn::new("remove", remove),
+            RustFn::new("has", has),
+            RustFn::new("keys", keys),
+            RustFn::new("values", values),
+            RustFn::new("entries", entries),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expr;
+    use crate::test_utils::eval_in;
+
+    fn map_test_env() -> Env {
+        let mut env = Env::test();
+        env.store_binding("m_test".to_string(), Val::from_obj(make_map_builtin()));
+        let (_, map_e) = Expr::new("👶 m = 🗺️ 🧵b🧵 ➡️ 2 💪 🧵a🧵 ➡️ 1 🧑‍🦲").unwrap();
+        env.eval(&map_e).unwrap();
+
+        env
+    }
+
+    #[test]
+    fn test_read() {
+        let mut env = map_test_env();
+        let ab = |a, b| {
+            let dq: VecDeque<_> = [a, b].into();
+            Ok(Val::Deque(Box::new(dq)))
+        };
+        let pair = |k: &str, v| {
+            let dq: VecDeque<_> = [Val::from(k), Val::Number(v)].into();
+            Val::Deque(Box::new(dq))
+        };
+
+        let cases = [
+            ("📞 m_test🪆len m", Ok(Val::Number(2))),
+            ("📞 m_test🪆len 🔖m", Ok(Val::Number(2))),
+            ("📞 m_test🪆get m 🧵a🧵", Ok(Val::Number(1))),
+            ("📞 m_test🪆has m 🧵b🧵", Ok(Val::Bool(true))),
+            ("📞 m_test🪆has m 🔡b🔡", Ok(Val::Bool(false))),
+            ("📞 m_test🪆keys m", ab(Val::from("a"), Val::from("b"))),
+            ("📞 m_test🪆values m", ab(Val::Number(1), Val::Number(2))),
+            ("📞 m_test🪆entries m", ab(pair("a", 1), pair("b", 2))),
+            (
+                "📞 m_test🪆get m 🧵c🧵",
+                Err(RuntimeError::NoKey("c".into()).into()),
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(eval_in(&mut env, input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let mut env = map_test_env();
+
+        let result = eval_in(&mut env, "📞 m_test🪆set m 3 4");
+        let expected = RuntimeError::CastError {
+            from: "🗺️".into(),
+            to: "🔖".into(),
+        };
+        assert_eq!(result, Err(expected.into()));
+
+        assert_eq!(eval_in(&mut env, "📞 m_test🪆set 🔖m 3 4"), Ok(Val::Unit));
+        assert_eq!(
+            eval_in(&mut env, "📞 m_test🪆set 🔖m 🧵a🧵 5"),
+            Ok(Val::Unit)
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 m_test🪆remove 🔖m 🧵b🧵"),
+            Ok(Val::Number(2))
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 m_test🪆remove 🔖m 🧵b🧵"),
+            Err(RuntimeError::NoKey("b".into()).into())
+        );
+
+        let mut expected = crate::val::Map::default();
+        expected.insert(Key::Str("a".into()), Val::Number(5));
+        expected.insert(Key::Number(3), Val::Number(4));
+        assert_eq!(
+            env.get_binding("m").unwrap().apply_to_root(Val::clone),
+            Ok(Val::Map(Box::new(expected)))
+        );
+    }
+}
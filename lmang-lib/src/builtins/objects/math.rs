@@ -0,0 +1,431 @@
+use crate::builtins::objects::rustobj::RustObj;
+use crate::builtins::rustfn::{FnState, RustFn};
+use crate::env::Env;
+use crate::error::RuntimeError;
+use crate::utils::kwords;
+use crate::val::view::{self, take_n, test_consumed, view1, view2, View as _};
+use crate::val::Val;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Number = view::AnyRef<view::Number>;
+type Float = view::AnyRef<view::Float>;
+
+type IntOp = fn(i32, i32) -> Result<i32, RuntimeError>;
+type FloatFn = fn(f64) -> f64;
+
+/// Arguments of a function on both integers and floats, promoted to floats
+/// unless all of them are integers.
+enum Numbers<const N: usize> {
+    Ints([i32; N]),
+    Floats([f64; N]),
+}
+
+fn numbers<const N: usize>(args: &mut [Val]) -> Result<Numbers<N>, RuntimeError> {
+    let (vals, tail) = take_n::<N>(args)?;
+    test_consumed(tail)?;
+
+    let mut ints = [0; N];
+    let mut floats = [0.0; N];
+    let mut all_ints = true;
+    for (i, val) in vals.iter_mut().enumerate() {
+        view::AnyRef::<view::Bottom>::view(val, |v| {
+            match v {
+                Val::Number(n) => ints[i] = *n,
+                _ => all_ints = false,
+            }
+            floats[i] = v.to_f64()?;
+
+            Ok(())
+        })?;
+    }
+
+    Ok(if all_ints {
+        Numbers::Ints(ints)
+    } else {
+        Numbers::Floats(floats)
+    })
+}
+
+fn abs(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    Ok(match numbers::<1>(args)? {
+        Numbers::Ints([n]) => Val::Number(n.checked_abs().ok_or(RuntimeError::Overflow)?),
+        Numbers::Floats([x]) => Val::Float(x.abs()),
+    })
+}
+
+fn min(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    Ok(match numbers::<2>(args)? {
+        Numbers::Ints([a, b]) => Val::Number(a.min(b)),
+        Numbers::Floats([a, b]) => Val::Float(a.min(b)),
+    })
+}
+
+fn max(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    Ok(match numbers::<2>(args)? {
+        Numbers::Ints([a, b]) => Val::Number(a.max(b)),
+        Numbers::Floats([a, b]) => Val::Float(a.max(b)),
+    })
+}
+
+/// Limits a number to a range, the upper bound winning if they cross.
+fn clamp(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    Ok(match numbers::<3>(args)? {
+        Numbers::Ints([n, lo, hi]) => Val::Number(n.max(lo).min(hi)),
+        Numbers::Floats([x, lo, hi]) => Val::Float(x.max(lo).min(hi)),
+    })
+}
+
+/// Integer powers of integers stay integers, negative ones being floats.
+fn pow(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    Ok(match numbers::<2>(args)? {
+        Numbers::Ints([base, exp]) if exp >= 0 => {
+            Val::Number(base.checked_pow(exp as u32).ok_or(RuntimeError::Overflow)?)
+        }
+        Numbers::Ints([base, exp]) => Val::Float((base as f64).powi(exp)),
+        Numbers::Floats([base, exp]) => Val::Float(base.powf(exp)),
+    })
+}
+
+/// Applies the operation in the state to two integers.
+fn int_op(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<Number, Number, _, _>(args, |a, b| {
+        let op = *state.0.borrow().downcast_ref::<IntOp>().unwrap();
+        op(*a, *b)
+    })?;
+    test_consumed(tail)?;
+
+    Ok(Val::Number(res))
+}
+
+fn gcd(a: i32, b: i32) -> Result<i32, RuntimeError> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    i32::try_from(a).map_err(|_| RuntimeError::Overflow)
+}
+
+fn divisor(b: i32) -> Result<i32, RuntimeError> {
+    match b {
+        0 => Err(RuntimeError::DivisionByZero),
+        b => Ok(b),
+    }
+}
+
+/// Division rounding towards negative infinity.
+fn div_floor(a: i32, b: i32) -> Result<i32, RuntimeError> {
+    let q = a.checked_div(divisor(b)?).ok_or(RuntimeError::Overflow)?;
+    Ok(if a % b != 0 && (a < 0) != (b < 0) {
+        q - 1
+    } else {
+        q
+    })
+}
+
+/// Remainder of `div_floor`, with the sign of the divisor.
+fn mod_floor(a: i32, b: i32) -> Result<i32, RuntimeError> {
+    let r = a.checked_rem(divisor(b)?).ok_or(RuntimeError::Overflow)?;
+    Ok(if r != 0 && (r < 0) != (b < 0) {
+        r + b
+    } else {
+        r
+    })
+}
+
+fn div_euclid(a: i32, b: i32) -> Result<i32, RuntimeError> {
+    a.checked_div_euclid(divisor(b)?)
+        .ok_or(RuntimeError::Overflow)
+}
+
+/// Remainder of `div_euclid`, never negative.
+fn rem_euclid(a: i32, b: i32) -> Result<i32, RuntimeError> {
+    a.checked_rem_euclid(divisor(b)?)
+        .ok_or(RuntimeError::Overflow)
+}
+
+/// Shift amount, which has to be less than the number of bits. Others fail
+/// to cast to the range of valid amounts.
+fn shift(n: i32) -> Result<u32, RuntimeError> {
+    u32::try_from(n)
+        .ok()
+        .filter(|&n| n < i32::BITS)
+        .ok_or_else(|| RuntimeError::CastError {
+            from: n.to_string(),
+            to: format!("0{}{}", kwords::RANGE, i32::BITS),
+        })
+}
+
+fn bit_not(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<Number, _, _>(args, |n| Ok(!*n))?;
+    test_consumed(tail)?;
+
+    Ok(Val::Number(res))
+}
+
+/// Applies the function in the state to a number as a float.
+fn float_fn(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<Float, _, _>(args, |x| {
+        let f = *state.0.borrow().downcast_ref::<FloatFn>().unwrap();
+        Ok(f(*x))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(Val::Float(res))
+}
+
+/// Angle of the point `(x, y)`, taking `y` first.
+fn atan2(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<Float, Float, _, _>(args, |y, x| Ok(y.atan2(*x)))?;
+    test_consumed(tail)?;
+
+    Ok(Val::Float(res))
+}
+
+const INT_OPS: [(&str, IntOp); 11] = [
+    ("gcd", gcd),
+    ("divFloor", div_floor),
+    ("modFloor", mod_floor),
+    ("divEuclid", div_euclid),
+    ("remEuclid", rem_euclid),
+    ("bitAnd", |a, b| Ok(a & b)),
+    ("bitOr", |a, b| Ok(a | b)),
+    ("bitXor", |a, b| Ok(a ^ b)),
+    ("shl", |a, b| Ok(a << shift(b)?)),
+    ("shr", |a, b| Ok(a >> shift(b)?)),
+    ("shrUnsigned", |a, b| Ok(((a as u32) >> shift(b)?) as i32)),
+];
+
+const FLOAT_FNS: [(&str, FloatFn); 11] = [
+    ("sqrt", f64::sqrt),
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+    ("floor", f64::floor),
+    ("ceil", f64::ceil),
+    ("round", f64::round),
+    ("trunc", f64::trunc),
+];
+
+pub(crate) fn make_math_builtin() -> RustObj {
+    let int_ops = INT_OPS
+        .into_iter()
+        .map(|(name, op)| RustFn::stateful(name, int_op, &Rc::new(RefCell::new(op))));
+    let float_fns = FLOAT_FNS
+        .into_iter()
+        .map(|(name, f)| RustFn::stateful(name, float_fn, &Rc::new(RefCell::new(f))));
+
+    RustObj::new(
+        "math",
+        vec![
+            RustFn::new("abs", abs),
+            RustFn::new("min", min),
+            RustFn::new("max", max),
+            RustFn::new("clamp", clamp),
+            RustFn::new("pow", pow),
+            RustFn::new("bitNot", bit_not),
+            RustFn::new("atan2", atan2),
+        ]
+        .into_iter()
+        .chain(int_ops)
+        .chain(float_fns)
+        .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::eval_in;
+
+    /// Test environment with `math` and a number `n`.
+    fn env() -> Env {
+        let mut env = Env::test();
+        env.store_binding("math".to_string(), Val::from_obj(make_math_builtin()));
+        env.store_binding("n".to_string(), Val::Number(-7));
+
+        env
+    }
+
+    #[test]
+    fn ints_and_floats() {
+        assert_eq!(eval_in(&mut env(), "📞 math🪆abs n"), Ok(Val::Number(7)));
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆abs 📦0-1.5🧑‍🦲"),
+            Ok(Val::Float(1.5))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆min 🔖n 3"),
+            Ok(Val::Number(-7))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆max 2 2.5"),
+            Ok(Val::Float(2.5))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆clamp 12 0 10"),
+            Ok(Val::Number(10))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆clamp n 0.5 10"),
+            Ok(Val::Float(0.5))
+        );
+        assert_eq!(eval_in(&mut env(), "📞 math🪆pow 3 4"), Ok(Val::Number(81)));
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆pow 2 📦0-1🧑‍🦲"),
+            Ok(Val::Float(0.5))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆pow 4 0.5"),
+            Ok(Val::Float(2.0))
+        );
+
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆pow 2 31"),
+            Err(RuntimeError::Overflow.into())
+        );
+    }
+
+    #[test]
+    fn division() {
+        let cases = [
+            ("divFloor", -3),
+            ("modFloor", 2),
+            ("divEuclid", -3),
+            ("remEuclid", 2),
+        ];
+        for (func, expected) in cases {
+            let res = eval_in(&mut env(), &format!("📞 math🪆{} n 3", func));
+            assert_eq!(res, Ok(Val::Number(expected)), "{}", func);
+        }
+        let cases = [
+            ("divFloor", 2),
+            ("modFloor", -1),
+            ("divEuclid", 3),
+            ("remEuclid", 2),
+        ];
+        for (func, expected) in cases {
+            let res = eval_in(&mut env(), &format!("📞 math🪆{} n 📦0-3🧑‍🦲", func));
+            assert_eq!(res, Ok(Val::Number(expected)), "{}", func);
+        }
+
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆gcd 12 📦0-18🧑‍🦲"),
+            Ok(Val::Number(6))
+        );
+        assert_eq!(eval_in(&mut env(), "📞 math🪆gcd 0 0"), Ok(Val::Number(0)));
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆remEuclid 1 0"),
+            Err(RuntimeError::DivisionByZero.into())
+        );
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆bitAnd 12 10"),
+            Ok(Val::Number(8))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆bitOr 12 10"),
+            Ok(Val::Number(14))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆bitXor 12 10"),
+            Ok(Val::Number(6))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆bitNot 0"),
+            Ok(Val::Number(-1))
+        );
+        assert_eq!(eval_in(&mut env(), "📞 math🪆shl 1 4"), Ok(Val::Number(16)));
+        assert_eq!(eval_in(&mut env(), "📞 math🪆shr n 1"), Ok(Val::Number(-4)));
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆shrUnsigned n 28"),
+            Ok(Val::Number(15))
+        );
+
+        let expected = RuntimeError::CastError {
+            from: "32".into(),
+            to: "0⏩32".into(),
+        };
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆shl 1 32"),
+            Err(expected.into())
+        );
+        let expected = RuntimeError::CastError {
+            from: "-1".into(),
+            to: "0⏩32".into(),
+        };
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆shr n 📦 - 1 🧑‍🦲"),
+            Err(expected.into())
+        );
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(eval_in(&mut env(), "📞 math🪆sqrt 16"), Ok(Val::Float(4.0)));
+        assert_eq!(eval_in(&mut env(), "📞 math🪆cos 0"), Ok(Val::Float(1.0)));
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆atan2 1 1"),
+            Ok(Val::Float(1f64.atan()))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆floor 📦0-2.5🧑‍🦲"),
+            Ok(Val::Float(-3.0))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆round 2.5"),
+            Ok(Val::Float(3.0))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆trunc n"),
+            Ok(Val::Float(-7.0))
+        );
+    }
+
+    #[test]
+    fn wrong_args() {
+        let expected = RuntimeError::WrongArgsN {
+            expected: 2,
+            received: 1,
+        };
+        assert_eq!(eval_in(&mut env(), "📞 math🪆min 1"), Err(expected.into()));
+
+        let expected = RuntimeError::WrongArgsN {
+            expected: 1,
+            received: 2,
+        };
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆sqrt 1 2"),
+            Err(expected.into())
+        );
+
+        let expected = RuntimeError::CastError {
+            from: "🔡".into(),
+            to: "🔢".into(),
+        };
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆abs 🔡a🔡"),
+            Err(expected.clone().into())
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆sin 🔡a🔡"),
+            Err(expected.into())
+        );
+
+        let expected = RuntimeError::CastError {
+            from: "🛟".into(),
+            to: "🔢".into(),
+        };
+        assert_eq!(
+            eval_in(&mut env(), "📞 math🪆gcd 1.5 2"),
+            Err(expected.into())
+        );
+    }
+}
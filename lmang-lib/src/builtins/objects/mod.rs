@@ -1,7 +1,10 @@
 mod deque;
 mod file;
+mod map;
+mod math;
 mod rng;
 mod rustobj;
+mod string;
 mod sys;
 mod types;
 
@@ -9,15 +12,21 @@ mod types;
 mod web;
 
 use crate::env::{Env, Eval};
+use crate::expr::for_expr;
 use crate::val::Val;
 use std::cell::RefCell;
 
 use deque::make_deque_builtin;
 use file::make_file_builtin;
+use map::make_map_builtin;
+use math::make_math_builtin;
 use rng::make_rng_builtin;
+use string::make_string_builtin;
 use sys::make_sys_builtin;
 use types::make_types_builtin;
 
+pub(crate) use rustobj::RustObj;
+
 #[cfg(feature = "web")]
 pub use web::make_web_builtin;
 
@@ -38,11 +47,15 @@ impl Eval for BuiltinObjects {
         env.store_binding("file".to_string(), Val::from_obj(make_file_builtin()));
         env.store_binding("rng".to_string(), Val::from_obj(make_rng_builtin()));
         env.store_binding("deque".to_string(), Val::from_obj(make_deque_builtin()));
+        env.store_binding("map".to_string(), Val::from_obj(make_map_builtin()));
+        env.store_binding("math".to_string(), Val::from_obj(make_math_builtin()));
+        env.store_binding("string".to_string(), Val::from_obj(make_string_builtin()));
         env.store_binding(
             "sys".to_string(),
             Val::from_obj(make_sys_builtin(self.args.borrow_mut().take().unwrap())),
         );
         env.store_binding("types".to_string(), Val::from_obj(make_types_builtin()));
+        env.store_binding(for_expr::DONE.to_string(), for_expr::done());
 
         #[cfg(feature = "web")]
         #[cfg(target_arch = "wasm32")]
@@ -1,18 +1,24 @@
 use crate::builtins::rustfn::RustFn;
 use crate::error::RuntimeError;
 use crate::val::{Object, Val};
+use std::any::Any;
 use std::fmt;
+use std::rc::Rc;
 
+/// Object with builtin methods. Cheap to clone, as its members are shared.
 #[derive(Clone, Debug)]
 pub struct RustObj {
-    name: String,
-    funcs: Vec<RustFn>,
+    name: Rc<str>,
+    funcs: Rc<[RustFn]>,
 }
 
 impl RustObj {
     pub fn new(name: impl Into<String>, funcs: Vec<RustFn>) -> Self {
-        let name = name.into();
-        RustObj { name, funcs }
+        let name: String = name.into();
+        RustObj {
+            name: name.into(),
+            funcs: funcs.into(),
+        }
     }
 }
 
@@ -42,4 +48,8 @@ impl Object for RustObj {
     fn name(&self) -> &str {
         &self.name
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
@@ -0,0 +1,366 @@
+use crate::builtins::objects::rustobj::RustObj;
+use crate::builtins::rustfn::{FnState, RustFn};
+use crate::env::Env;
+use crate::error::RuntimeError;
+use crate::expr::class::show;
+use crate::expr::for_expr;
+use crate::val::view::{self, take_n, test_consumed, view1, view2, view3, View as _};
+use crate::val::Val;
+use std::collections::VecDeque;
+
+type Str = view::AnyRef<view::String>;
+
+fn strings<'a>(parts: impl Iterator<Item = &'a str>) -> Val {
+    Val::Deque(Box::new(parts.map(Val::from).collect()))
+}
+
+/// Parts of a string between occurrences of a separator, or between runs of
+/// whitespace if there's no separator.
+fn split(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (s, tail) = view1::<Str, _, _>(args, |s| Ok(s.clone()))?;
+    let (sep, tail) = match tail.split_first_mut() {
+        Some((sep, tail)) => (
+            Some(view::AnyRef::<view::String>::view(sep, |s| Ok(s.clone()))?),
+            tail,
+        ),
+        None => (None, tail),
+    };
+    test_consumed(tail)?;
+
+    match sep {
+        Some(sep) if !sep.is_empty() => Ok(strings(s.split(sep.as_str()))),
+        _ => Ok(strings(s.split_whitespace())),
+    }
+}
+
+/// Values of an iterator, deque or string, shown one after another with a
+/// separator in between.
+fn join(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([seq], tail) = take_n::<1>(args)?;
+    let (sep, tail) = view1::<Str, _, _>(tail, |s| Ok(s.clone()))?;
+    test_consumed(tail)?;
+
+    let mut items = for_expr::items(seq)?;
+    let mut res = String::new();
+    let mut first = true;
+    while let Some(val) = items.next(env)? {
+        if !first {
+            res.push_str(&sep);
+        }
+        first = false;
+        res.push_str(&show(&val, env)?);
+    }
+
+    Ok(Val::from(res.as_str()))
+}
+
+fn trim(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<Str, _, _>(args, |s| Ok(Val::from(s.trim())))?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// Index of the first character of the first occurrence of a pattern,
+/// `📦🧑‍🦲` if there's none.
+fn find(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<Str, Str, _, _>(args, |s, pat| {
+        Ok(match s.find(pat.as_str()) {
+            Some(byte_idx) => Val::Number(s[..byte_idx].chars().count() as i32),
+            None => Val::Unit,
+        })
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn starts_with(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<Str, Str, _, _>(args, |s, pat| Ok(s.starts_with(pat.as_str())))?;
+    test_consumed(tail)?;
+
+    Ok(Val::Bool(res))
+}
+
+fn ends_with(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<Str, Str, _, _>(args, |s, pat| Ok(s.ends_with(pat.as_str())))?;
+    test_consumed(tail)?;
+
+    Ok(Val::Bool(res))
+}
+
+/// Replaces every occurrence of a pattern.
+fn replace(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view3::<Str, Str, Str, _, _>(args, |s, from, to| {
+        Ok(Val::from(s.replace(from.as_str(), to).as_str()))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn upper(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<Str, _, _>(args, |s| Ok(Val::from(s.to_uppercase().as_str())))?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+fn lower(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<Str, _, _>(args, |s| Ok(Val::from(s.to_lowercase().as_str())))?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// A string repeated a number of times, none if it's negative.
+fn repeat(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view2::<Str, view::AnyRef<view::Number>, _, _>(args, |s, n| {
+        Ok(Val::from(s.repeat((*n).max(0) as usize).as_str()))
+    })?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// Pads a string up to a width with spaces, or the given character, on the
+/// left for positive widths and on the right for negative ones.
+fn pad(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (s, tail) = view1::<Str, _, _>(args, |s| Ok(s.clone()))?;
+    let (width, tail) = view1::<view::AnyRef<view::Number>, _, _>(tail, |n| Ok(*n))?;
+    let (fill, tail) = match tail.split_first_mut() {
+        Some((fill, tail)) => (view::AnyRef::<view::Char>::view(fill, |c| Ok(*c))?, tail),
+        None => (' ', tail),
+    };
+    test_consumed(tail)?;
+
+    let missing = (width.unsigned_abs() as usize).saturating_sub(s.chars().count());
+    let padding: String = std::iter::repeat_n(fill, missing).collect();
+    let res = if width < 0 {
+        s + &padding
+    } else {
+        padding + &s
+    };
+
+    Ok(Val::from(res.as_str()))
+}
+
+fn chars(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<Str, _, _>(args, |s| Ok(s.chars().map(Val::Char).collect()))?;
+    test_consumed(tail)?;
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Lines of a string, without their line endings.
+fn lines(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<Str, _, _>(args, |s| Ok(strings(s.lines())))?;
+    test_consumed(tail)?;
+
+    Ok(res)
+}
+
+/// Puts the values following a template in place of its `{}` placeholders,
+/// `{{` and `}}` standing for literal braces.
+fn format(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (template, vals) = view1::<Str, _, _>(args, |s| Ok(s.clone()))?;
+
+    let mut vals: VecDeque<_> = vals.iter().collect();
+    let mut placeholders = 0;
+    let mut res = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                res.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(val) = vals.pop_front() {
+                    res.push_str(&show(val, env)?);
+                }
+            }
+            _ => res.push(c),
+        }
+    }
+
+    if placeholders != args.len() - 1 {
+        return Err(RuntimeError::WrongArgsN {
+            expected: placeholders + 1,
+            received: args.len(),
+        }
+        .into());
+    }
+
+    Ok(Val::from(res.as_str()))
+}
+
+pub(crate) fn make_string_builtin() -> RustObj {
+    RustObj::new(
+        "string",
+        vec![
+            RustFn::new("split", split),
+            RustFn::new("join", join),
+            RustFn::new("trim", trim),
+            RustFn::new("find", find),
+            RustFn::new("startsWith", starts_with),
+            RustFn::new("endsWith", ends_with),
+            RustFn::new("replace", replace),
+            RustFn::new("upper", upper),
+            RustFn::new("lower", lower),
+            RustFn::new("repeat", repeat),
+            RustFn::new("pad", pad),
+            RustFn::new("chars", chars),
+            RustFn::new("lines", lines),
+            RustFn::new("format", format),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::eval_in;
+
+    /// Test environment with `string` and a string `s`.
+    fn env() -> Env {
+        let mut env = Env::test();
+        env.store_binding("string".to_string(), Val::from_obj(make_string_builtin()));
+        env.store_binding("s".to_string(), Val::from(" a,b,,c "));
+
+        env
+    }
+
+    fn strs(parts: &[&str]) -> Val {
+        strings(parts.iter().copied())
+    }
+
+    #[test]
+    fn split_join() {
+        let res = eval_in(&mut env(), "📞 string🪆split s 🧵,🧵");
+        assert_eq!(res, Ok(strs(&[" a", "b", "", "c "])));
+
+        let res = eval_in(&mut env(), "📞 string🪆split 🧵 one  two\n three🧵");
+        assert_eq!(res, Ok(strs(&["one", "two", "three"])));
+
+        let res = eval_in(
+            &mut env(),
+            "📦
+                👶 parts = 📞 string🪆split 🔖s 🧵,🧵 💪
+                📞 string🪆join parts 🧵-🧵
+            🧑‍🦲",
+        );
+        assert_eq!(res, Ok(Val::from(" a-b--c ")));
+
+        let res = eval_in(
+            &mut env(),
+            "📦
+                👶 gen = 🧰 ➡️ 🐣 1 🧑‍🦲 💪 🐣 🔡x🔡 🧑‍🦲 🧑‍🦲 💪
+                👶 g = 📞 gen 💪
+                📞 string🪆join g 🧵, 🧵
+            🧑‍🦲",
+        );
+        assert_eq!(res, Ok(Val::from("1, x")));
+    }
+
+    #[test]
+    fn search() {
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆find 🧵żółw🧵 🧵łw🧵"),
+            Ok(Val::Number(2))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆find s 🧵x🧵"),
+            Ok(Val::Unit)
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆startsWith s 🧵 a🧵"),
+            Ok(Val::Bool(true))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆endsWith 🔖s 🧵c🧵"),
+            Ok(Val::Bool(false))
+        );
+    }
+
+    #[test]
+    fn transform() {
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆trim s"),
+            Ok(Val::from("a,b,,c"))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆replace s 🧵,🧵 🧵;🧵"),
+            Ok(Val::from(" a;b;;c "))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆upper 🧵żółw🧵"),
+            Ok(Val::from("ŻÓŁW"))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆lower 🧵ŻÓŁW🧵"),
+            Ok(Val::from("żółw"))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆repeat 🧵ab🧵 3"),
+            Ok(Val::from("ababab"))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆repeat 🧵ab🧵 📦0-1🧑‍🦲"),
+            Ok(Val::from(""))
+        );
+    }
+
+    #[test]
+    fn pad() {
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆pad 🧵ół🧵 4"),
+            Ok(Val::from("  ół"))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆pad 🧵ół🧵 📦0-4🧑‍🦲 🔡.🔡"),
+            Ok(Val::from("ół.."))
+        );
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆pad 🧵abc🧵 2"),
+            Ok(Val::from("abc"))
+        );
+    }
+
+    #[test]
+    fn chars_lines() {
+        let expected = ['ż', 'ó'].into_iter().map(Val::Char).collect();
+        assert_eq!(
+            eval_in(&mut env(), "📞 string🪆chars 🧵żó🧵"),
+            Ok(Val::Deque(Box::new(expected)))
+        );
+
+        let res = eval_in(&mut env(), "📞 string🪆lines 🧵a\r\nb\n\nc🧵");
+        assert_eq!(res, Ok(strs(&["a", "b", "", "c"])));
+    }
+
+    #[test]
+    fn format() {
+        let res = eval_in(
+            &mut env(),
+            "📞 string🪆format 🧵{} + {} = {{{}}}🧵 1 🔡x🔡 🔖s",
+        );
+        assert_eq!(res, Ok(Val::from("1 + x = { a,b,,c }")));
+
+        let res = eval_in(&mut env(), "📞 string🪆format 🧵{} {}🧵 1");
+        let expected = RuntimeError::WrongArgsN {
+            expected: 3,
+            received: 2,
+        };
+        assert_eq!(res, Err(expected.into()));
+
+        let res = eval_in(&mut env(), "📞 string🪆format 1");
+        let expected = RuntimeError::CastError {
+            from: "🔢".into(),
+            to: "🧵".into(),
+        };
+        assert_eq!(res, Err(expected.into()));
+    }
+}
@@ -4,12 +4,11 @@ use crate::env::Env;
 use crate::val::view::test_consumed;
 use crate::val::Val;
 use std::cell::RefCell;
-use std::collections::VecDeque;
 use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub(crate) struct SysState {
-    args: Vec<VecDeque<Val>>,
+    args: Vec<Rc<str>>,
 }
 
 fn get_args(tail: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
@@ -18,18 +17,12 @@ fn get_args(tail: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val
     let mut borrow = state.0.borrow_mut();
     let sys: &SysState = borrow.downcast_mut::<SysState>().unwrap();
 
-    let res_deque = sys
-        .args
-        .iter()
-        .cloned()
-        .map(Box::new)
-        .map(Val::Deque)
-        .collect();
+    let res_deque = sys.args.iter().cloned().map(Val::Str).collect();
     Ok(Val::Deque(Box::new(res_deque)))
 }
 
 pub(crate) fn make_sys_builtin(args: impl Iterator<Item = String>) -> RustObj {
-    let args = args.map(|s| s.chars().map(Val::Char).collect()).collect();
+    let args = args.map(Rc::from).collect();
     let state = Rc::new(RefCell::new(SysState { args }));
 
     RustObj::new("sys", vec![RustFn::stateful("args", get_args, &state)])
@@ -1,22 +1,184 @@
 use crate::builtins::objects::rustobj::RustObj;
 use crate::builtins::rustfn::{FnState, RustFn};
 use crate::env::Env;
-use crate::val::view::{self, test_consumed, view1};
+use crate::error::RuntimeError;
+use crate::expr::class;
+use crate::utils::kwords;
+use crate::val::view::{self, take_n, test_consumed, view1, View as _};
 use crate::val::Val;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 
+/// Parses a number from a string, in base 10 or the given radix, ignoring
+/// surrounding whitespace.
+fn to_number(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (s, tail) = view1::<view::AnyRef<view::String>, _, _>(args, |s| Ok(s.clone()))?;
+    let (radix, tail) = match tail.split_first_mut() {
+        Some((radix, tail)) => (view::AnyRef::<view::Number>::view(radix, |n| Ok(*n))?, tail),
+        None => (10, tail),
+    };
+    test_consumed(tail)?;
+
+    if !(2..=36).contains(&radix) {
+        return Err(RuntimeError::CastError {
+            from: radix.to_string(),
+            to: format!("2{}37", kwords::RANGE),
+        }
+        .into());
+    }
+
+    match i32::from_str_radix(s.trim(), radix as u32) {
+        Ok(n) => Ok(Val::Number(n)),
+        Err(_) => Err(RuntimeError::CastError {
+            from: format!("🧵{}🧵", s),
+            to: "🔢".into(),
+        }
+        .into()),
+    }
+}
+
+/// Character with the given unicode codepoint.
 fn to_char(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
-    let (res_char, tail) =
-        view1::<view::AnyRef<view::Number>, _, _>(args, |n| Ok(*n as u8 as char))?;
+    let (res_char, tail) = view1::<view::AnyRef<view::Number>, _, _>(args, |n| {
+        u32::try_from(*n)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| RuntimeError::CastError {
+                from: n.to_string(),
+                to: "🔡".into(),
+            })
+    })?;
     test_consumed(tail)?;
 
     Ok(Val::Char(res_char))
 }
 
-fn to_string(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
-    let (res, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| Ok(format!("{}", v)))?;
+fn to_codepoint(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Char>, _, _>(args, |c| Ok(*c as i32))?;
+    test_consumed(tail)?;
+
+    Ok(Val::Number(res))
+}
+
+/// Converts booleans, numbers (true unless zero) and the boolean keywords
+/// in a string to a boolean.
+fn to_bool(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| match v {
+        Val::Bool(b) => Ok(*b),
+        Val::Number(n) => Ok(*n != 0),
+        Val::Str(s) if s.trim() == kwords::TRUE => Ok(true),
+        Val::Str(s) if s.trim() == kwords::FALSE => Ok(false),
+        Val::Str(s) => Err(RuntimeError::CastError {
+            from: format!("🧵{}🧵", s),
+            to: "🤨".into(),
+        }),
+        _ => Err(RuntimeError::CastError {
+            from: v.variant_name().to_string(),
+            to: "🤨".into(),
+        }),
+    })?;
+    test_consumed(tail)?;
+
+    Ok(Val::Bool(res))
+}
+
+/// Name of the variant of the value a reference refers to, or of the value
+/// itself.
+fn type_of(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| Ok(v.variant_name()))?;
+    test_consumed(tail)?;
+
+    Ok(Val::from(res))
+}
+
+/// Whether a value is of the variant named in the state, following references
+/// unless that's the variant asked about.
+fn is_variant(args: &mut [Val], _env: &mut Env, state: FnState) -> Result<Val, Val> {
+    let ([val], tail) = take_n::<1>(args)?;
+    test_consumed(tail)?;
+
+    let name = *state.0.borrow().downcast_ref::<&'static str>().unwrap();
+    let res = match val {
+        Val::Ref(_) | Val::Weak(_) if val.variant_name() == name => true,
+        _ => val.apply_to_root(|v| v.variant_name() == name)?,
+    };
+
+    Ok(Val::Bool(res))
+}
+
+const VARIANTS: [(&str, &str); 12] = [
+    ("isNumber", "🔢"),
+    ("isFloat", "🛟"),
+    ("isChar", "🔡"),
+    ("isString", "🧵"),
+    ("isBool", "🤨"),
+    ("isUnit", "📦🧑‍🦲"),
+    ("isDeque", "😵‍💫😵‍💫"),
+    ("isMap", "🗺️"),
+    ("isFunc", "🧰"),
+    ("isObject", "🧑‍🏫"),
+    ("isRef", "🔖"),
+    ("isWeak", "🦽"),
+];
+
+/// Converts a number to a float.
+fn to_float(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| {
+        v.to_f64().map_err(|_| RuntimeError::CastError {
+            from: v.variant_name().to_string(),
+            to: "🛟".into(),
+        })
+    })?;
     test_consumed(tail)?;
 
-    Ok(Val::from(res.as_ref()))
+    Ok(Val::Float(res))
+}
+
+/// Converts a number to an integer, rounding towards zero. Floats that aren't
+/// finite or are out of range can't be converted.
+fn to_int(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let in_range = |x: f64| (i32::MIN as f64..=i32::MAX as f64).contains(&x.trunc());
+    let (res, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| match v {
+        Val::Number(n) => Ok(*n),
+        Val::Float(x) if !in_range(*x) => Err(RuntimeError::CastError {
+            from: v.to_string(),
+            to: "🔢".into(),
+        }),
+        _ => Ok(v.to_f64()? as i32),
+    })?;
+    test_consumed(tail)?;
+
+    Ok(Val::Number(res))
+}
+
+fn to_string(args: &mut [Val], env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let ([val], tail) = take_n::<1>(args)?;
+    test_consumed(tail)?;
+
+    Ok(Val::from(class::show(val, env)?.as_ref()))
+}
+
+/// Converts a string to a deque of its characters.
+fn to_deque(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (res, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| v.to_deque())?;
+    test_consumed(tail)?;
+
+    Ok(Val::Deque(Box::new(res)))
+}
+
+/// Deque of the objects the class of an object derives from, nearest first.
+fn to_bases(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, Val> {
+    let (mut obj, tail) = view1::<view::AnyRef<view::Bottom>, _, _>(args, |v| Ok(v.clone()))?;
+    test_consumed(tail)?;
+
+    let mut bases = VecDeque::new();
+    while let Some(base) = class::base_of(&mut obj)? {
+        bases.push_back(base.clone());
+        obj = base;
+    }
+
+    Ok(Val::Deque(Box::new(bases)))
 }
 
 #[cfg(feature = "web")]
@@ -28,20 +190,37 @@ fn jv_to_val(args: &mut [Val], _env: &mut Env, _state: FnState) -> Result<Val, V
 }
 
 pub(crate) fn make_types_builtin() -> RustObj {
+    let predicates = VARIANTS
+        .into_iter()
+        .map(|(name, variant)| RustFn::stateful(name, is_variant, &Rc::new(RefCell::new(variant))));
+
     RustObj::new(
         "types",
         vec![
+            RustFn::new("number", to_number),
             RustFn::new("char", to_char),
+            RustFn::new("codepoint", to_codepoint),
+            RustFn::new("bool", to_bool),
+            RustFn::new("of", type_of),
+            RustFn::new("float", to_float),
+            RustFn::new("int", to_int),
             RustFn::new("string", to_string),
+            RustFn::new("deque", to_deque),
+            RustFn::new("bases", to_bases),
             #[cfg(feature = "web")]
             RustFn::new("fromJs", jv_to_val),
-        ],
+        ]
+        .into_iter()
+        .chain(predicates)
+        .collect(),
     )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::RuntimeError;
+    use crate::test_utils::eval_in;
 
     #[test]
     fn val_to_char() {
@@ -57,4 +236,214 @@ mod tests {
             Ok(Val::Char(24 as char))
         );
     }
+
+    #[test]
+    fn parse_number() {
+        let mut env = Env::test();
+        let mut parse = |args: &mut [Val]| to_number(args, &mut env, FnState::default());
+
+        assert_eq!(parse(&mut [Val::from(" -42\n")]), Ok(Val::Number(-42)));
+        assert_eq!(
+            parse(&mut [Val::from("ff"), Val::Number(16)]),
+            Ok(Val::Number(255))
+        );
+
+        let expected = RuntimeError::CastError {
+            from: "🧵12x🧵".into(),
+            to: "🔢".into(),
+        };
+        assert_eq!(parse(&mut [Val::from("12x")]), Err(expected.into()));
+
+        let expected = RuntimeError::CastError {
+            from: "1".into(),
+            to: "2⏩37".into(),
+        };
+        assert_eq!(
+            parse(&mut [Val::from("0"), Val::Number(1)]),
+            Err(expected.into())
+        );
+    }
+
+    #[test]
+    fn unicode_chars() {
+        let mut env = Env::test();
+
+        let res = to_char(&mut [Val::Number(0x17c)], &mut env, FnState::default());
+        assert_eq!(res, Ok(Val::Char('ż')));
+
+        let err = to_char(&mut [Val::Number(0xd800)], &mut env, FnState::default());
+        let expected = RuntimeError::CastError {
+            from: "55296".into(),
+            to: "🔡".into(),
+        };
+        assert_eq!(err, Err(expected.into()));
+
+        let res = to_codepoint(&mut [Val::Char('🐣')], &mut env, FnState::default());
+        assert_eq!(res, Ok(Val::Number(0x1f423)));
+    }
+
+    #[test]
+    fn val_to_bool() {
+        let mut env = Env::test();
+
+        let cases = [
+            (Val::Bool(false), false),
+            (Val::Number(2), true),
+            (Val::Number(0), false),
+            (Val::from("🙆‍♀️\n"), true),
+            (Val::from("🙅‍♀️"), false),
+        ];
+        for (arg, expected) in cases {
+            let res = to_bool(&mut [arg], &mut env, FnState::default());
+            assert_eq!(res, Ok(Val::Bool(expected)));
+        }
+
+        let err = to_bool(&mut [Val::from("yes")], &mut env, FnState::default());
+        let expected = RuntimeError::CastError {
+            from: "🧵yes🧵".into(),
+            to: "🤨".into(),
+        };
+        assert_eq!(err, Err(expected.into()));
+
+        let err = to_bool(&mut [Val::Char('y')], &mut env, FnState::default());
+        let expected = RuntimeError::CastError {
+            from: "🔡".into(),
+            to: "🤨".into(),
+        };
+        assert_eq!(err, Err(expected.into()));
+    }
+
+    #[test]
+    fn variants() {
+        let mut env = Env::test();
+        env.store_binding("types".to_string(), Val::from_obj(make_types_builtin()));
+        eval_in(&mut env, "👶 x = 🧵a🧵").unwrap();
+
+        assert_eq!(eval_in(&mut env, "📞 types🪆of x"), Ok(Val::from("🧵")));
+        assert_eq!(
+            eval_in(&mut env, "📞 types🪆isString x"),
+            Ok(Val::Bool(true))
+        );
+        assert_eq!(eval_in(&mut env, "📞 types🪆isRef x"), Ok(Val::Bool(false)));
+        assert_eq!(eval_in(&mut env, "📞 types🪆of 🔖x"), Ok(Val::from("🧵")));
+        assert_eq!(
+            eval_in(&mut env, "📞 types🪆isString 🔖x"),
+            Ok(Val::Bool(true))
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 types🪆isRef 🔖x"),
+            Ok(Val::Bool(true))
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 types🪆isDeque 1"),
+            Ok(Val::Bool(false))
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 types🪆isUnit 📦🧑‍🦲"),
+            Ok(Val::Bool(true))
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 types🪆isNumber 1.5"),
+            Ok(Val::Bool(false))
+        );
+        assert_eq!(
+            eval_in(&mut env, "📞 types🪆isFloat 1.5"),
+            Ok(Val::Bool(true))
+        );
+    }
+
+    #[test]
+    fn str_to_deque() {
+        let mut env = Env::test();
+        let result = to_deque(&mut [Val::from("ab")], &mut env, FnState::default());
+        let expected: VecDeque<_> = [Val::Char('a'), Val::Char('b')].into_iter().collect();
+
+        assert!(matches!(result, Ok(Val::Deque(ref dq)) if **dq == expected));
+        assert_eq!(result, Ok(Val::from("ab")));
+
+        let err = to_deque(&mut [Val::Number(1)], &mut env, FnState::default());
+        let expected = RuntimeError::CastError {
+            from: "🔢".into(),
+            to: "😵‍💫😵‍💫".into(),
+        };
+        assert_eq!(err, Err(expected.into()));
+    }
+
+    #[test]
+    fn float_int_conversions() {
+        let mut env = Env::test();
+
+        type Conversion = fn(&mut [Val], &mut Env, FnState) -> Result<Val, Val>;
+        let cases = [
+            (to_float as Conversion, Val::Number(3), Val::Float(3.0)),
+            (to_float, Val::Float(0.5), Val::Float(0.5)),
+            (to_int, Val::Float(-2.75), Val::Number(-2)),
+            (to_int, Val::Number(7), Val::Number(7)),
+        ];
+        for (func, arg, expected) in cases {
+            assert_eq!(func(&mut [arg], &mut env, FnState::default()), Ok(expected));
+        }
+
+        let cast = |from: &str, to: &str| RuntimeError::CastError {
+            from: from.into(),
+            to: to.into(),
+        };
+        let cases = [
+            (to_int as Conversion, Val::Bool(true), cast("🤨", "🔢")),
+            (to_int, Val::Float(f64::NAN), cast("NaN", "🔢")),
+            (to_int, Val::Float(f64::NEG_INFINITY), cast("-inf", "🔢")),
+            (to_int, Val::Float(3e9), cast("3000000000.0", "🔢")),
+            (
+                to_int,
+                Val::Float(-2147483649.0),
+                cast("-2147483649.0", "🔢"),
+            ),
+            (to_float, Val::from("1.5"), cast("🧵", "🛟")),
+        ];
+        for (func, arg, expected) in cases {
+            let err = func(&mut [arg], &mut env, FnState::default());
+            assert_eq!(err, Err(expected.into()));
+        }
+
+        let min = to_int(
+            &mut [Val::Float(-2147483648.5)],
+            &mut env,
+            FnState::default(),
+        );
+        assert_eq!(min, Ok(Val::Number(i32::MIN)));
+    }
+
+    #[test]
+    fn class_bases() {
+        let mut env = Env::test();
+        eval_in(&mut env, "👶 a = 🧑‍🏫 👶 x = 1 🧑‍🦲").unwrap();
+        eval_in(&mut env, "👶 b = 🧑‍🏫 🧬 a 👶 y = 2 🧑‍🦲").unwrap();
+        let c = eval_in(&mut env, "🧑‍🏫 🧬 b 🧑‍🦲").unwrap();
+        let a = eval_in(&mut env, "a").unwrap();
+
+        let mut env = Env::test();
+        let result = to_bases(&mut [c], &mut env, FnState::default());
+        let names = |val: &Val| {
+            let mut names = val.as_object().unwrap().0.member_names();
+            names.sort();
+            names
+        };
+        match result {
+            Ok(Val::Deque(bases)) => {
+                let bases: Vec<_> = bases.iter().map(names).collect();
+                assert_eq!(bases, [vec!["x", "y"], vec!["x"]]);
+            }
+            result => panic!("expected a deque, got {:?}", result),
+        }
+
+        let result = to_bases(&mut [a], &mut env, FnState::default());
+        assert_eq!(result, Ok(Val::Deque(Box::default())));
+
+        let err = to_bases(&mut [Val::Number(1)], &mut env, FnState::default());
+        let expected = RuntimeError::CastError {
+            from: "🔢".into(),
+            to: "🧑‍🏫".into(),
+        };
+        assert_eq!(err, Err(expected.into()));
+    }
 }
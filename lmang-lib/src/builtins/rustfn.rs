@@ -1,5 +1,6 @@
 use crate::env::Env;
-use crate::val::{Callee, Val};
+use crate::error::RuntimeError;
+use crate::val::{Callee, DynObject, Val};
 
 use std::any::Any;
 use std::cell::RefCell;
@@ -81,9 +82,25 @@ impl fmt::Debug for RustFn {
     }
 }
 
+/// Makes an arity error count all `argc` arguments of a builtin. The view
+/// helpers only see the arguments left after those taken before them.
+fn rebase_arity(mut err: Val, argc: usize) -> Val {
+    if let Val::Object(DynObject(obj)) = &mut err {
+        if let Some(RuntimeError::WrongArgsN { expected, received }) =
+            obj.as_any_mut().downcast_mut::<RuntimeError>()
+        {
+            *expected += argc.saturating_sub(*received);
+            *received = argc;
+        }
+    }
+
+    err
+}
+
 impl Callee for RustFn {
     fn call(&self, args: &mut [Val], env: &mut Env) -> Result<Val, Val> {
-        (self.func)(args, env, self.state.clone())
+        let argc = args.len();
+        (self.func)(args, env, self.state.clone()).map_err(|err| rebase_arity(err, argc))
     }
 
     fn clone_box(&self) -> Box<dyn Callee> {
@@ -1,29 +1,64 @@
-use crate::error::RuntimeError;
+use crate::error::{RuntimeError, TraceFrame};
 use crate::val::Val;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use ahash::RandomState;
 
-type StackFrame = HashMap<String, Val, RandomState>;
+type Bindings = HashMap<String, Val, RandomState>;
+
+/// A block or a function call. Locals resolved ahead of time live in the
+/// `Env`'s slots starting at `base`, others are kept by name.
+#[derive(Debug, PartialEq)]
+struct Frame {
+    named: Option<Bindings>,
+    base: usize,
+}
+
+/// Location of a resolved local: `index` within the frame `depth` frames up
+/// from the innermost one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+/// Modules of a program, shared by all of its `Env`s.
+#[derive(Debug, PartialEq, Default)]
+pub(crate) struct Modules {
+    /// Searched for imports not found next to the importing file.
+    pub(crate) lib_dir: Option<PathBuf>,
+    /// Files being evaluated, the innermost import last.
+    pub(crate) loading: Vec<PathBuf>,
+    pub(crate) cache: HashMap<PathBuf, Val>,
+}
 
 #[derive(Debug, PartialEq, Default)]
 pub struct Env {
-    last_popped: Option<HashMap<String, Val, RandomState>>,
-    root: Rc<RefCell<StackFrame>>,
-    stack: Vec<StackFrame>,
+    root: Rc<RefCell<Bindings>>,
+    stack: Vec<Frame>,
+    slots: Vec<Option<Val>>,
+    /// Frames below this one are hidden from lookups by name.
+    floor: usize,
     timeout: Option<Instant>,
+    pub(crate) modules: Rc<RefCell<Modules>>,
+    /// Calls the errors being raised unwound through, innermost first.
+    trace: Rc<RefCell<Vec<TraceFrame>>>,
 }
 
 impl Env {
     pub fn new() -> Self {
         Env {
-            last_popped: None,
             root: Rc::new(RefCell::new(HashMap::default())),
             stack: Vec::new(),
+            slots: Vec::new(),
+            floor: 0,
             timeout: None,
+            modules: Rc::default(),
+            trace: Rc::default(),
         }
     }
 
@@ -32,38 +67,120 @@ impl Env {
         let timeout = Instant::now() + Duration::from_secs_f32(0.1);
 
         Env {
-            last_popped: None,
             root: Rc::new(RefCell::new(HashMap::default())),
             stack: Vec::new(),
+            slots: Vec::new(),
+            floor: 0,
             timeout: Some(timeout),
+            modules: Rc::default(),
+            trace: Rc::default(),
         }
     }
 
     pub fn shared(&self) -> Self {
         Env {
-            last_popped: None,
             root: self.root.clone(),
             stack: Vec::new(),
+            slots: Vec::new(),
+            floor: 0,
             timeout: self.timeout,
+            modules: self.modules.clone(),
+            trace: self.trace.clone(),
         }
     }
 
+    /// Sets the file of the program, imports are looked up next to it.
+    pub fn set_main_file(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        let path = path.canonicalize().unwrap_or(path);
+        self.modules.borrow_mut().loading = vec![path];
+    }
+
+    /// Sets the directory searched for imports not found next to the
+    /// importing file.
+    pub fn set_lib_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.modules.borrow_mut().lib_dir = Some(dir.into());
+    }
+
     pub fn push(&mut self) {
-        self.stack.push(HashMap::default());
+        self.stack.push(Frame {
+            named: None,
+            base: self.slots.len(),
+        });
+    }
+
+    /// Pushes a frame with its first slots already taken.
+    pub(crate) fn push_slots(&mut self, slots: impl IntoIterator<Item = Option<Val>>) {
+        self.push();
+        self.slots.extend(slots);
     }
 
     pub fn pop(&mut self) {
-        self.last_popped = Some(self.stack.pop().expect("stack empty"));
+        let frame = self.stack.pop().expect("stack empty");
+        self.slots.truncate(frame.base);
+    }
+
+    /// Pops the innermost frame, returning its bindings by name and its slots.
+    pub(crate) fn pop_frame(&mut self) -> (Bindings, Vec<Option<Val>>) {
+        let frame = self.stack.pop().expect("stack empty");
+        let slots = self.slots.split_off(frame.base);
+
+        (frame.named.unwrap_or_default(), slots)
+    }
+
+    fn named_frames(&mut self) -> impl Iterator<Item = &mut Bindings> {
+        self.stack[self.floor..]
+            .iter_mut()
+            .rev()
+            .filter_map(|frame| frame.named.as_mut())
+    }
+
+    /// Whether `name` is bound globally.
+    pub fn is_global(&self, name: &str) -> bool {
+        self.root.borrow().contains_key(name)
+    }
+
+    fn local(&mut self, slot: Slot, name: &str) -> Result<&mut Val, RuntimeError> {
+        let base = self.stack[self.stack.len() - 1 - slot.depth].base;
+        self.slots
+            .get_mut(base + slot.index)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| RuntimeError::NoBinding(name.into()))
     }
 
-    pub fn take_last_popped(&mut self) -> Option<HashMap<String, Val, RandomState>> {
-        self.last_popped.take()
+    pub fn get_local(&mut self, slot: Slot, name: &str) -> Result<Val, RuntimeError> {
+        self.local(slot, name).map(|val| val.clone())
+    }
+
+    /// Binds a slot of the innermost frame.
+    pub fn store_local(&mut self, index: usize, val: Val) {
+        let idx = self.stack.last().expect("stack empty").base + index;
+        if self.slots.len() <= idx {
+            self.slots.resize(idx + 1, None);
+        }
+        self.slots[idx] = Some(val);
+    }
+
+    pub fn set_local(&mut self, slot: Slot, name: &str, new_val: Val) -> Result<(), RuntimeError> {
+        match self.local(slot, name)? {
+            Val::Ref(rc) => *rc.borrow_mut() = new_val,
+            val => *val = new_val,
+        }
+
+        Ok(())
+    }
+
+    pub fn ref_local(&mut self, slot: Slot, name: &str) -> Result<Val, RuntimeError> {
+        self.local(slot, name).map(Val::make_ref)
     }
 
     pub fn store_binding(&mut self, name: String, val: Val) {
         match self.stack.last_mut() {
             Some(frame) => {
-                frame.insert(name, val);
+                frame
+                    .named
+                    .get_or_insert_with(HashMap::default)
+                    .insert(name, val);
             }
             None => {
                 let mut borrow = self.root.borrow_mut();
@@ -78,7 +195,7 @@ impl Env {
     }
 
     pub fn set_binding(&mut self, name: &str, new_val: Val) -> Result<(), RuntimeError> {
-        for frame in self.stack.iter_mut().rev() {
+        for frame in self.named_frames() {
             if let Some(val) = frame.get_mut(name) {
                 match val {
                     Val::Ref(rc) => *rc.borrow_mut() = new_val,
@@ -103,7 +220,8 @@ impl Env {
     }
 
     pub fn get_binding(&self, name: &str) -> Result<Val, RuntimeError> {
-        for frame in self.stack.iter().rev() {
+        let frames = self.stack[self.floor..].iter().rev();
+        for frame in frames.filter_map(|frame| frame.named.as_ref()) {
             if let Some(val) = frame.get(name) {
                 return Ok(val.clone());
             }
@@ -118,11 +236,8 @@ impl Env {
     }
 
     pub fn take_ref(&mut self, name: &str) -> Result<Val, RuntimeError> {
-        for frame in self.stack.iter_mut().rev() {
-            if let Some(val) = frame.get_mut(name) {
-                let val_ref = val.make_ref();
-                return Ok(val_ref);
-            }
+        if let Some(val_ref) = self.take_local_ref(name) {
+            return Ok(val_ref);
         }
 
         let mut borrow = self.root.borrow_mut();
@@ -134,15 +249,64 @@ impl Env {
         Err(RuntimeError::NoBinding(name.into()))
     }
 
+    /// Like `take_ref`, but ignores globals.
+    pub fn take_local_ref(&mut self, name: &str) -> Option<Val> {
+        for frame in self.named_frames() {
+            if let Some(val) = frame.get_mut(name) {
+                return Some(val.make_ref());
+            }
+        }
+
+        None
+    }
+
+    /// Runs `f` with only globals visible, as is the case in function bodies.
+    pub fn isolated<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let floor = std::mem::replace(&mut self.floor, self.stack.len());
+        let result = f(self);
+        self.floor = floor;
+
+        result
+    }
+
+    /// Records that the error being raised unwound through `frame`.
+    pub(crate) fn push_frame(&mut self, frame: TraceFrame) {
+        self.trace.borrow_mut().push(frame);
+    }
+
+    /// Number of frames recorded so far, errors caught by a handler
+    /// installed now only unwind through the frames recorded after.
+    pub(crate) fn trace_len(&self) -> usize {
+        self.trace.borrow().len()
+    }
+
+    /// Takes the frames recorded after the first `len`.
+    pub(crate) fn split_trace(&mut self, len: usize) -> Vec<TraceFrame> {
+        let mut trace = self.trace.borrow_mut();
+        let len = len.min(trace.len());
+        trace.split_off(len)
+    }
+
+    /// Takes the frames of an error that unwound all the way out.
+    pub fn take_trace(&mut self) -> Vec<TraceFrame> {
+        self.split_trace(0)
+    }
+
     pub fn set_timeout(&mut self, dur: Duration) {
         self.timeout = Some(Instant::now() + dur);
     }
 
     pub fn eval(&mut self, expr: &impl Eval) -> Result<Val, Val> {
+        self.check_timeout()?;
+        expr.eval(self)
+    }
+
+    #[inline]
+    pub(crate) fn check_timeout(&self) -> Result<(), RuntimeError> {
         if self.timeout.map(|t| Instant::now() > t).unwrap_or(false) {
-            Err(RuntimeError::Timeout.into())
+            Err(RuntimeError::Timeout)
         } else {
-            expr.eval(self)
+            Ok(())
         }
     }
 }
@@ -200,6 +364,31 @@ mod tests {
         assert_eq!(env.get_binding("c"), Ok(Val::Number(7)));
     }
 
+    #[test]
+    fn env_slots() {
+        let mut env = Env::test();
+        env.push();
+        env.store_local(1, Val::Number(1));
+
+        env.push_slots([Some(Val::Number(2))]);
+        let outer = Slot { depth: 1, index: 1 };
+        assert_eq!(env.get_local(outer, "a"), Ok(Val::Number(1)));
+        assert_eq!(
+            env.get_local(Slot { depth: 1, index: 0 }, "b"),
+            Err(RuntimeError::NoBinding("b".into()))
+        );
+
+        env.set_local(outer, "a", Val::Number(3)).unwrap();
+        env.pop();
+
+        let inner = Slot { depth: 0, index: 1 };
+        assert_eq!(env.get_local(inner, "a"), Ok(Val::Number(3)));
+        assert_eq!(
+            env.get_local(Slot { depth: 0, index: 2 }, "c"),
+            Err(RuntimeError::NoBinding("c".into()))
+        );
+    }
+
     #[test]
     fn prevent_double_ref() {
         let mut env = Env::test();
@@ -1,14 +1,16 @@
-use crate::utils::kwords;
-use crate::val::{Object, Val};
+use crate::utils::{self, kwords};
+use crate::val::{DynObject, Object, Val};
+use std::any::Any;
 use std::fmt;
 use strum::AsRefStr;
 use thiserror::Error;
+use unicode_segmentation::UnicodeSegmentation as _;
 
 #[cfg(feature = "web")]
 use wasm_bindgen::JsValue;
 
 #[derive(Error, Clone, Debug, PartialEq, AsRefStr)]
-pub enum ParseError {
+pub enum ParseErrorKind {
     #[error("Expected digits")]
     ExpectedDigits,
     #[error("Expected whitespace")]
@@ -25,12 +27,137 @@ pub enum ParseError {
     ExpectedIndex,
     #[error("Expected expression")]
     ExpectedExpr,
-    #[error("Variadic must be the last argument")]
+    #[error("Variadic arguments must come last")]
     PrematureVariadic,
     #[error("Unexpected end of file")]
     UnexpectedEof,
     #[error("Unexpected consequtive equality")]
     UnexpectedEquals,
+    #[error("Unresolved name {0}")]
+    UnresolvedName(String),
+    #[error("Number out of range")]
+    NumberOutOfRange,
+    #[error("Expected pattern")]
+    ExpectedPattern,
+}
+
+impl ParseErrorKind {
+    /// Creates an error located at the beginning of `s`, the input remaining
+    /// at the point of failure.
+    pub(crate) fn at(self, s: &str) -> ParseError {
+        ParseError {
+            kind: self,
+            rem: s.len(),
+            span: None,
+        }
+    }
+
+    /// Creates an error for an expression already parsed, `rem` long before
+    /// the end of the input and found at `span`.
+    pub(crate) fn at_expr(self, rem: usize, span: Option<Span>) -> ParseError {
+        ParseError {
+            kind: self,
+            rem,
+            span,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Computes 1-based line and grapheme column of a byte `offset` in `source`.
+    pub fn locate(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+
+        Span {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].graphemes(true).count() + 1,
+        }
+    }
+}
+
+#[derive(Error, Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    rem: usize,
+    pub span: Option<Span>,
+}
+
+impl ParseError {
+    /// Moves the error to the beginning of `s`.
+    pub(crate) fn at(self, s: &str) -> Self {
+        self.kind.at(s)
+    }
+
+    /// Picks whichever error got further into the input, preferring `other`
+    /// on ties.
+    pub(crate) fn furthest(self, other: ParseError) -> Self {
+        if other.rem <= self.rem {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Whether the failing parser consumed anything beyond the start of `s`.
+    pub(crate) fn progressed_past(&self, s: &str) -> bool {
+        self.rem < s.len()
+    }
+
+    /// Resolves the position of the error within `source`, which must be the
+    /// complete input the failing parser was given.
+    pub fn locate(self, source: &str) -> Self {
+        let offset = source.len().saturating_sub(self.rem);
+
+        ParseError {
+            span: Some(Span::locate(source, offset)),
+            ..self
+        }
+    }
+
+    /// Renders the error along with the offending line of `source` and a caret
+    /// pointing at the exact position.
+    pub fn render(&self, source: &str) -> String {
+        let span = match self.span {
+            Some(span) => span,
+            None => return format!("{}", self),
+        };
+
+        let line = source.lines().nth(span.line - 1).unwrap_or("");
+        let gutter = format!("{} | ", span.line);
+        let caret_pad: String = line
+            .graphemes(true)
+            .take(span.column - 1)
+            .map(|g| if g == "\t" { "\t" } else { utils::pad_for(g) })
+            .collect();
+
+        format!(
+            "{}\n{}{}\n{:>w$}{}^",
+            self,
+            gutter,
+            line,
+            "| ",
+            caret_pad,
+            w = gutter.len(),
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} at {}:{}", self.kind, span.line, span.column),
+            None => write!(f, "{}", self.kind),
+        }
+    }
 }
 
 impl Object for ParseError {
@@ -38,24 +165,31 @@ impl Object for ParseError {
         ["type"]
             .into_iter()
             .chain({
-                use ParseError::*;
-                match self {
+                use ParseErrorKind::*;
+                match self.kind {
                     ExpectedTag(_) => vec!["expectedTag"],
                     _ => vec![],
                 }
                 .into_iter()
             })
+            .chain(match self.span {
+                Some(_) => vec!["offset", "line", "column"],
+                None => vec![],
+            })
             .map(|s| s.to_string())
             .collect()
     }
 
     fn member(&self, name: &str) -> Result<Val, RuntimeError> {
         if name == "type" {
-            Ok(Val::from(self.as_ref()))
+            Ok(Val::from(self.kind.as_ref()))
         } else {
-            use ParseError::*;
-            match (name, self) {
-                ("expectedTag", ExpectedTag(s)) => Ok(Val::from(s.as_ref())),
+            use ParseErrorKind::*;
+            match (name, &self.kind, self.span) {
+                ("expectedTag", ExpectedTag(s), _) => Ok(Val::from(*s)),
+                ("offset", _, Some(span)) => Ok(Val::Number(span.offset as i32)),
+                ("line", _, Some(span)) => Ok(Val::Number(span.line as i32)),
+                ("column", _, Some(span)) => Ok(Val::Number(span.column as i32)),
                 _ => Err(RuntimeError::NoKey(name.into())),
             }
         }
@@ -76,6 +210,10 @@ impl Object for ParseError {
     fn name(&self) -> &str {
         "ParseError"
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 #[derive(Error, Clone, Debug, PartialEq, AsRefStr)]
@@ -86,8 +224,10 @@ pub enum RuntimeError {
     Timeout,
     #[error("Out of bounds access: {idx}, size is: {len}")]
     OutOfBounds { idx: i32, len: usize },
-    #[error("Invalid number of arguments")]
-    WrongArgsN,
+    #[error("Invalid number of arguments, expected {expected}, received {received}")]
+    WrongArgsN { expected: usize, received: usize },
+    #[error("Unexpected argument {0}")]
+    UnexpectedArg(String),
     #[error("Can't cast from {from} to {to}")]
     CastError { from: String, to: String },
     #[error("Invalid operation {lhs} {op}, {rhs}")]
@@ -100,10 +240,26 @@ pub enum RuntimeError {
     Dangling,
     #[error("Can't open file {file}, reason: {reason}")]
     IoError { file: String, reason: String },
+    #[error("Import cycle through {0}")]
+    ImportCycle(String),
+    #[error("Can't load module {file}: {reason}")]
+    ModuleError { file: String, reason: String },
     #[error("Invalid handle {0}")]
     NoHandle(i32),
     #[error("No key {0}")]
     NoKey(String),
+    #[error("Member {0} can't be set")]
+    ReadOnlyMember(String),
+    #[error("No pattern matches {0}")]
+    NoMatch(String),
+    #[error("Break outside of a loop")]
+    BreakOutsideLoop,
+    #[error("Yield outside of a generator")]
+    YieldOutsideGenerator,
+    #[error("Integer overflow")]
+    Overflow,
+    #[error("Division by zero")]
+    DivisionByZero,
     #[cfg(feature = "web")]
     #[error("Js error {:?}", .0)]
     JsError(JsValue),
@@ -118,11 +274,17 @@ impl Object for RuntimeError {
                 match self {
                     NoBinding(_) => vec!["binding"],
                     OutOfBounds { .. } => vec!["idx", "len"],
+                    WrongArgsN { .. } => vec!["expected", "received"],
+                    UnexpectedArg(_) => vec!["arg"],
                     CastError { .. } => vec!["from", "to"],
                     InvalidOp { .. } => vec!["lhs", "op", "rhs"],
                     IoError { .. } => vec!["file", "reason"],
+                    ImportCycle(_) => vec!["file"],
+                    ModuleError { .. } => vec!["file", "reason"],
                     NoHandle(_) => vec!["handle"],
                     NoKey(_) => vec!["key"],
+                    ReadOnlyMember(_) => vec!["member"],
+                    NoMatch(_) => vec!["val"],
                     #[cfg(feature = "web")]
                     JsError(_) => vec!["jsError"],
                     _ => vec![],
@@ -142,6 +304,9 @@ impl Object for RuntimeError {
                 ("binding", NoBinding(s)) => Ok(Val::from(s.as_ref())),
                 ("idx", OutOfBounds { idx, .. }) => Ok(Val::Number(*idx)),
                 ("len", OutOfBounds { len, .. }) => Ok(Val::Number(*len as i32)),
+                ("expected", WrongArgsN { expected, .. }) => Ok(Val::Number(*expected as i32)),
+                ("received", WrongArgsN { received, .. }) => Ok(Val::Number(*received as i32)),
+                ("arg", UnexpectedArg(arg)) => Ok(Val::from(arg.as_ref())),
                 ("from", CastError { from, .. }) => Ok(Val::from(from.as_ref())),
                 ("to", CastError { to, .. }) => Ok(Val::from(to.as_ref())),
                 ("lhs", InvalidOp { lhs, .. }) => Ok(Val::from(lhs.as_ref())),
@@ -149,8 +314,13 @@ impl Object for RuntimeError {
                 ("rhs", InvalidOp { rhs, .. }) => Ok(Val::from(rhs.as_ref())),
                 ("file", IoError { file, .. }) => Ok(Val::from(file.as_ref())),
                 ("reason", IoError { reason, .. }) => Ok(Val::from(reason.as_ref())),
+                ("file", ImportCycle(file)) => Ok(Val::from(file.as_ref())),
+                ("file", ModuleError { file, .. }) => Ok(Val::from(file.as_ref())),
+                ("reason", ModuleError { reason, .. }) => Ok(Val::from(reason.as_ref())),
                 ("handle", NoHandle(handle)) => Ok(Val::Number(*handle)),
                 ("key", NoKey(key)) => Ok(Val::from(key.as_ref())),
+                ("member", ReadOnlyMember(member)) => Ok(Val::from(member.as_ref())),
+                ("val", NoMatch(val)) => Ok(Val::from(val.as_ref())),
                 #[cfg(feature = "web")]
                 ("jsError", JsError(jv)) => Ok(Val::JsValue(jv.clone())),
                 _ => Err(RuntimeError::NoKey(name.into())),
@@ -173,6 +343,10 @@ impl Object for RuntimeError {
     fn name(&self) -> &str {
         "RuntimeError"
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 impl<'a> From<&'a RuntimeError> for RuntimeError {
@@ -239,6 +413,10 @@ impl Object for Error {
     fn name(&self) -> &str {
         "Error"
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 impl From<ParseError> for Error {
@@ -270,3 +448,194 @@ impl From<RuntimeError> for Val {
         Val::from_obj(e)
     }
 }
+
+/// A call an error unwound through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceFrame {
+    pub name: String,
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(span) = self.span {
+            write!(f, " at {}:{}", span.line, span.column)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Object for TraceFrame {
+    fn member_names(&self) -> Vec<String> {
+        ["name"]
+            .into_iter()
+            .chain(match self.span {
+                Some(_) => vec!["offset", "line", "column"],
+                None => vec![],
+            })
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn member(&self, name: &str) -> Result<Val, RuntimeError> {
+        match (name, self.span) {
+            ("name", _) => Ok(Val::from(self.name.as_ref())),
+            ("offset", Some(span)) => Ok(Val::Number(span.offset as i32)),
+            ("line", Some(span)) => Ok(Val::Number(span.line as i32)),
+            ("column", Some(span)) => Ok(Val::Number(span.column as i32)),
+            _ => Err(RuntimeError::NoKey(name.into())),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Object> {
+        Box::new(self.clone())
+    }
+
+    fn dyn_debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+
+    fn name(&self) -> &str {
+        "TraceFrame"
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// An error caught by a handler along with the calls it unwound through,
+/// innermost first. Behaves like the wrapped error, with an additional `trace`
+/// member left out of its display.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Traced {
+    pub error: Val,
+    pub trace: Vec<TraceFrame>,
+}
+
+impl Traced {
+    /// The value handlers see for `err`, objects getting the frames it
+    /// unwound through as their `trace`.
+    pub(crate) fn caught(mut err: Val, trace: Vec<TraceFrame>) -> Val {
+        // rethrown errors keep the frames from where they were first raised.
+        if let Some(traced) = Self::get_mut(&mut err) {
+            traced.trace.extend(trace);
+            return err;
+        }
+
+        match err {
+            Val::Object(_) => Val::from_obj(Traced { error: err, trace }),
+            err => err,
+        }
+    }
+
+    /// Accesses the trace attached to `err`, if any.
+    pub fn get_mut(err: &mut Val) -> Option<&mut Traced> {
+        match err {
+            Val::Object(DynObject(obj)) => obj.as_any_mut().downcast_mut::<Traced>(),
+            _ => None,
+        }
+    }
+
+    /// Renders the error followed by the calls it unwound through, as
+    /// reported for errors nothing caught.
+    pub fn report(&self) -> String {
+        let mut report = self.error.to_string().trim_end().to_string();
+        for frame in &self.trace {
+            report.push_str(&format!("\n    in {}", frame));
+        }
+
+        report
+    }
+}
+
+impl Object for Traced {
+    fn member_names(&self) -> Vec<String> {
+        match self.error.as_object() {
+            Ok(obj) => obj.0.member_names(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn member(&self, name: &str) -> Result<Val, RuntimeError> {
+        if name == "trace" {
+            let frames = self.trace.iter().cloned().map(Val::from_obj).collect();
+            Ok(Val::Deque(Box::new(frames)))
+        } else {
+            self.error.as_object()?.0.member(name)
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Object> {
+        Box::new(self.clone())
+    }
+
+    // shows as the wrapped error, so caught errors compare equal to it.
+    fn dyn_debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error.as_object() {
+            Ok(obj) => obj.0.dyn_debug(f),
+            Err(_) => write!(f, "{:?}", self.error),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self.error.as_object() {
+            Ok(obj) => obj.0.name(),
+            Err(_) => "Traced",
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expr;
+
+    #[test]
+    fn locate_counts_graphemes() {
+        let source = "📦\n 👶 🅱️ = 🧑‍🦲";
+        let offset = source.find('=').unwrap();
+
+        assert_eq!(
+            Span::locate(source, offset),
+            Span {
+                offset,
+                line: 2,
+                column: 6,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_error_located() {
+        let source = "📦\n    👶 x = 📦 1 + 🧑‍🦲\n🧑‍🦲";
+        let err = Expr::parse(source).unwrap_err();
+
+        assert_eq!(err.kind, ParseErrorKind::ExpectedExpr);
+        assert_eq!(
+            err.span,
+            Some(Span {
+                offset: source.find("🧑‍🦲").unwrap(),
+                line: 2,
+                column: 17,
+            })
+        );
+        assert_eq!(err.member("line"), Ok(Val::Number(2)));
+        assert_eq!(err.member("column"), Ok(Val::Number(17)));
+    }
+
+    #[test]
+    fn parse_error_render() {
+        let source = "📦\n 📞 f a - 💪\n🧑‍🦲";
+        let err = Expr::parse(source).unwrap_err();
+        let expected = "Expected expression at 2:10\n2 |  📞 f a - 💪\n  |           ^";
+
+        assert_eq!(err.render(source), expected);
+    }
+}
@@ -1,8 +1,10 @@
-use crate::env::{Env, Eval};
-use crate::error::ParseError;
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, ParseErrorKind, Span};
+use crate::expr::func::FuncVal;
 use crate::expr::Expr;
 use crate::utils::{self, kwords};
-use crate::val::Val;
+use crate::val::{DynFunc, Val};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
@@ -11,11 +13,17 @@ pub enum Mode {
     Set,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Clone)]
 pub struct BindingUpdate {
     pub(crate) name: String,
     pub(crate) val: Expr,
     pub(crate) mode: Mode,
+    /// Where the binding lives, if resolved to a local.
+    pub(crate) slot: Option<Slot>,
+    /// Length of the input remaining at the name, resolved into `span` once
+    /// the whole program is parsed.
+    pub(crate) rem: usize,
+    pub(crate) span: Option<Span>,
 }
 
 impl BindingUpdate {
@@ -29,10 +37,11 @@ impl BindingUpdate {
         } else if let Ok(s) = utils::tag(kwords::SET, s) {
             (s, Mode::Set)
         } else {
-            return Err(ParseError::ExpectedBindingUpdate);
+            return Err(ParseErrorKind::ExpectedBindingUpdate.at(s));
         };
 
         let (s, _) = utils::extract_whitespace(s);
+        let rem = s.len();
         let (s, name) = utils::extract_ident(s)?;
 
         let (s, _) = utils::extract_whitespace(s);
@@ -41,7 +50,7 @@ impl BindingUpdate {
         // as of now two update separators concatenated without whitespace
         // should instead be parsed as equality check.
         if s.starts_with(kwords::UPDATE_SEP) {
-            return Err(ParseError::UnexpectedEquals);
+            return Err(ParseErrorKind::UnexpectedEquals.at(s));
         }
 
         let (s, _) = utils::extract_whitespace(s);
@@ -53,21 +62,80 @@ impl BindingUpdate {
                 name: name.to_string(),
                 val,
                 mode,
+                slot: None,
+                rem,
+                span: None,
             },
         ))
     }
 }
 
+// like for `BindingUsage`, the position is left out of comparisons.
+impl PartialEq for BindingUpdate {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.val == other.val
+            && self.mode == other.mode
+            && self.slot == other.slot
+    }
+}
+
+impl fmt::Debug for BindingUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BindingUpdate")
+            .field("name", &self.name)
+            .field("val", &self.val)
+            .field("mode", &self.mode)
+            .field("slot", &self.slot)
+            .finish()
+    }
+}
+
+impl BindingUpdate {
+    fn store_local(&self, env: &mut Env, value: Val) {
+        match self.slot {
+            Some(slot) => env.store_local(slot.index, value),
+            None => env.store_binding(self.name.clone(), value),
+        }
+    }
+
+    /// Binds a local function such that it can capture, and so call, itself.
+    fn eval_local_func(&self, env: &mut Env) -> Result<Val, Val> {
+        self.store_local(env, Val::Unit);
+
+        let mut value = env.eval(&self.val)?;
+        let own_cell = match &mut value {
+            Val::Func(DynFunc(df)) => df
+                .as_any_mut()
+                .downcast_mut::<FuncVal>()
+                .and_then(|func_val| func_val.weaken_capture(&self.name)),
+            _ => None,
+        };
+
+        match own_cell {
+            Some(rc) => *rc.borrow_mut() = value,
+            None => self.store_local(env, value),
+        }
+
+        Ok(Val::Unit)
+    }
+}
+
 impl Eval for BindingUpdate {
     fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        if let (Mode::CreateLocal, Expr::Func(_)) = (&self.mode, &self.val) {
+            return self.eval_local_func(env);
+        }
+
         let value = env.eval(&self.val)?;
         match value {
-            Val::Break(_) => Ok(value),
+            Val::Break(_) | Val::Return(_) => Ok(value),
             _ => {
-                match self.mode {
-                    Mode::Set => env.set_binding(&self.name, value)?,
-                    Mode::CreateLocal => env.store_binding(self.name.clone(), value),
-                    Mode::CreateGlobal => env.store_global(self.name.clone(), value),
+                match (&self.mode, self.slot) {
+                    (Mode::Set, Some(slot)) => env.set_local(slot, &self.name, value)?,
+                    (Mode::Set, None) => env.set_binding(&self.name, value)?,
+                    (Mode::CreateLocal, _) => self.store_local(env, value),
+                    (Mode::CreateGlobal, _) => env.store_global(self.name.clone(), value),
                 }
 
                 Ok(Val::Unit)
@@ -110,6 +178,9 @@ mod tests {
                     name: "a".to_string(),
                     val: Expr::Literal(Literal(Val::Number(10))),
                     mode: Mode::Set,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 },
             )),
         );
@@ -128,7 +199,10 @@ mod tests {
                         rhs: Box::new(Expr::Literal(Literal(Val::Number(2)))),
                         op: Op::Div,
                     },
-                    mode: Mode::CreateLocal
+                    mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 },
             )),
         );
@@ -144,6 +218,9 @@ mod tests {
                     name: "aaa".to_string(),
                     val: Expr::Literal(Literal(Val::Number(1))),
                     mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 }
             )),
         );
@@ -1,29 +1,60 @@
-use crate::env::{Env, Eval};
-use crate::error::ParseError;
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, Span};
 use crate::utils;
 use crate::val::Val;
+use std::fmt;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Clone)]
 pub struct BindingUsage {
     pub(crate) name: String,
+    pub(crate) slot: Option<Slot>,
+    /// Length of the input remaining at the name, resolved into `span` once
+    /// the whole program is parsed.
+    pub(crate) rem: usize,
+    pub(crate) span: Option<Span>,
 }
 
 impl BindingUsage {
     pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let rem = s.len();
         let (s, name) = utils::extract_ident(s)?;
 
         Ok((
             s,
             Self {
                 name: name.to_string(),
+                slot: None,
+                rem,
+                span: None,
             },
         ))
     }
 }
 
+// where a name is used doesn't change what it refers to, so the position is
+// left out of comparisons, including the ones between functions going by their
+// debug output.
+impl PartialEq for BindingUsage {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.slot == other.slot
+    }
+}
+
+impl fmt::Debug for BindingUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BindingUsage")
+            .field("name", &self.name)
+            .field("slot", &self.slot)
+            .finish()
+    }
+}
+
 impl Eval for BindingUsage {
     fn eval(&self, env: &mut Env) -> Result<Val, Val> {
-        Ok(env.get_binding(&self.name)?)
+        match self.slot {
+            Some(slot) => Ok(env.get_local(slot, &self.name)?),
+            None => Ok(env.get_binding(&self.name)?),
+        }
     }
 }
 
@@ -48,6 +79,9 @@ mod tests {
                 "",
                 BindingUsage {
                     name: "abc".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 },
             )),
         );
@@ -60,6 +94,9 @@ mod tests {
         assert_eq!(
             empty_env.eval(&BindingUsage {
                 name: "i_dont_exist".to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
             }),
             Err(RuntimeError::NoBinding("i_dont_exist".into()).into())
         );
@@ -1,5 +1,6 @@
-use crate::env::{Env, Eval};
+use crate::env::{Env, Eval, Slot};
 use crate::error::ParseError;
+use crate::expr::binding_update::Mode;
 use crate::expr::{Expr, Literal};
 use crate::utils::{self, kwords};
 use crate::val::Val;
@@ -27,14 +28,34 @@ impl Block {
         Self::strong_implicit(s)
     }
 
+    pub(crate) fn visit_mut(&mut self, f: &mut dyn FnMut(&mut Expr)) {
+        self.walk(f, true);
+    }
+
+    /// Visits the expressions of the block, leaving out the bodies of the
+    /// functions defined in it unless `funcs` is set.
+    pub(crate) fn walk(&mut self, f: &mut dyn FnMut(&mut Expr), funcs: bool) {
+        for expr in &mut self.exprs {
+            expr.walk(f, funcs);
+        }
+    }
+
     fn strong_implicit(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
 
         let mut s = s;
         let mut exprs = Vec::new();
         let mut trailing_sep = false;
+        let mut expr_err = None;
 
-        while let Ok((new_s, expr)) = Expr::new(s) {
+        loop {
+            let (new_s, expr) = match Expr::new(s) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    expr_err = Some(e);
+                    break;
+                }
+            };
             exprs.push(expr);
 
             let (new_s, _) = utils::extract_whitespace(new_s);
@@ -59,40 +80,58 @@ impl Block {
             exprs.push(Expr::Literal(Literal(Val::Unit)));
         }
 
+        // a statement that failed to parse past its beginning is usually a
+        // better hint than the missing block close.
         let (s, _) = utils::extract_whitespace(s);
-        let s = utils::tag(kwords::BLOCK_CLOSE, s)?;
+        let s = utils::tag(kwords::BLOCK_CLOSE, s).map_err(|e| match expr_err {
+            Some(expr_err) => expr_err.furthest(e),
+            None => e,
+        })?;
 
         Ok((s, Block { exprs }))
     }
 }
-impl Eval for Block {
-    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
-        let len = self.exprs.len();
+impl Block {
+    /// Locals declared directly in the block that were resolved to slots.
+    pub(crate) fn resolved_locals(&self) -> impl Iterator<Item = (&str, Slot)> {
+        self.exprs.iter().filter_map(|expr| match expr {
+            Expr::BindingUpdate(bu) if bu.mode == Mode::CreateLocal => {
+                Some((bu.name.as_str(), bu.slot?))
+            }
+            _ => None,
+        })
+    }
 
-        if len == 0 {
-            Ok(Val::Unit)
-        } else {
-            env.push();
-
-            for expr in &self.exprs[0..len - 1] {
-                let intermediate = match env.eval(expr) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        env.pop();
-                        return Err(e);
-                    }
-                };
-                if let Val::Break(_) = &intermediate {
-                    env.pop();
-                    return Ok(intermediate);
-                }
+    /// Evaluates the expressions in the innermost frame, stopping at a break
+    /// or a return.
+    pub(crate) fn eval_in_frame(&self, env: &mut Env) -> Result<Val, Val> {
+        let (last, init) = match self.exprs.split_last() {
+            Some(split) => split,
+            None => return Ok(Val::Unit),
+        };
+
+        for expr in init {
+            let intermediate = env.eval(expr)?;
+            if let Val::Break(_) | Val::Return(_) = &intermediate {
+                return Ok(intermediate);
             }
+        }
 
-            let result = env.eval(&self.exprs[len - 1]);
-            env.pop();
+        env.eval(last)
+    }
+}
 
-            result
+impl Eval for Block {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        if self.exprs.is_empty() {
+            return Ok(Val::Unit);
         }
+
+        env.push();
+        let result = self.eval_in_frame(env);
+        env.pop();
+
+        result
     }
 }
 
@@ -116,7 +155,7 @@ impl<'a> crate::expr::Format for FormatImplicit<'a> {
             if i != exprs.len() - 1 || trailing_sep {
                 writeln!(w, " {}", kwords::EXPR_SEP)?;
             } else {
-                writeln!(w, "")?;
+                writeln!(w)?;
             }
         }
         Self::indent(w, depth)?;
@@ -140,14 +179,12 @@ impl<'a> crate::expr::Format for FormatExplicit<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ParseErrorKind;
     use crate::expr::{binding_update::Mode, BindingUpdate, BindingUsage, Op};
 
     #[test]
     fn parse_empty_block() {
-        assert_eq!(
-            Block::implicit("🧑‍🦲"),
-            Ok(("", Block { exprs: Vec::new() }))
-        );
+        assert_eq!(Block::implicit("🧑‍🦲"), Ok(("", Block { exprs: Vec::new() })));
         assert_eq!(
             Block::implicit("📦 🧑‍🦲"),
             Ok(("", Block { exprs: Vec::new() }))
@@ -175,15 +212,15 @@ mod tests {
     fn parse_block_missing_token() {
         assert_eq!(
             Block::implicit("📦"),
-            Err(ParseError::ExpectedTag("🧑‍🦲".into()))
+            Err(ParseErrorKind::ExpectedTag("🧑‍🦲").at(""))
         );
         assert_eq!(
             Block::explicit("📦"),
-            Err(ParseError::ExpectedTag("🧑‍🦲".into()))
+            Err(ParseErrorKind::ExpectedTag("🧑‍🦲").at(""))
         );
         assert_eq!(
             Block::explicit("🧑‍🦲"),
-            Err(ParseError::ExpectedTag("📦".into()))
+            Err(ParseErrorKind::ExpectedTag("📦").at("🧑‍🦲"))
         );
     }
 
@@ -223,7 +260,7 @@ mod tests {
             },
         ];
 
-        for (block, res_expr) in blocks.into_iter().zip(res_exprs.into_iter()) {
+        for (block, res_expr) in blocks.into_iter().zip(res_exprs) {
             assert_eq!(
                 block,
                 Ok((
@@ -253,16 +290,28 @@ mod tests {
                     name: "a".to_string(),
                     val: Expr::Literal(Literal(Val::Number(10))),
                     mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })),
                 Expr::BindingUpdate(Box::new(BindingUpdate {
                     name: "b".to_string(),
                     val: Expr::BindingUsage(BindingUsage {
                         name: "a".to_string(),
+                        slot: None,
+                        rem: 0,
+                        span: None,
                     }),
                     mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })),
                 Expr::BindingUsage(BindingUsage {
                     name: "b".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 }),
             ],
         };
@@ -278,10 +327,20 @@ mod tests {
             exprs: vec![Expr::Block(Block {
                 exprs: vec![Expr::Operation {
                     lhs: Box::new(Expr::Block(Block {
-                        exprs: vec![Expr::BindingUsage(BindingUsage { name: "a".into() })],
+                        exprs: vec![Expr::BindingUsage(BindingUsage {
+                            name: "a".into(),
+                            slot: None,
+                            rem: 0,
+                            span: None,
+                        })],
                     })),
                     rhs: Box::new(Expr::Block(Block {
-                        exprs: vec![Expr::BindingUsage(BindingUsage { name: "b".into() })],
+                        exprs: vec![Expr::BindingUsage(BindingUsage {
+                            name: "b".into(),
+                            slot: None,
+                            rem: 0,
+                            span: None,
+                        })],
                     })),
                     op: Op::Add,
                 }],
@@ -297,10 +356,20 @@ mod tests {
 
         let expected = Expr::Operation {
             lhs: Box::new(Expr::Block(Block {
-                exprs: vec![Expr::BindingUsage(BindingUsage { name: "a".into() })],
+                exprs: vec![Expr::BindingUsage(BindingUsage {
+                    name: "a".into(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
+                })],
             })),
             rhs: Box::new(Expr::Block(Block {
-                exprs: vec![Expr::BindingUsage(BindingUsage { name: "b".into() })],
+                exprs: vec![Expr::BindingUsage(BindingUsage {
+                    name: "b".into(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
+                })],
             })),
             op: Op::Add,
         };
@@ -61,6 +61,9 @@ mod tests {
                     body: Block {
                         exprs: vec![Expr::BindingUsage(BindingUsage {
                             name: "🚀".to_string(),
+                            slot: None,
+                            rem: 0,
+                            span: None,
                         })],
                     },
                 },
@@ -72,6 +75,9 @@ mod tests {
                         exprs: vec![Expr::Operation {
                             lhs: Box::new(Expr::BindingUsage(BindingUsage {
                                 name: "a".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             rhs: Box::new(Expr::Literal(Literal(Val::Number(2)))),
                             op: Op::Div,
@@ -1,5 +1,5 @@
 use crate::env::{Env, Eval};
-use crate::error::ParseError;
+use crate::error::{ParseError, Span, TraceFrame};
 use crate::expr::Expr;
 use crate::utils::{self, kwords};
 use crate::val::Val;
@@ -8,11 +8,16 @@ use crate::val::Val;
 pub struct Call {
     pub(crate) func: Expr,
     pub(crate) args: Vec<Expr>,
+    /// Length of the input remaining at the call, resolved into `span` once
+    /// the whole program is parsed.
+    pub(crate) rem: usize,
+    pub(crate) span: Option<Span>,
 }
 
 impl Call {
     pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
+        let rem = s.len();
         let s = utils::tag(kwords::CALL, s)?;
 
         let (s, func) = Expr::new(s)?;
@@ -21,37 +26,87 @@ impl Call {
         let mut s = s;
         let mut args = Vec::new();
 
-        while let Ok((new_s, arg_val_e)) = Expr::new(s) {
-            let (new_s, _) = utils::extract_whitespace(new_s);
-            s = new_s;
-            args.push(arg_val_e);
+        loop {
+            match Expr::new(s) {
+                Ok((new_s, arg_val_e)) => {
+                    let (new_s, _) = utils::extract_whitespace(new_s);
+                    s = new_s;
+                    args.push(arg_val_e);
+                }
+                // an argument that fails past its first token is malformed
+                // rather than absent.
+                Err(e) if e.progressed_past(s) => return Err(e),
+                Err(_) => break,
+            }
         }
 
-        Ok((s, Call { func, args }))
+        Ok((
+            s,
+            Call {
+                func,
+                args,
+                rem,
+                span: None,
+            },
+        ))
+    }
+}
+
+/// Name the function is reached by at the call site, e.g. `f` or `deque.at`.
+pub(crate) fn callee_name(func: &Expr) -> String {
+    match func {
+        Expr::BindingUsage(usage) => usage.name.clone(),
+        Expr::Index(index) => std::iter::once(callee_name(&index.root))
+            .chain(index.idents.iter().cloned())
+            .collect::<Vec<_>>()
+            .join("."),
+        _ => kwords::FUNC.to_string(),
     }
 }
 
+/// Calls `func` with `args`, recording the frame made by `frame` in the trace
+/// of the `Env` if the callee fails.
+pub(crate) fn call_val(
+    func: &Val,
+    args: &mut [Val],
+    env: &mut Env,
+    frame: impl Fn() -> TraceFrame,
+) -> Result<Val, Val> {
+    func.apply_to_root(|val| -> Result<_, Val> {
+        #[cfg(feature = "web")]
+        if let Val::JsValue(ref jv) = val {
+            Ok(Val::convert_from_jv(jv.clone())
+                .as_func()?
+                .0
+                .call(args, env)
+                .inspect_err(|_| env.push_frame(frame()))?)
+        } else {
+            Ok(val
+                .as_func()?
+                .0
+                .call(args, env)
+                .inspect_err(|_| env.push_frame(frame()))?)
+        }
+        #[cfg(not(feature = "web"))]
+        {
+            val.as_func()?
+                .0
+                .call(args, env)
+                .inspect_err(|_| env.push_frame(frame()))
+        }
+    })?
+}
+
 impl Eval for Call {
     fn eval(&self, env: &mut Env) -> Result<Val, Val> {
         let args: Result<Vec<Val>, _> = self.args.iter().map(|arg| env.eval(arg)).collect();
         let mut args = args?;
 
         let func_owned = env.eval(&self.func)?;
-        func_owned.apply_to_root(|val| -> Result<_, Val> {
-            #[cfg(feature = "web")]
-            if let Val::JsValue(ref jv) = val {
-                Ok(Val::convert_from_jv(jv.clone())
-                    .as_func()?
-                    .0
-                    .call(args.as_mut_slice(), env)?)
-            } else {
-                Ok(val.as_func()?.0.call(args.as_mut_slice(), env)?)
-            }
-            #[cfg(not(feature = "web"))]
-            {
-                val.as_func()?.0.call(args.as_mut_slice(), env)
-            }
-        })?
+        call_val(&func_owned, &mut args, env, || TraceFrame {
+            name: callee_name(&self.func),
+            span: self.span,
+        })
     }
 }
 
@@ -87,13 +142,21 @@ mod tests {
         let expected = Call {
             func: Expr::BindingUsage(BindingUsage {
                 name: "add".to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
             }),
             args: vec![
                 Expr::BindingUsage(BindingUsage {
                     name: "a".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 }),
                 Expr::Literal(Literal(Val::Number(1))),
             ],
+            rem: "📞 add a 1".len(),
+            span: None,
         };
 
         assert_eq!(call_e, Ok(("", expected)));
@@ -105,13 +168,21 @@ mod tests {
         let expected = Call {
             func: Expr::BindingUsage(BindingUsage {
                 name: "add".to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
             }),
             args: vec![
                 Expr::BindingUsage(BindingUsage {
                     name: "a".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 }),
                 Expr::Literal(Literal(Val::Number(1))),
             ],
+            rem: "📞 add a 1 💪 👶 a = 2".len(),
+            span: None,
         };
 
         assert_eq!(call_e, Ok(("💪 👶 a = 2", expected)));
@@ -178,6 +249,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn eval_error_trace() {
+        let code = "📦
+    👶 inner = 🧰 ➡️ x 🧑‍🦲 💪
+    👶 outer = 🧰 ➡️ 📞 inner 🧑‍🦲 💪
+    📞 outer
+🧑‍🦲";
+        let expected = vec![
+            ("inner".to_string(), Val::Number(3), Val::Number(19)),
+            ("outer".to_string(), Val::Number(4), Val::Number(5)),
+        ];
+
+        // uncaught errors leave their frames in the env.
+        let (_, expr_e) = Expr::parse(code).unwrap();
+        let mut env = Env::test();
+        let err = env.eval(&expr_e).unwrap_err();
+        let frames: Vec<_> = env
+            .take_trace()
+            .into_iter()
+            .map(|frame| {
+                let span = frame.span.unwrap();
+                let (line, column) = (span.line as i32, span.column as i32);
+                (frame.name, Val::Number(line), Val::Number(column))
+            })
+            .collect();
+
+        assert_eq!(
+            err.as_object().unwrap().0.member("type"),
+            Ok(Val::from("NoBinding"))
+        );
+        assert_eq!(frames, expected);
+        assert!(!err.to_string().contains("inner"));
+
+        // caught ones carry them as their `trace`.
+        let caught = format!("👩‍🚒 {} 🤡 e ➡️ e 🧑‍🦲", code);
+        let (_, expr_e) = Expr::parse(&caught).unwrap();
+        let err = env.eval(&expr_e).unwrap();
+        let err = err.as_object().unwrap();
+
+        assert_eq!(err.0.member("type"), Ok(Val::from("NoBinding")));
+        assert!(env.take_trace().is_empty());
+
+        let trace = err.0.member("trace").unwrap();
+        let frames: Vec<_> = trace
+            .as_deque()
+            .unwrap()
+            .iter()
+            .map(|frame| {
+                let frame = frame.as_object().unwrap();
+                let name = frame.0.member("name").unwrap().to_string();
+                let line = frame.0.member("line").unwrap();
+                let column = frame.0.member("column").unwrap();
+
+                (name, line, column)
+            })
+            .collect();
+
+        assert_eq!(frames, expected);
+        assert!(!err.to_string().contains("inner"));
+    }
+
+    #[test]
+    fn callee_names() {
+        let name = |code| match Expr::new(code).unwrap() {
+            (_, Expr::Call(call_e)) => callee_name(&call_e.func),
+            _ => panic!("not a call"),
+        };
+
+        assert_eq!(name("📞 f 1"), "f");
+        assert_eq!(name("📞 deque🪆at d 1"), "deque.at");
+        assert_eq!(name("📞 📦🧰 ➡️ 1 🧑‍🦲🧑‍🦲"), "🧰");
+    }
+
     #[test]
     fn format() {
         let (_, call_e) = Call::new("📞add a    1").unwrap();
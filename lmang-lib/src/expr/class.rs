@@ -1,73 +1,327 @@
-use crate::env::{Env, Eval};
-use crate::error::{ParseError, RuntimeError};
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, RuntimeError, TraceFrame};
 use crate::expr::block::{Block, FormatImplicit};
-use crate::expr::func::FuncVal;
+use crate::expr::call;
+use crate::expr::func::{FuncVal, Parent};
+use crate::expr::Expr;
 use crate::utils::{self, kwords};
 use crate::val::{DynFunc, Object, Val, WeakWrapper};
+use crate::vm::VmFunc;
+use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
 
+/// Name the base of a derived class is bound to in its body.
+pub(crate) const BASE: &str = "👴";
+
+type Members = HashMap<String, Val, ahash::RandomState>;
+
 #[derive(Clone, Debug, PartialEq)]
-pub struct Class(pub(crate) Block);
+pub struct Class {
+    /// Object or constructor the class derives from.
+    pub(crate) base: Option<Expr>,
+    pub(crate) body: Block,
+    /// Slot of the base in the body, once resolved.
+    pub(crate) slot: Option<Slot>,
+}
 
 impl Class {
     pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
         let s = utils::tag(kwords::CLASS, s)?;
 
-        let (s, block_e) = Block::implicit(s)?;
+        let (new_s, _) = utils::extract_whitespace(s);
+        let (s, base) = match utils::tag(kwords::INHERIT, new_s) {
+            Ok(new_s) => {
+                let (new_s, base) = Expr::new(new_s)?;
+                (new_s, Some(base))
+            }
+            Err(_) => (s, None),
+        };
 
-        Ok((s, Class(block_e)))
+        let (s, body) = Block::implicit(s)?;
+        let class_e = Class {
+            base,
+            body,
+            slot: None,
+        };
+
+        Ok((s, class_e))
     }
 }
 
 impl Eval for Class {
     fn eval(&self, env: &mut Env) -> Result<Val, Val> {
-        env.eval(&self.0)?;
-
-        let mut members = env.take_last_popped().unwrap();
-        for (_, v) in members.iter_mut() {
-            let owned_v = std::mem::replace(v, Val::Unit);
-            *v = Val::Ref(Rc::new(RefCell::new(owned_v)));
+        let base = match &self.base {
+            Some(base) => {
+                let base = env.eval(base)?;
+                Some(base_object(base, env)?)
+            }
+            None => None,
+        };
+
+        env.push();
+        if let Some(base) = base {
+            match self.slot {
+                Some(slot) => env.store_local(slot.index, base),
+                None => env.store_binding(BASE.to_string(), base),
+            }
         }
-
-        let frozen = members.clone();
-
-        for (key, v) in members.iter_mut() {
-            if let Val::Ref(r) = v {
-                let mut b = r.borrow_mut();
-
-                if let Val::Func(DynFunc(df)) = &mut *b {
-                    if let Some(func_val) = df.as_any_mut().downcast_mut::<FuncVal>() {
-                        let mut subenv = frozen.clone();
-                        let self_val = subenv.remove(key).unwrap();
-                        let self_rc = self_val.as_val_ref()?;
-                        let weak_val = Val::Weak(WeakWrapper(Rc::downgrade(self_rc)));
-                        subenv.insert(key.clone(), weak_val);
-                        func_val.parent = Some(subenv);
-                    }
-                }
+        let result = self.body.eval_in_frame(env);
+        let (mut members, mut slots) = env.pop_frame();
+        result?;
+
+        let base_binding = match (&self.base, self.slot) {
+            (None, _) => None,
+            (Some(_), Some(slot)) => slots.get_mut(slot.index).and_then(Option::take),
+            (Some(_), None) => members.remove(BASE),
+        };
+        for (name, slot) in self.body.resolved_locals() {
+            if let Some(val) = slots.get_mut(slot.index).and_then(Option::take) {
+                members.insert(name.to_string(), val);
             }
         }
 
-        Ok(Val::from_obj(ClassObject { members }))
+        let class_obj = match base_binding {
+            Some(base_binding) => ClassObject::derive(base_binding, members)?,
+            None => ClassObject::new(members)?,
+        };
+
+        Ok(Val::from_obj(class_obj))
     }
 }
 
 impl crate::expr::Format for Class {
     fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
-        write!(w, "{}\n", kwords::CLASS)?;
-        FormatImplicit(&self.0).format(w, depth)?;
+        write!(w, "{}", kwords::CLASS)?;
+        if let Some(base) = &self.base {
+            write!(w, " {} ", kwords::INHERIT)?;
+            base.format(w, depth)?;
+        }
+        writeln!(w)?;
+        FormatImplicit(&self.body).format(w, depth)?;
 
         Ok(())
     }
 }
 
+/// Object a class derives from `base`, made by calling it if it's a
+/// constructor.
+pub(crate) fn base_object(base: Val, env: &mut Env) -> Result<Val, Val> {
+    let base = match base.apply_to_root(Val::clone)? {
+        Val::Func(DynFunc(constructor)) => constructor.call(&mut [], env)?,
+        base => base,
+    };
+    let base = base.apply_to_root(Val::clone)?;
+    base.as_object()?;
+
+    Ok(base)
+}
+
+/// Member `name` of `val` if it's an object made by a class.
+pub(crate) fn class_member(val: &Val, name: &str) -> Option<Val> {
+    // plain values are far more common here, and `as_object` would build an
+    // error for each.
+    let mut obj = val
+        .apply_to_root(|root| match root {
+            Val::Object(obj) => Some(obj.clone()),
+            _ => None,
+        })
+        .ok()??;
+    let class_obj = obj.0.as_any_mut().downcast_mut::<ClassObject>()?;
+
+    class_obj.members.get(name).cloned()
+}
+
+// members of class objects with a special meaning. They're named by emoji no
+// ordinary member is expected to use, so that existing methods don't take
+// over by accident.
+
+/// Shows the object as a string, either a value or a method making one.
+pub(crate) const SHOW: &str = "🖼️";
+
+/// Methods overloading the operators, called with both operands, the left one
+/// first. `!=` negates the result of `EQ`.
+pub(crate) const ADD: &str = "➕";
+pub(crate) const SUB: &str = "➖";
+pub(crate) const MUL: &str = "✖️";
+pub(crate) const DIV: &str = "➗";
+pub(crate) const LT: &str = "◀️";
+pub(crate) const GT: &str = "▶️";
+pub(crate) const EQ: &str = "🟰";
+pub(crate) const FUZZY_EQ: &str = "〰️";
+
+/// How `val` shows up as a string, through its `SHOW` member if it's a class
+/// object with one. Methods are called in `env`, passing on their errors.
+pub fn show(val: &Val, env: &mut Env) -> Result<String, Val> {
+    let shown = match class_member(val, SHOW) {
+        Some(member) if member.apply_to_root(|m| matches!(m, Val::Func(_)))? => {
+            call::call_val(&member, &mut [], env, || TraceFrame {
+                name: SHOW.to_string(),
+                span: None,
+            })?
+        }
+        Some(member) => member,
+        None => val.clone(),
+    };
+
+    Ok(shown.apply_to_root(|v| v.to_string())?)
+}
+
+/// How the `SHOW` member of `obj` displays it, if it's a plain value. Methods
+/// need an environment to run in, so only `show` calls them.
+pub(crate) fn display(obj: &dyn Object) -> Option<String> {
+    match obj.member(SHOW).ok()?.apply_to_root(Val::clone).ok()? {
+        Val::Func(_) => None,
+        shown => shown.apply_to_root(|v| v.to_string()).ok(),
+    }
+}
+
+/// Object the class of `val` derives from, if any.
+pub(crate) fn base_of(val: &mut Val) -> Result<Option<Val>, RuntimeError> {
+    let obj = val.as_object_mut()?;
+    let class_obj = obj.0.as_any_mut().downcast_mut::<ClassObject>();
+
+    Ok(class_obj.and_then(|class_obj| class_obj.base.clone()))
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct ClassObject {
-    members: HashMap<String, Val, ahash::RandomState>,
+    members: Members,
+    base: Option<Val>,
+}
+
+impl ClassObject {
+    /// Makes an object out of the bindings of a class body. Members are shared
+    /// between copies of the object and methods see their sibling members.
+    pub(crate) fn new(members: Members) -> Result<Self, RuntimeError> {
+        let members: Members = members
+            .into_iter()
+            .map(|(name, v)| (name, Val::Ref(Rc::new(RefCell::new(v)))))
+            .collect();
+        bind_methods(&members, &members)?;
+
+        Ok(ClassObject {
+            members,
+            base: None,
+        })
+    }
+
+    /// Makes an object out of the bindings of the body of a class deriving
+    /// from the object `base_binding` holds. Members of the base are copied
+    /// unless the body overrides them, and inherited methods see the members
+    /// of the new object.
+    ///
+    /// Methods of the body reach the base through the binding, which ends up
+    /// holding the base with its methods seeing the new object's members too.
+    pub(crate) fn derive(base_binding: Val, members: Members) -> Result<Self, RuntimeError> {
+        let base = base_binding.apply_to_root(Val::clone)?;
+        let base_obj = base.as_object()?;
+
+        let mut all = Members::default();
+        for name in base_obj.0.member_names() {
+            if !members.contains_key(&name) {
+                let val = base_obj.0.member(&name)?.apply_to_root(Val::clone)?;
+                all.insert(name, val);
+            }
+        }
+        let inherited: Vec<_> = all.keys().cloned().collect();
+        all.extend(members);
+
+        let mut class_obj = Self::new(all)?;
+        for name in &inherited {
+            if let Val::Ref(cell) = &class_obj.members[name] {
+                rebind_base(&mut cell.borrow_mut(), &class_obj.members)?;
+            }
+        }
+
+        if let Val::Ref(cell) = base_binding {
+            let base_view = view(base.clone(), &class_obj.members)?;
+            *cell.borrow_mut() = base_view;
+        }
+
+        class_obj.base = Some(base);
+        Ok(class_obj)
+    }
+}
+
+/// The object `base` with its methods seeing the members of `object`, which
+/// derives from it, instead of its own.
+fn view(mut base: Val, object: &Members) -> Result<Val, RuntimeError> {
+    let mut members = Members::default();
+    let base_obj = base.as_object()?;
+    for name in base_obj.0.member_names() {
+        let mut val = base_obj.0.member(&name)?.apply_to_root(Val::clone)?;
+        let member = match (&val, object.get(&name)) {
+            (Val::Func(_), _) | (_, None) => {
+                rebind_base(&mut val, object)?;
+                Val::Ref(Rc::new(RefCell::new(val)))
+            }
+            (_, Some(shared)) => shared.clone(),
+        };
+        members.insert(name, member);
+    }
+    bind_methods(&members, object)?;
+
+    let base = base_of(&mut base)?;
+    Ok(Val::from_obj(ClassObject { members, base }))
+}
+
+/// Points the base an inherited method reaches at a view seeing the members of
+/// `object`, so that overrides further up the chain see them too.
+fn rebind_base(val: &mut Val, object: &Members) -> Result<(), RuntimeError> {
+    let parent = match val {
+        Val::Func(DynFunc(df)) => match method_parent(df.as_any_mut()) {
+            Some(Some(parent)) => Rc::make_mut(parent),
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    if let Some(base_binding) = parent.get(BASE) {
+        let base = base_binding.apply_to_root(Val::clone)?;
+        if base.as_object().is_ok() {
+            let base_view = view(base, object)?;
+            parent.insert(BASE.to_string(), Val::Ref(Rc::new(RefCell::new(base_view))));
+        }
+    }
+
+    Ok(())
+}
+
+/// Lets the methods among `members` see the members of `object`, with
+/// their own entry held weakly.
+fn bind_methods(members: &Members, object: &Members) -> Result<(), RuntimeError> {
+    for (key, v) in members.iter() {
+        if let Val::Ref(r) = v {
+            let mut b = r.borrow_mut();
+
+            if let Val::Func(DynFunc(df)) = &mut *b {
+                if let Some(parent) = method_parent(df.as_any_mut()) {
+                    let mut subenv = object.clone();
+                    if let Some(self_val) = subenv.remove(key) {
+                        let self_rc = self_val.as_val_ref()?;
+                        let weak_val = Val::Weak(WeakWrapper(Rc::downgrade(self_rc)));
+                        subenv.insert(key.clone(), weak_val);
+                    }
+                    Rc::make_mut(parent.get_or_insert_with(Parent::default)).extend(subenv);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Captured bindings of a user-defined function, tree-walked or compiled.
+fn method_parent(func: &mut dyn Any) -> Option<&mut Option<Parent>> {
+    if func.is::<FuncVal>() {
+        func.downcast_mut::<FuncVal>().map(|f| &mut f.parent)
+    } else {
+        func.downcast_mut::<VmFunc>().map(|f| &mut f.parent)
+    }
 }
 
 impl Object for ClassObject {
@@ -93,30 +347,56 @@ impl Object for ClassObject {
     fn name(&self) -> &str {
         "user-defined"
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_member(&self, name: &str, val: Val) -> Result<(), RuntimeError> {
+        match self.members.get(name) {
+            Some(Val::Ref(rc)) => {
+                *rc.borrow_mut() = val;
+                Ok(())
+            }
+            _ => Err(RuntimeError::NoKey(name.to_string())),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::expr::{binding_update::Mode, BindingUpdate, Expr, Literal};
+    use crate::expr::{binding_update::Mode, BindingUpdate, BindingUsage, Expr, Literal};
+    use crate::test_utils::eval_resolved;
 
     #[test]
     fn parse_class_empty() {
         let class_e = Class::new("🧑‍🏫 🧑‍🦲");
-        let expected = Class(Block { exprs: Vec::new() });
+        let expected = Class {
+            base: None,
+            body: Block { exprs: Vec::new() },
+            slot: None,
+        };
         assert_eq!(class_e, Ok(("", expected)));
     }
 
     #[test]
     fn parse_class_var() {
         let class_e = Class::new("🧑‍🏫 👶 x = 0 🧑‍🦲");
-        let expected = Class(Block {
-            exprs: vec![Expr::BindingUpdate(Box::new(BindingUpdate {
-                name: "x".to_string(),
-                val: Expr::Literal(Literal(Val::Number(0))),
-                mode: Mode::CreateLocal,
-            }))],
-        });
+        let expected = Class {
+            base: None,
+            body: Block {
+                exprs: vec![Expr::BindingUpdate(Box::new(BindingUpdate {
+                    name: "x".to_string(),
+                    val: Expr::Literal(Literal(Val::Number(0))),
+                    mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
+                }))],
+            },
+            slot: None,
+        };
 
         assert_eq!(class_e, Ok(("", expected)));
     }
@@ -225,6 +505,148 @@ mod tests {
         assert_eq!(result, Ok(Val::Number(4)));
     }
 
+    #[test]
+    fn parse_class_base() {
+        let class_e = Class::new("🧑‍🏫 🧬 Base 👶 x = 0 🧑‍🦲");
+        let expected = Class {
+            base: Some(Expr::BindingUsage(BindingUsage {
+                name: "Base".to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
+            })),
+            body: Block {
+                exprs: vec![Expr::BindingUpdate(Box::new(BindingUpdate {
+                    name: "x".to_string(),
+                    val: Expr::Literal(Literal(Val::Number(0))),
+                    mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
+                }))],
+            },
+            slot: None,
+        };
+
+        assert_eq!(class_e, Ok(("", expected)));
+    }
+
+    #[test]
+    fn derive_copies_members() {
+        let input = "📦
+                👶 a = 🧑‍🏫 👶 x = 1 💪 👶 inc = 🧰 ➡️ ♻️ x = x + 1 🧑‍🦲 🧑‍🦲 💪
+                👶 b = 🧑‍🏫 🧬 a 👶 y = 2 🧑‍🦲 💪
+                📞 b🪆inc 💪 📞 b🪆inc 💪
+                👶 hundreds = b🪆x * 100 💪
+                👶 tens = b🪆y * 10 💪
+                hundreds + tens + a🪆x
+            🧑‍🦲";
+        assert_eq!(eval_resolved(input), Ok(Val::Number(321)));
+    }
+
+    #[test]
+    fn derive_overrides() {
+        let input = "📦
+                👶 Counter = 🧰 ➡️ 🧑‍🏫
+                    👶 n = 0 💪
+                    👶 step = 1 💪
+                    👶 inc = 🧰 ➡️ ♻️ n = n + step 🧑‍🦲 💪
+                    👶 get = 🧰 ➡️ n 🧑‍🦲
+                🧑‍🦲 🧑‍🦲 💪
+                👶 c = 🧑‍🏫 🧬 Counter
+                    👶 step = 10 💪
+                    👶 get = 🧰 ➡️ 1000 + 📞 👴🪆get 🧑‍🦲
+                🧑‍🦲 💪
+                📞 c🪆inc 💪 📞 c🪆inc 💪
+                📞 c🪆get
+            🧑‍🦲";
+        assert_eq!(eval_resolved(input), Ok(Val::Number(1020)));
+    }
+
+    #[test]
+    fn derive_not_object() {
+        let expected = RuntimeError::CastError {
+            from: "🔢".into(),
+            to: "🧑‍🏫".into(),
+        };
+        assert_eq!(eval_resolved("🧑‍🏫 🧬 5 🧑‍🦲"), Err(expected.into()));
+    }
+
+    const VEC: &str = "👶 Vec = 🧰 x y ➡️ 🧑‍🏫
+            👶 x = x 💪
+            👶 y = y 💪
+            👶 ➕ = 🧰 a b ➡️ 📞 Vec 📦 a🪆x + b🪆x 🧑‍🦲 📦 a🪆y + b🪆y 🧑‍🦲 🧑‍🦲 💪
+            👶 ✖️ = 🧰 k v ➡️ 📞 Vec 📦 k * v🪆x 🧑‍🦲 📦 k * v🪆y 🧑‍🦲 🧑‍🦲 💪
+            👶 🟰 = 🧰 a b ➡️ ❓ a🪆x == b🪆x a🪆y == b🪆y 🧑‍🦲 😡 🙅‍♀️ 🧑‍🦲 🧑‍🦲
+        🧑‍🦲 🧑‍🦲 💪";
+
+    #[test]
+    fn overloaded_ops() {
+        let input = format!(
+            "📦 {}
+                👶 c = 📞 Vec 1 2 + 📞 Vec 3 4 💪
+                c🪆y + c🪆x * 10
+            🧑‍🦲",
+            VEC
+        );
+        let result = eval_resolved(&input).map(|v| v.apply_to_root(Val::clone).unwrap());
+        assert_eq!(result, Ok(Val::Number(46)));
+
+        let input = format!(
+            "📦 {}
+                👶 v = 2 * 📞 Vec 1 2 💪
+                👶 same = v == 📞 Vec 2 4 💪
+                👶 differ = v != 📞 Vec 2 4 💪
+                same 🤝 🚫 differ
+            🧑‍🦲",
+            VEC
+        );
+        assert_eq!(eval_resolved(&input), Ok(Val::Bool(true)));
+    }
+
+    #[test]
+    fn not_overloaded_ops() {
+        let input = format!("📦 {} 📞 Vec 1 2 - 📞 Vec 1 2 🧑‍🦲", VEC);
+        let expected = RuntimeError::CastError {
+            from: "🧑‍🏫".into(),
+            to: "🔢".into(),
+        };
+        assert_eq!(eval_resolved(&input), Err(expected.into()));
+
+        let input = "📦 👶 a = 🧑‍🏫 👶 x = 1 🧑‍🦲 💪 a == a 🧑‍🦲";
+        assert_eq!(eval_resolved(input), Ok(Val::Bool(true)));
+
+        // ordinary methods named after an operation don't overload it.
+        let input = "📦 👶 a = 🧑‍🏫 👶 add = 🧰 l r ➡️ 0 🧑‍🦲 🧑‍🦲 💪 a + 1 🧑‍🦲";
+        let expected = RuntimeError::CastError {
+            from: "🧑‍🏫".into(),
+            to: "🔢".into(),
+        };
+        assert_eq!(eval_resolved(input), Err(expected.into()));
+    }
+
+    #[test]
+    fn display_member() {
+        let (_, class_e) = Expr::new("🧑‍🏫 👶 🖼️ = 🧵point🧵 🧑‍🦲").unwrap();
+        let mut env = Env::test();
+        let obj = env.eval(&class_e).unwrap();
+        assert_eq!(obj.to_string(), "point");
+        assert_eq!(show(&obj, &mut env), Ok("point".to_string()));
+
+        // methods run in the caller's environment, seeing its globals.
+        let (_, class_e) = Expr::new("🧑‍🏫 👶 legs = 4 💪 👶 🖼️ = 🧰 ➡️ legs * pack 🧑‍🦲 🧑‍🦲").unwrap();
+        let obj = env.eval(&class_e).unwrap();
+        env.store_binding("pack".to_string(), Val::Number(3));
+        assert_eq!(show(&obj, &mut env), Ok("12".to_string()));
+        assert!(obj.to_string().starts_with(kwords::CLASS));
+
+        let (_, class_e) = Expr::new("🧑‍🏫 👶 🖼️ = 🧰 ➡️ 🧨 🧵oops🧵 🧑‍🦲 🧑‍🦲 🧑‍🦲").unwrap();
+        let obj = env.eval(&class_e).unwrap();
+        assert_eq!(show(&obj, &mut env), Err(Val::from("oops")));
+
+        assert_eq!(show(&Val::Number(3), &mut env), Ok("3".to_string()));
+    }
+
     #[test]
     fn format() {
         let (_, class_e) = Class::new("🧑‍🏫👶x=0🧑‍🦲").unwrap();
@@ -233,4 +655,13 @@ mod tests {
             "🧑‍🏫\n    👶 x = 0\n🧑‍🦲"
         )
     }
+
+    #[test]
+    fn format_base() {
+        let (_, class_e) = Class::new("🧑‍🏫🧬📦📞Base 1🧑‍🦲👶x=0🧑‍🦲").unwrap();
+        assert_eq!(
+            format!("{}", crate::expr::Display(&class_e)),
+            "🧑‍🏫 🧬 📦\n    📞 Base 1\n🧑‍🦲\n    👶 x = 0\n🧑‍🦲"
+        )
+    }
 }
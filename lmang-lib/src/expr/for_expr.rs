@@ -0,0 +1,324 @@
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, RuntimeError, TraceFrame};
+use crate::expr::block::{Block, FormatImplicit};
+use crate::expr::call;
+use crate::expr::{Expr, Format};
+use crate::utils::{self, kwords};
+use crate::val::{sorted_entries, Object, Val};
+use std::any::Any;
+use std::fmt;
+
+/// Member of iterators returning their next value, or `🏁` once there are
+/// none left.
+pub(crate) const NEXT: &str = "next";
+
+/// Builtin binding of the value ending an iteration, set apart from anything
+/// an iterator could yield.
+pub(crate) const DONE: &str = "🏁";
+
+#[derive(Clone, Debug)]
+struct Done;
+
+impl Object for Done {
+    fn member_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn member(&self, name: &str) -> Result<Val, RuntimeError> {
+        Err(RuntimeError::NoKey(name.into()))
+    }
+
+    fn clone_box(&self) -> Box<dyn Object> {
+        Box::new(self.clone())
+    }
+
+    fn dyn_debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+
+    fn name(&self) -> &str {
+        DONE
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Value `next` returns once an iterator has no values left.
+pub(crate) fn done() -> Val {
+    Val::from_obj(Done)
+}
+
+fn is_done(val: &Val) -> Result<bool, RuntimeError> {
+    val.apply_to_root(|root| match root.clone() {
+        Val::Object(mut obj) => obj.0.as_any_mut().is::<Done>(),
+        _ => false,
+    })
+}
+
+/// Values a loop goes through one at a time.
+pub(crate) enum Iter {
+    /// Values worked out without running any code.
+    Vals(Box<dyn Iterator<Item = Val>>),
+    /// The `next` member of an iterator, called for every value.
+    Next(Val),
+}
+
+impl Iter {
+    pub(crate) fn next(&mut self, env: &mut Env) -> Result<Option<Val>, Val> {
+        match self {
+            Self::Vals(vals) => Ok(vals.next()),
+            Self::Next(next) => {
+                let val = call::call_val(next, &mut [], env, || TraceFrame {
+                    name: NEXT.to_string(),
+                    span: None,
+                })?;
+                if is_done(&val)? {
+                    Ok(None)
+                } else {
+                    Ok(Some(val))
+                }
+            }
+        }
+    }
+}
+
+/// Values of an iterator, taken as they're needed, or else items of a deque,
+/// characters of a string or key-value pairs of a map ordered by key, taken
+/// when the loop starts.
+pub(crate) fn items(val: &Val) -> Result<Iter, RuntimeError> {
+    let next = val.apply_to_root(|root| match root.as_object() {
+        Ok(obj) if obj.0.member_names().iter().any(|name| name == NEXT) => {
+            obj.0.member(NEXT).map(Some)
+        }
+        _ => Ok(None),
+    })??;
+    if let Some(next) = next {
+        return Ok(Iter::Next(next));
+    }
+
+    let items = val.apply_to_root(|root| match root {
+        Val::Map(map) => Ok(sorted_entries(map)
+            .into_iter()
+            .map(|(k, v)| Val::Deque(Box::new([Val::from(k.clone()), v.clone()].into())))
+            .collect()),
+        root => root.to_deque(),
+    })??;
+    Ok(Iter::Vals(Box::new(items.into_iter())))
+}
+
+/// Numbers from `start` up to, but not including, `end`.
+pub(crate) fn range(start: &Val, end: &Val) -> Result<Iter, RuntimeError> {
+    let start = start.apply_to_root(|v| v.as_number().copied())??;
+    let end = end.apply_to_root(|v| v.as_number().copied())??;
+    Ok(Iter::Vals(Box::new((start..end).map(Val::Number))))
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Iterable {
+    Items(Expr),
+    Range(Expr, Expr),
+}
+
+impl Iterable {
+    fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, start) = Expr::new(s)?;
+
+        let (new_s, _) = utils::extract_whitespace(s);
+        match utils::tag(kwords::RANGE, new_s) {
+            Ok(new_s) => {
+                let (new_s, end) = Expr::new(new_s)?;
+                Ok((new_s, Self::Range(start, end)))
+            }
+            Err(_) => Ok((s, Self::Items(start))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct For {
+    pub(crate) var: String,
+    pub(crate) iterable: Iterable,
+    pub(crate) body: Block,
+    /// Slot of `var`, once resolved.
+    pub(crate) slot: Option<Slot>,
+}
+
+impl For {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::FOR, s)?;
+
+        let (s, _) = utils::extract_whitespace(s);
+        let (s, var) = utils::extract_ident(s)?;
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::IN, s)?;
+        let (s, iterable) = Iterable::new(s)?;
+
+        let (s, body) = Block::implicit(s)?;
+        let for_e = For {
+            var: var.to_string(),
+            iterable,
+            body,
+            slot: None,
+        };
+
+        Ok((s, for_e))
+    }
+}
+
+impl Eval for For {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        let mut iter = match &self.iterable {
+            Iterable::Items(expr) => items(&env.eval(expr)?)?,
+            Iterable::Range(start, end) => range(&env.eval(start)?, &env.eval(end)?)?,
+        };
+
+        while let Some(val) = iter.next(env)? {
+            // the variable gets a frame of its own around the body.
+            env.push();
+            match self.slot {
+                Some(slot) => env.store_local(slot.index, val),
+                None => env.store_binding(self.var.clone(), val),
+            }
+            let result = env.eval(&self.body);
+            env.pop();
+
+            match result? {
+                Val::Break(inner_box) => return Ok(*inner_box),
+                ret @ Val::Return(_) => return Ok(ret),
+                _ => {}
+            }
+        }
+
+        Ok(Val::Unit)
+    }
+}
+
+impl Format for For {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        write!(w, "{} {} {} ", kwords::FOR, self.var, kwords::IN)?;
+        match &self.iterable {
+            Iterable::Items(expr) => expr.format(w, depth)?,
+            Iterable::Range(start, end) => {
+                start.format(w, depth)?;
+                write!(w, " {} ", kwords::RANGE)?;
+                end.format(w, depth)?;
+            }
+        }
+        writeln!(w)?;
+        FormatImplicit(&self.body).format(w, depth)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BindingUsage, Display, Literal};
+    use crate::test_utils::{eval, eval_in, iter_env};
+
+    #[test]
+    fn parse_for() {
+        let expected = For {
+            var: "i".to_string(),
+            iterable: Iterable::Range(
+                Expr::Literal(Literal(Val::Number(0))),
+                Expr::BindingUsage(BindingUsage {
+                    name: "n".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
+                }),
+            ),
+            body: Block {
+                exprs: vec![Expr::BindingUsage(BindingUsage {
+                    name: "i".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
+                })],
+            },
+            slot: None,
+        };
+
+        assert_eq!(For::new("🔂 i 📥 0 ⏩ n i 🧑‍🦲"), Ok(("", expected)));
+    }
+
+    #[test]
+    fn eval_range() {
+        let input = "📦 👶 s = 0 💪 🔂 i 📥 1 ⏩ 5 ♻️ s = s + i 🧑‍🦲 💪 s 🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(10)));
+
+        let input = "📦 👶 s = 0 💪 🔂 i 📥 5 ⏩ 1 ♻️ s = s + i 🧑‍🦲 💪 s 🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(0)));
+    }
+
+    #[test]
+    fn eval_items() {
+        let input = "📦 👶 n = 0 💪 🔂 c 📥 🧵abcb🧵 ❓ c == 🔡b🔡 ♻️ n = n + 1 🧑‍🦲 🧑‍🦲 💪 n 🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(2)));
+
+        let input = "📦 👶 s = 🧵abc🧵 💪 👶 t = 0 💪 🔂 c 📥 🔖s ♻️ t = t + 1 🧑‍🦲 💪 t 🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(3)));
+    }
+
+    #[test]
+    fn eval_map_entries() {
+        let input = "📦
+                👶 m = 🗺️ 3 ➡️ 30 💪 1 ➡️ 10 💪 2 ➡️ 20 🧑‍🦲 💪
+                👶 s = 0 💪
+                🔂 e 📥 m
+                    🧩 e 👉 😵‍💫 k 💪 v 🧑‍🦲 ➡️ ♻️ s = v + k + s * 100 🧑‍🦲 🧑‍🦲
+                🧑‍🦲 💪
+                s
+            🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(112233)));
+    }
+
+    #[test]
+    fn eval_break() {
+        let input = "🔂 i 📥 0 ⏩ 10 ❓ 10 < i * i 💔 i 🧑‍🦲 🧑‍🦲 🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(4)));
+
+        let (_, mut expr) = Expr::new(input).unwrap();
+        expr.resolve(&Env::test()).unwrap();
+        assert_eq!(Env::test().eval(&expr), Ok(Val::Number(4)));
+    }
+
+    #[test]
+    fn eval_iterator() {
+        let input = "📦
+                👶 countdown = 🧑‍🏫
+                    👶 n = 3 💪
+                    👶 next = 🧰 ➡️
+                        👶 left = n - 1 💪
+                        ❓ left < 0 🏁 🧑‍🦲 😡 ♻️ n = left 💪 left + 1 🧑‍🦲
+                    🧑‍🦲
+                🧑‍🦲 💪
+                👶 s = 0 💪
+                🔂 i 📥 countdown ♻️ s = i + s * 10 🧑‍🦲 💪
+                s
+            🧑‍🦲";
+        assert_eq!(eval_in(&mut iter_env(), input), Ok(Val::Number(321)));
+    }
+
+    #[test]
+    fn eval_not_iterable() {
+        let expected = RuntimeError::CastError {
+            from: "🔢".into(),
+            to: "😵‍💫😵‍💫".into(),
+        };
+        assert_eq!(eval("🔂 i 📥 5 🧑‍🦲"), Err(expected.into()));
+    }
+
+    #[test]
+    fn format() {
+        let (_, for_e) = Expr::new("🔂 i 📥 0 ⏩ n 📞 f i 🧑‍🦲").unwrap();
+        let expected = "🔂 i 📥 0 ⏩ n\n    📞 f i\n🧑‍🦲";
+
+        assert_eq!(format!("{}", Display(&for_e)), expected);
+    }
+}
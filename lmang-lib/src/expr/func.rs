@@ -1,56 +1,203 @@
-use crate::env::{Env, Eval};
-use crate::error::{ParseError, RuntimeError};
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, ParseErrorKind, RuntimeError};
+use crate::expr::binding_update::Mode;
 use crate::expr::block::{Block, FormatImplicit};
+use crate::expr::Expr;
 use crate::utils::{self, kwords};
-use crate::val::{Callee, Val};
+use crate::val::{Callee, Key, Map, Val, WeakWrapper};
+use crate::vm::{Compiler, Proto, VmFunc};
 use std::any::Any;
-use std::collections::{HashMap, VecDeque};
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Arg {
     Single(String),
+    /// A parameter which can be left out, the expression is evaluated when the
+    /// function is created.
+    Default(String, Expr),
+    /// Collects the positional arguments left over.
     Variadic(String),
+    /// Collects the named arguments matching no parameter into a map.
+    KwVariadic(String),
 }
 
 impl Arg {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            Self::Single(name)
+            | Self::Default(name, _)
+            | Self::Variadic(name)
+            | Self::KwVariadic(name) => name,
+        }
+    }
+
+    /// Whether the parameter can be bound to a single argument, by position or
+    /// by name.
+    fn is_single(&self) -> bool {
+        matches!(self, Self::Single(_) | Self::Default(..))
+    }
+
     pub fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
-        let (s, variadic) = match utils::tag(kwords::VARIADIC, s) {
-            Ok(s) => (s, true),
-            _ => (s, false),
-        };
-        let (s, ident) = utils::extract_ident(s)?;
-
-        let arg = if variadic {
-            Arg::Variadic(ident.into())
-        } else {
-            Arg::Single(ident.into())
-        };
+        if let Ok(s) = utils::tag(kwords::VARIADIC, s) {
+            let (s, ident) = utils::extract_ident(s)?;
+            return Ok((s, Arg::Variadic(ident.into())));
+        }
+        if let Ok(s) = utils::tag(kwords::MAP, s) {
+            let (s, ident) = utils::extract_ident(s)?;
+            return Ok((s, Arg::KwVariadic(ident.into())));
+        }
 
-        Ok((s, arg))
+        let (s, ident) = utils::extract_ident(s)?;
+        let (new_s, _) = utils::extract_whitespace(s);
+        match utils::tag(kwords::UPDATE_SEP, new_s) {
+            Ok(new_s) => {
+                let (new_s, default) = Expr::new(new_s)?;
+                Ok((new_s, Arg::Default(ident.into(), default)))
+            }
+            Err(_) => Ok((s, Arg::Single(ident.into()))),
+        }
     }
 }
 
 impl crate::expr::Format for Arg {
-    fn format(&self, w: &mut dyn std::fmt::Write, _depth: usize) -> std::fmt::Result {
-        let name = match self {
-            Self::Single(s) => s,
-            Self::Variadic(s) => {
-                write!(w, "{}", kwords::VARIADIC)?;
-                s
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        match self {
+            Self::Single(name) => write!(w, "{}", name)?,
+            Self::Default(name, default) => {
+                write!(w, "{} {} ", name, kwords::UPDATE_SEP)?;
+                default.format(w, depth)?;
             }
-        };
-        write!(w, "{}", name)?;
+            Self::Variadic(name) => write!(w, "{}{}", kwords::VARIADIC, name)?,
+            Self::KwVariadic(name) => write!(w, "{}{}", kwords::MAP, name)?,
+        }
 
         Ok(())
     }
 }
 
+/// Whether `args` bind to `params` one by one, by far the most common case.
+pub(crate) fn positional(params: &[Arg], args: &[Val]) -> bool {
+    args.len() == params.len()
+        && params.iter().all(|param| matches!(param, Arg::Single(_)))
+        && args.iter().all(|arg| !matches!(arg, Val::Named(_)))
+}
+
+/// Values of `params` for a call with `args`, in the order of `params`.
+/// Named arguments bind to the parameter of the same name, the others bind in
+/// order. `defaults` holds the values of the parameters with defaults.
+pub(crate) fn bind_args(
+    params: &[Arg],
+    defaults: &[Val],
+    args: &[Val],
+) -> Result<Vec<Val>, RuntimeError> {
+    if positional(params, args) {
+        return Ok(args.to_vec());
+    }
+
+    let mut positional = Vec::new();
+    let mut named = Vec::new();
+    for arg in args {
+        match arg {
+            Val::Named((name, val)) => named.push((name, val.as_ref())),
+            _ => positional.push(arg),
+        }
+    }
+
+    // too many and too few arguments are both reported against the parameters
+    // taking a single argument, with or without a default.
+    let single_n = params.iter().filter(|param| param.is_single()).count();
+    let wrong_args_n = || RuntimeError::WrongArgsN {
+        expected: single_n,
+        received: args.len(),
+    };
+    let variadic = params.iter().any(|param| matches!(param, Arg::Variadic(_)));
+    if positional.len() > single_n && !variadic {
+        return Err(wrong_args_n());
+    }
+
+    let mut positional = positional.into_iter().cloned();
+    let mut bound: Vec<_> = params
+        .iter()
+        .map(|param| match param {
+            Arg::Variadic(_) => Some(Val::Deque(Box::new(positional.by_ref().collect()))),
+            Arg::KwVariadic(_) => None,
+            _ => positional.next(),
+        })
+        .collect();
+
+    let kw_variadic = params
+        .iter()
+        .any(|param| matches!(param, Arg::KwVariadic(_)));
+    let mut kwargs = Map::default();
+    for (name, val) in named {
+        let param = params
+            .iter()
+            .position(|param| param.is_single() && param.name() == name);
+        match param {
+            Some(k) if bound[k].is_none() => bound[k] = Some(val.clone()),
+            None if kw_variadic => {
+                kwargs.insert(Key::Str(name.as_str().into()), val.clone());
+            }
+            _ => return Err(RuntimeError::UnexpectedArg(name.clone())),
+        }
+    }
+
+    let mut defaults = defaults.iter();
+    let mut kwargs = Some(kwargs);
+    let values: Option<Vec<_>> = params
+        .iter()
+        .zip(bound)
+        .map(|(param, val)| match param {
+            Arg::Default(..) => {
+                let default = defaults.next();
+                val.or_else(|| default.cloned())
+            }
+            Arg::KwVariadic(_) => kwargs.take().map(|map| Val::Map(Box::new(map))),
+            _ => val,
+        })
+        .collect();
+
+    values.ok_or_else(wrong_args_n)
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Func {
     pub(crate) args: Vec<Arg>,
     pub(crate) body: Block,
+    /// Names the body refers to besides its arguments, captured from the
+    /// defining scope when the function is created.
+    pub(crate) captures: Vec<String>,
+    /// Where each of `captures` lives in the defining scope, once resolved.
+    pub(crate) resolved: Option<Vec<Slot>>,
+    /// Whether the body yields, making calls return a generator instead of
+    /// running it.
+    pub(crate) generator: bool,
+    /// Whether the body uses 🪞, whose code looks up the locals of the
+    /// function by name, so they can't live in slots.
+    pub(crate) evals: bool,
+    /// Code of a generator, compiled the first time it's created.
+    pub(crate) compiled: Compiled,
+}
+
+/// Compiled code of a function, which follows from the rest of it and so is
+/// left out of comparisons.
+#[derive(Clone, Default)]
+pub(crate) struct Compiled(OnceCell<Rc<Proto>>);
+
+impl PartialEq for Compiled {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for Compiled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Compiled")
+    }
 }
 
 impl Func {
@@ -64,34 +211,146 @@ impl Func {
         let (new_s, _) = utils::extract_whitespace(s);
         s = new_s;
 
-        let mut variadic_found = false;
         while let Ok((new_s, arg)) = Arg::new(s) {
-            if variadic_found {
-                return Err(ParseError::PrematureVariadic);
+            // only the keyword collector can follow the positional one.
+            let misplaced = match args.last() {
+                Some(Arg::KwVariadic(_)) => true,
+                Some(Arg::Variadic(_)) => !matches!(arg, Arg::KwVariadic(_)),
+                _ => false,
+            };
+            if misplaced {
+                return Err(ParseErrorKind::PrematureVariadic.at(s));
             }
 
             s = new_s;
-            variadic_found |= matches!(arg, Arg::Variadic(_));
             args.push(arg);
         }
 
         let (s, _) = utils::extract_whitespace(s);
         let s = utils::tag(kwords::FUNC_SEP, s)?;
 
-        let (s, body) = Block::implicit(s)?;
+        let (s, mut body) = Block::implicit(s)?;
+        let captures = free_names(&mut body, &args);
+        let generator = yields(&mut body);
+        let evals = calls_eval(&mut body);
+
+        Ok((
+            s,
+            Func {
+                args,
+                body,
+                captures,
+                resolved: None,
+                generator,
+                evals,
+                compiled: Compiled::default(),
+            },
+        ))
+    }
+}
+
+fn free_names(body: &mut Block, args: &[Arg]) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    body.visit_mut(&mut |e| match e {
+        Expr::BindingUsage(usage) => {
+            names.insert(usage.name.clone());
+        }
+        Expr::Ref(ref_e) => {
+            names.insert(ref_e.ident.clone());
+        }
+        Expr::BindingUpdate(bu) if bu.mode == Mode::Set => {
+            names.insert(bu.name.clone());
+        }
+        _ => {}
+    });
 
-        Ok((s, Func { args, body }))
+    for arg in args {
+        names.remove(arg.name());
     }
+
+    names.into_iter().collect()
+}
+
+/// Whether `body` yields, leaving out the functions defined in it.
+fn yields(body: &mut Block) -> bool {
+    let mut found = false;
+    body.walk(&mut |e| found |= matches!(e, Expr::Yield(_)), false);
+
+    found
+}
+
+/// Whether `body` uses 🪞, leaving out the functions defined in it.
+fn calls_eval(body: &mut Block) -> bool {
+    let mut found = false;
+    body.walk(&mut |e| found |= e.is_eval(), false);
+
+    found
 }
 
 impl Eval for Func {
-    fn eval(&self, _env: &mut Env) -> Result<Val, Val> {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        // globals stay visible in function bodies, so only locals need to be
+        // captured.
+        let captured: Captured = match &self.resolved {
+            Some(slots) => self
+                .captures
+                .iter()
+                .zip(slots)
+                .filter_map(|(name, slot)| Some((name.clone(), env.ref_local(*slot, name).ok()?)))
+                .collect(),
+            None => self
+                .captures
+                .iter()
+                .filter_map(|name| Some((name.clone(), env.take_local_ref(name)?)))
+                .collect(),
+        };
+
+        let defaults: Result<Vec<_>, _> = self
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                Arg::Default(_, default) => Some(env.eval(default)),
+                _ => None,
+            })
+            .collect();
+
+        // generators suspend in the middle of their body, which only compiled
+        // code can do.
+        if self.generator {
+            let proto = self
+                .compiled
+                .0
+                .get_or_init(|| Rc::new(Compiler::function(self)));
+            let func = VmFunc::new(proto.clone(), defaults?, captured);
+            return Ok(Val::from_func(func));
+        }
+
+        Ok(self.interpreted(defaults?, captured))
+    }
+}
+
+impl Func {
+    /// Creates the function evaluating its body directly, given the values of
+    /// its defaults and the bindings it captures.
+    pub(crate) fn interpreted(&self, defaults: Vec<Val>, captured: Captured) -> Val {
+        // locals of a body using 🪞 aren't resolved.
+        let slots = match &self.resolved {
+            Some(_) if !self.evals => Some(self.captures.clone().into()),
+            _ => None,
+        };
+
         let funcval = FuncVal {
-            args: self.args.clone(),
-            body: self.body.clone(),
-            parent: None,
+            args: self.args.clone().into(),
+            defaults: defaults.into(),
+            body: Rc::new(self.body.clone()),
+            parent: if captured.is_empty() {
+                None
+            } else {
+                Some(Rc::new(captured))
+            },
+            slots,
         };
-        Ok(Val::from_func(funcval))
+        Val::from_func(funcval)
     }
 }
 
@@ -111,52 +370,115 @@ impl crate::expr::Format for Func {
     }
 }
 
+/// Bindings captured by a function, by name.
+pub(crate) type Captured = HashMap<String, Val, ahash::RandomState>;
+
+/// Bindings captured by a function, shared by its copies until one of them
+/// changes.
+pub(crate) type Parent = Rc<Captured>;
+
+/// Downgrades the binding `name` captured in `parent` to a weak reference,
+/// returning the cell it refers to.
+pub(crate) fn weaken_capture(parent: &mut Option<Parent>, name: &str) -> Option<Rc<RefCell<Val>>> {
+    let captured = Rc::make_mut(parent.as_mut()?).get_mut(name)?;
+    let rc = captured.as_val_ref().ok()?.clone();
+    *captured = Val::Weak(WeakWrapper(Rc::downgrade(&rc)));
+
+    Some(rc)
+}
+
+/// Value of a call whose body evaluated to `val`, unwrapping a return. A break
+/// can't leave the function it's in.
+pub(crate) fn returned(val: Val) -> Result<Val, RuntimeError> {
+    match val {
+        Val::Return(val) => Ok(*val),
+        Val::Break(_) => Err(RuntimeError::BreakOutsideLoop),
+        val => Ok(val),
+    }
+}
+
+/// Value of a call returning `val`, copied out of its cell if it is one of
+/// the bindings captured in `parent`. Callers would otherwise share the
+/// state of the function.
+pub(crate) fn unshare(val: Val, parent: &Option<Parent>) -> Val {
+    let captured = |rc: &Rc<RefCell<Val>>| {
+        parent
+            .iter()
+            .flat_map(|parent| parent.values())
+            .any(|captured| match captured {
+                Val::Ref(cell) => Rc::ptr_eq(cell, rc),
+                _ => false,
+            })
+    };
+
+    match val {
+        Val::Ref(rc) if captured(&rc) => rc.borrow().clone(),
+        val => val,
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct FuncVal {
-    pub(crate) args: Vec<Arg>,
-    pub(crate) body: Block,
-    pub(crate) parent: Option<HashMap<String, Val, ahash::RandomState>>,
+    pub(crate) args: Rc<[Arg]>,
+    /// Values of the parameters with defaults, in order.
+    pub(crate) defaults: Rc<[Val]>,
+    pub(crate) body: Rc<Block>,
+    pub(crate) parent: Option<Parent>,
+    /// Captured names in the order of their slots, which follow the arguments,
+    /// if the body was resolved.
+    pub(crate) slots: Option<Rc<[String]>>,
 }
 
-impl Callee for FuncVal {
-    fn call(&self, args: &mut [Val], env: &mut Env) -> Result<Val, Val> {
-        env.push();
-
-        let mut idx = 0;
-        for param in &self.args {
-            match param {
-                Arg::Single(name) => {
-                    let val = args[idx].clone();
-                    idx += 1;
-                    env.store_binding(name.to_string(), val);
-                }
-                Arg::Variadic(name) => {
-                    let mut dq = VecDeque::new();
-                    while idx < args.len() {
-                        dq.push_back(args[idx].clone());
-                        idx += 1;
+impl FuncVal {
+    /// Downgrades the captured binding `name` to a weak reference, returning
+    /// the cell it refers to. Functions capturing themselves would otherwise
+    /// never be freed.
+    pub(crate) fn weaken_capture(&mut self, name: &str) -> Option<Rc<RefCell<Val>>> {
+        weaken_capture(&mut self.parent, name)
+    }
+
+    fn call_isolated(&self, args: &mut [Val], env: &mut Env) -> Result<Val, Val> {
+        let vals = bind_args(&self.args, &self.defaults, args)?;
+        let params = self.args.iter().map(Arg::name).zip(vals);
+
+        match &self.slots {
+            Some(names) => {
+                let captured = names
+                    .iter()
+                    .map(|name| self.parent.as_ref()?.get(name).cloned());
+                env.push_slots(params.map(|(_, val)| Some(val)).chain(captured));
+
+                let result = self.body.eval_in_frame(env);
+                env.pop();
+
+                result
+            }
+            None => {
+                env.push();
+
+                if let Some(parent_vars) = &self.parent {
+                    for (k, v) in parent_vars.iter() {
+                        env.store_binding(k.to_string(), v.clone());
                     }
+                }
 
-                    env.store_binding(name.to_string(), Val::Deque(Box::new(dq)));
+                for (name, val) in params {
+                    env.store_binding(name.to_string(), val);
                 }
-            }
-        }
 
-        if let Some(parent_vars) = &self.parent {
-            for (k, v) in parent_vars {
-                env.store_binding(k.to_string(), v.clone());
+                let result = env.eval(self.body.as_ref());
+                env.pop();
+
+                result
             }
         }
+    }
+}
 
-        let result = if idx == args.len() {
-            env.eval(&self.body)
-        } else {
-            Err(RuntimeError::WrongArgsN.into())
-        };
-
-        env.pop();
-
-        result
+impl Callee for FuncVal {
+    fn call(&self, args: &mut [Val], env: &mut Env) -> Result<Val, Val> {
+        let val = env.isolated(|env| self.call_isolated(args, env))?;
+        Ok(unshare(returned(val)?, &self.parent))
     }
 
     fn clone_box(&self) -> Box<dyn Callee> {
@@ -180,7 +502,8 @@ impl Callee for FuncVal {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::expr::{binding_update::Mode, binding_usage::BindingUsage, BindingUpdate, Expr, Op};
+    use crate::expr::{binding_usage::BindingUsage, BindingUpdate, Literal, Op};
+    use crate::test_utils::iter_env;
 
     #[test]
     fn func_parse_id() {
@@ -190,8 +513,16 @@ mod tests {
             body: Block {
                 exprs: vec![Expr::BindingUsage(BindingUsage {
                     name: "a".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })],
             },
+            captures: Vec::new(),
+            resolved: None,
+            generator: false,
+            evals: false,
+            compiled: Compiled::default(),
         };
 
         assert_eq!(func_e, Ok(("", expected)));
@@ -205,8 +536,16 @@ mod tests {
             body: Block {
                 exprs: vec![Expr::BindingUsage(BindingUsage {
                     name: "v".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })],
             },
+            captures: Vec::new(),
+            resolved: None,
+            generator: false,
+            evals: false,
+            compiled: Compiled::default(),
         };
 
         assert_eq!(func_e, Ok(("", expected)));
@@ -215,9 +554,105 @@ mod tests {
     #[test]
     fn error_on_premature_variadic() {
         let func_e = Func::new("🧰 👨‍👨‍👦v x ➡️ v 🧑‍🦲");
-        let expected = ParseError::PrematureVariadic;
+        let expected = ParseErrorKind::PrematureVariadic.at(" x ➡️ v 🧑‍🦲");
 
         assert_eq!(func_e, Err(expected));
+
+        let func_e = Func::new("🧰 🗺️o 👨‍👨‍👦v ➡️ v 🧑‍🦲");
+        let expected = ParseErrorKind::PrematureVariadic.at(" 👨‍👨‍👦v ➡️ v 🧑‍🦲");
+
+        assert_eq!(func_e, Err(expected));
+    }
+
+    #[test]
+    fn func_parse_defaults() {
+        let (_, func_e) = Func::new("🧰 a b = 2 👨‍👨‍👦rest 🗺️opts ➡️ a 🧑‍🦲").unwrap();
+        let expected = vec![
+            Arg::Single("a".to_string()),
+            Arg::Default("b".to_string(), Expr::Literal(Literal(Val::Number(2)))),
+            Arg::Variadic("rest".to_string()),
+            Arg::KwVariadic("opts".to_string()),
+        ];
+
+        assert_eq!(func_e.args, expected);
+    }
+
+    #[test]
+    fn func_eval_default_named() {
+        let mut env = Env::test();
+        let (_, func_def) = Expr::new("👶 f = 🧰 a b = 10 ➡️ a - b 🧑‍🦲").unwrap();
+        env.eval(&func_def).unwrap();
+
+        let cases = [
+            ("📞 f 1", -9),
+            ("📞 f 1 2", -1),
+            ("📞 f b: 1 a: 5", 4),
+            ("📞 f 3 b: 1", 2),
+        ];
+        for (input, expected) in cases {
+            let (_, call_e) = Expr::new(input).unwrap();
+            assert_eq!(env.eval(&call_e), Ok(Val::Number(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn func_eval_kw_variadic() {
+        let mut env = Env::test();
+        let (_, func_def) = Expr::new("👶 f = 🧰 a 🗺️opts ➡️ opts 🧑‍🦲").unwrap();
+        env.eval(&func_def).unwrap();
+
+        let (_, call_e) = Expr::new("📞 f x: 2 1 a: 3").unwrap();
+        let result = env.eval(&call_e);
+        assert_eq!(result, Err(RuntimeError::UnexpectedArg("a".into()).into()));
+
+        let (_, call_e) = Expr::new("📞 f x: 2 1").unwrap();
+        let mut expected = Map::default();
+        expected.insert(Key::Str("x".into()), Val::Number(2));
+        assert_eq!(env.eval(&call_e), Ok(Val::Map(Box::new(expected))));
+    }
+
+    #[test]
+    fn func_wrong_args() {
+        let mut env = Env::test();
+        let (_, func_def) = Expr::new("👶 f = 🧰 a b = 1 ➡️ a 🧑‍🦲").unwrap();
+        env.eval(&func_def).unwrap();
+
+        let cases = [
+            (
+                "📞 f",
+                RuntimeError::WrongArgsN {
+                    expected: 2,
+                    received: 0,
+                },
+            ),
+            (
+                "📞 f b: 2",
+                RuntimeError::WrongArgsN {
+                    expected: 2,
+                    received: 1,
+                },
+            ),
+            (
+                "📞 f 1 2 3",
+                RuntimeError::WrongArgsN {
+                    expected: 2,
+                    received: 3,
+                },
+            ),
+            (
+                "📞 f 1 2 3 b: 4",
+                RuntimeError::WrongArgsN {
+                    expected: 2,
+                    received: 4,
+                },
+            ),
+            ("📞 f 1 c: 2", RuntimeError::UnexpectedArg("c".into())),
+        ];
+        for (input, expected) in cases {
+            let (_, call_e) = Expr::new(input).unwrap();
+            let result = env.eval(&call_e);
+            assert_eq!(result, Err(expected.into()), "{}", input);
+        }
     }
 
     #[test]
@@ -229,13 +664,24 @@ mod tests {
                 exprs: vec![Expr::Operation {
                     lhs: Box::new(Expr::BindingUsage(BindingUsage {
                         name: "a".to_string(),
+                        slot: None,
+                        rem: 0,
+                        span: None,
                     })),
                     rhs: Box::new(Expr::BindingUsage(BindingUsage {
                         name: "b".to_string(),
+                        slot: None,
+                        rem: 0,
+                        span: None,
                     })),
                     op: Op::Add,
                 }],
             },
+            captures: Vec::new(),
+            resolved: None,
+            generator: false,
+            evals: false,
+            compiled: Compiled::default(),
         };
 
         assert_eq!(func_e, Ok(("", expected)));
@@ -253,13 +699,27 @@ mod tests {
                         body: Block {
                             exprs: vec![Expr::BindingUsage(BindingUsage {
                                 name: "a".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })],
                         },
+                        captures: Vec::new(),
+                        resolved: None,
+                        generator: false,
+                        evals: false,
+                        compiled: Compiled::default(),
                     })),
                     mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })),
                 Expr::BindingUsage(BindingUsage {
                     name: "id".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 }),
             ],
         });
@@ -271,13 +731,18 @@ mod tests {
     fn func_eval_id() {
         let (_, func_e) = Func::new("🧰 a ➡️ a 🧑‍🦲").unwrap();
         let expected = FuncVal {
-            args: vec![Arg::Single("a".to_string())],
-            body: Block {
+            args: vec![Arg::Single("a".to_string())].into(),
+            defaults: Vec::new().into(),
+            body: Rc::new(Block {
                 exprs: vec![Expr::BindingUsage(BindingUsage {
                     name: "a".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })],
-            },
+            }),
             parent: None,
+            slots: None,
         };
 
         let mut env = Env::test();
@@ -290,13 +755,18 @@ mod tests {
     fn func_eval_variadic_id() {
         let (_, func_e) = Func::new("🧰 👨‍👨‍👦a ➡️ a 🧑‍🦲").unwrap();
         let expected = FuncVal {
-            args: vec![Arg::Variadic("a".to_string())],
-            body: Block {
+            args: vec![Arg::Variadic("a".to_string())].into(),
+            defaults: Vec::new().into(),
+            body: Rc::new(Block {
                 exprs: vec![Expr::BindingUsage(BindingUsage {
                     name: "a".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })],
-            },
+            }),
             parent: None,
+            slots: None,
         };
 
         let mut env = Env::test();
@@ -341,6 +811,163 @@ mod tests {
         );
     }
 
+    #[test]
+    fn func_parse_captures() {
+        let (_, func_e) = Func::new("🧰 a ➡️ ♻️ b = 📞 f a 🔖c 🧑‍🦲").unwrap();
+        let expected = vec!["b".to_string(), "c".to_string(), "f".to_string()];
+
+        assert_eq!(func_e.captures, expected);
+    }
+
+    #[test]
+    fn func_closure_counter() {
+        let (_, make_counter) = Expr::new(
+            "👶 counter = 🧰 ➡️
+                👶 n = 0 💪
+                🧰 ➡️ ♻️ n = n + 1 💪 n 🧑‍🦲
+            🧑‍🦲",
+        )
+        .unwrap();
+        let (_, make_c1) = Expr::new("👶 c1 = 📞 counter").unwrap();
+        let (_, make_c2) = Expr::new("👶 c2 = 📞 counter").unwrap();
+        let (_, call_c1) = Expr::new("📞 c1").unwrap();
+        let (_, call_c2) = Expr::new("📞 c2").unwrap();
+
+        let mut env = Env::test();
+        env.eval(&make_counter).unwrap();
+        env.eval(&make_c1).unwrap();
+        env.eval(&make_c2).unwrap();
+
+        assert_eq!(env.eval(&call_c1), Ok(Val::Number(1)));
+        assert_eq!(env.eval(&call_c1), Ok(Val::Number(2)));
+        assert_eq!(env.eval(&call_c2), Ok(Val::Number(1)));
+    }
+
+    #[test]
+    fn func_closure_result_unshared() {
+        let (_, block) = Expr::new(
+            "📦
+                👶 counter = 🧰 ➡️
+                    👶 n = 0 💪
+                    🧰 ➡️ ♻️ n = n + 1 💪 n 🧑‍🦲
+                🧑‍🦲 💪
+                👶 c = 📞 counter 💪
+                👶 first = 📞 c 💪
+                ♻️ first = 100 💪
+                📞 c
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        assert_eq!(Env::test().eval(&block), Ok(Val::Number(2)));
+    }
+
+    #[test]
+    fn func_lexical_scope() {
+        let (_, block) = Expr::new(
+            "📦
+                👶 apply = 🧰 f ➡️ 👶 k = 100 💪 📞 f 🧑‍🦲 💪
+                👶 k = 5 💪
+                📞 apply 🧰 ➡️ k 🧑‍🦲
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        let mut env = Env::test();
+        let res = env.eval(&block).unwrap().apply_to_root(|v| v.clone());
+
+        assert_eq!(res, Ok(Val::Number(5)));
+    }
+
+    #[test]
+    fn func_no_dynamic_scope() {
+        let (_, block) = Expr::new(
+            "📦
+                👶 get_k = 🧰 ➡️ k 🧑‍🦲 💪
+                👶 apply = 🧰 f ➡️ 👶 k = 100 💪 📞 f 🧑‍🦲 💪
+                📞 apply get_k
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        let mut env = Env::test();
+        let res = env.eval(&block);
+
+        assert_eq!(res, Err(RuntimeError::NoBinding("k".into()).into()));
+    }
+
+    #[test]
+    fn func_parse_generator() {
+        let (_, func_e) = Func::new("🧰 ➡️ ❓ 🙆‍♀️ 🐣 1 🧑‍🦲 🧑‍🦲 🧑‍🦲").unwrap();
+        assert!(func_e.generator);
+
+        // yields belong to the innermost function.
+        let (_, func_e) = Func::new("🧰 ➡️ 🧰 ➡️ 🐣 1 🧑‍🦲 🧑‍🦲 🧑‍🦲").unwrap();
+        assert!(!func_e.generator);
+    }
+
+    #[test]
+    fn func_eval_generator() {
+        let (_, mut block) = Expr::new(
+            "📦
+                👶 limit = 20 💪
+                👶 fib = 🧰 ➡️
+                    👶 a = 0 💪
+                    👶 b = 1 💪
+                    ⏳ a < limit
+                        🐣 a 🧑‍🦲 💪
+                        👶 t = a + b 💪
+                        ♻️ a = b 💪
+                        ♻️ b = t
+                    🧑‍🦲
+                🧑‍🦲 💪
+                👶 sum = 0 💪
+                👶 fibs = 📞 fib 💪
+                🔂 x 📥 fibs ♻️ sum = sum + x 🧑‍🦲 💪
+                👶 done = 📞 fibs🪆next 💪
+                ❓ done == 🏁 sum 🧑‍🦲 😡 0 🧑‍🦲
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        let res = iter_env().eval(&block);
+        assert_eq!(res, Ok(Val::Number(33)));
+
+        block.resolve(&iter_env()).unwrap();
+        let res = iter_env().eval(&block);
+        assert_eq!(res, Ok(Val::Number(33)));
+    }
+
+    #[test]
+    fn func_generator_compiled_once() {
+        let (_, func_e) = Func::new("🧰 ➡️ 🐣 1 🧑‍🦲 🧑‍🦲").unwrap();
+        let mut env = Env::test();
+
+        env.eval(&func_e).unwrap();
+        let first = func_e.compiled.0.get().unwrap().clone();
+        env.eval(&func_e).unwrap();
+
+        assert!(Rc::ptr_eq(&first, func_e.compiled.0.get().unwrap()));
+    }
+
+    #[test]
+    fn func_generator_error() {
+        let (_, block) = Expr::new(
+            "📦
+                👶 gen = 🧰 ➡️ 🐣 1 🧑‍🦲 💪 🧨 🧵oops🧵 🧑‍🦲 🧑‍🦲 💪
+                👶 g = 📞 gen 💪
+                👶 first = 📞 g🪆next 💪
+                👶 second = 👩‍🚒 📞 g🪆next 🧑‍🦲 🤡 e ➡️ 🧵caught🧵 🧑‍🦲 💪
+                👶 third = 📞 g🪆next 💪
+                ❓ third == 🏁 second 🧑‍🦲 😡 first 🧑‍🦲
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        let res = iter_env().eval(&block);
+        assert_eq!(res, Ok(Val::from("caught")));
+    }
+
     #[test]
     fn format() {
         let (_, func_e) = Func::new("🧰x👨‍👨‍👦v➡️v🧑‍🦲").unwrap();
@@ -348,5 +975,11 @@ mod tests {
             format!("{}", crate::expr::Display(&func_e)),
             "🧰 x 👨‍👨‍👦v ➡️\n    v\n🧑‍🦲"
         );
+
+        let (_, func_e) = Func::new("🧰x=1🗺️o➡️x🧑‍🦲").unwrap();
+        assert_eq!(
+            format!("{}", crate::expr::Display(&func_e)),
+            "🧰 x = 1 🗺️o ➡️\n    x\n🧑‍🦲"
+        );
     }
 }
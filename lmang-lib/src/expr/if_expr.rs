@@ -95,13 +95,13 @@ impl crate::expr::Format for If {
     fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
         write!(w, "{} ", kwords::IF)?;
         self.cond.format(w, depth)?;
-        writeln!(w, "")?;
+        writeln!(w)?;
         FormatImplicit(&self.body).format(w, depth)?;
 
         for elif in &self.elifs {
             write!(w, " {} ", kwords::ELIF)?;
             elif.0.format(w, depth)?;
-            writeln!(w, "")?;
+            writeln!(w)?;
             FormatImplicit(&elif.1).format(w, depth)?;
         }
 
@@ -134,7 +134,10 @@ mod tests {
                 If {
                     cond: Expr::Operation {
                         lhs: Box::new(Expr::BindingUsage(BindingUsage {
-                            name: "a".to_string()
+                            name: "a".to_string(),
+                            slot: None,
+                            rem: 0,
+                            span: None,
                         })),
                         rhs: Box::new(Expr::Literal(Literal(Val::Number(0)))),
                         op: Op::Greater
@@ -165,7 +168,10 @@ mod tests {
                 If {
                     cond: Expr::Operation {
                         lhs: Box::new(Expr::BindingUsage(BindingUsage {
-                            name: "a".to_string()
+                            name: "a".to_string(),
+                            slot: None,
+                            rem: 0,
+                            span: None,
                         })),
                         rhs: Box::new(Expr::Literal(Literal(Val::Number(0)))),
                         op: Op::Greater
@@ -198,7 +204,10 @@ mod tests {
                 If {
                     cond: Expr::Operation {
                         lhs: Box::new(Expr::BindingUsage(BindingUsage {
-                            name: "a".to_string()
+                            name: "a".to_string(),
+                            slot: None,
+                            rem: 0,
+                            span: None,
                         })),
                         rhs: Box::new(Expr::Literal(Literal(Val::Number(0)))),
                         op: Op::Greater
@@ -209,7 +218,10 @@ mod tests {
                     elifs: vec![(
                         Expr::Operation {
                             lhs: Box::new(Expr::BindingUsage(BindingUsage {
-                                name: "a".to_string()
+                                name: "a".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             rhs: Box::new(Expr::Literal(Literal(Val::Number(1)))),
                             op: Op::Greater
@@ -242,7 +254,10 @@ mod tests {
                 If {
                     cond: Expr::Operation {
                         lhs: Box::new(Expr::BindingUsage(BindingUsage {
-                            name: "a".to_string()
+                            name: "a".to_string(),
+                            slot: None,
+                            rem: 0,
+                            span: None,
                         })),
                         rhs: Box::new(Expr::Literal(Literal(Val::Number(0)))),
                         op: Op::Greater
@@ -253,7 +268,10 @@ mod tests {
                     elifs: vec![(
                         Expr::Operation {
                             lhs: Box::new(Expr::BindingUsage(BindingUsage {
-                                name: "a".to_string()
+                                name: "a".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             rhs: Box::new(Expr::Literal(Literal(Val::Number(1)))),
                             op: Op::Greater
@@ -357,11 +375,7 @@ mod tests {
         for (if_in, if_out) in results {
             env.store_binding("a".to_string(), Val::Number(if_in));
             let res = env.eval(&if_e);
-            if if_in > 0 {
-                assert_eq!(res, Ok(Val::Number(if_out)));
-            } else {
-                assert_eq!(res, Ok(Val::Number(if_out)));
-            }
+            assert_eq!(res, Ok(Val::Number(if_out)));
         }
     }
 
@@ -0,0 +1,276 @@
+use crate::env::{Env, Eval, Modules};
+use crate::error::{ParseError, RuntimeError};
+use crate::expr::class::ClassObject;
+use crate::expr::Expr;
+use crate::utils::{self, kwords};
+use crate::val::view::{self, View as _};
+use crate::val::Val;
+use std::path::{Path, PathBuf};
+
+/// Extension assumed for imports which leave it out.
+const EXTENSION: &str = "🆖";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Import {
+    pub(crate) path: Expr,
+}
+
+impl Import {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::IMPORT, s)?;
+
+        let (s, path) = Expr::new(s)?;
+
+        Ok((s, Import { path }))
+    }
+}
+
+impl Eval for Import {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        let path = env.eval(&self.path)?;
+        import(env, path)
+    }
+}
+
+impl crate::expr::Format for Import {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        write!(w, "{} ", kwords::IMPORT)?;
+        self.path.format(w, depth)
+    }
+}
+
+/// Finds the file `name` refers to, next to the importing file or else in the
+/// library directory.
+fn locate(modules: &Modules, name: &str) -> Result<PathBuf, RuntimeError> {
+    let mut rel = PathBuf::from(name);
+    if rel.extension().is_none() {
+        rel.set_extension(EXTENSION);
+    }
+
+    let importer_dir = modules
+        .loading
+        .last()
+        .and_then(|file| file.parent())
+        .unwrap_or_else(|| Path::new(""));
+    let found = std::iter::once(importer_dir.join(&rel))
+        .chain(modules.lib_dir.iter().map(|dir| dir.join(&rel)))
+        .find(|path| path.is_file());
+
+    let not_found = |reason: String| RuntimeError::IoError {
+        file: name.into(),
+        reason,
+    };
+    match found {
+        Some(path) => path.canonicalize().map_err(|e| not_found(e.to_string())),
+        None => Err(not_found("module not found".into())),
+    }
+}
+
+/// Object of the module at `path`, evaluated on first import.
+pub(crate) fn import(env: &Env, mut path: Val) -> Result<Val, Val> {
+    let name = view::AnyRef::<view::String>::view(&mut path, |s| Ok(s.clone()))?;
+    let path = {
+        let modules = env.modules.borrow();
+        let path = locate(&modules, &name)?;
+
+        if let Some(module) = modules.cache.get(&path) {
+            return Ok(module.clone());
+        }
+        if modules.loading.contains(&path) {
+            return Err(RuntimeError::ImportCycle(path.display().to_string()).into());
+        }
+
+        path
+    };
+
+    let code = std::fs::read_to_string(&path).map_err(|e| RuntimeError::IoError {
+        file: path.display().to_string(),
+        reason: e.to_string(),
+    })?;
+    let in_module = |e: ParseError| RuntimeError::ModuleError {
+        file: path.display().to_string(),
+        reason: e.render(&code),
+    };
+    let (_, mut expr) = Expr::parse(&code).map_err(in_module)?;
+    expr.resolve(env).map_err(in_module)?;
+
+    env.modules.borrow_mut().loading.push(path.clone());
+    let result = eval_module(&expr, &mut env.shared());
+    env.modules.borrow_mut().loading.pop();
+
+    let module = result?;
+    env.modules.borrow_mut().cache.insert(path, module.clone());
+
+    Ok(module)
+}
+
+/// Evaluates the code of a module in a frame of its own, whose bindings become
+/// members of the module.
+fn eval_module(expr: &Expr, env: &mut Env) -> Result<Val, Val> {
+    env.push();
+    let result = match expr {
+        Expr::Block(block) => block.eval_in_frame(env),
+        expr => env.eval(expr),
+    };
+    let (mut members, mut slots) = env.pop_frame();
+    result?;
+
+    if let Expr::Block(block) = expr {
+        for (name, slot) in block.resolved_locals() {
+            if let Some(val) = slots.get_mut(slot.index).and_then(Option::take) {
+                members.insert(name.to_string(), val);
+            }
+        }
+    }
+
+    Ok(Val::from_obj(ClassObject::new(members)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Literal;
+    use crate::test_utils::eval_in;
+
+    /// Writes `files` to a fresh directory named after the test.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lmang-import-{}", test));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        for (name, code) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, code).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn parse_import() {
+        let expected = Import {
+            path: Expr::Literal(Literal(Val::from("utils"))),
+        };
+
+        assert_eq!(Import::new("🧳 🧵utils🧵"), Ok(("", expected)));
+    }
+
+    #[test]
+    fn import_members() {
+        let dir = write_files(
+            "members",
+            &[
+                ("main.🆖", ""),
+                (
+                    "util/math.🆖",
+                    "📦
+                        👶 square = 🧰 x ➡️ x * x 🧑‍🦲 💪
+                        👶 quad = 🧰 x ➡️ 📞 square 📞 square x 🧑‍🦲
+                    🧑‍🦲",
+                ),
+            ],
+        );
+        let mut env = Env::test();
+        env.set_main_file(dir.join("main.🆖"));
+
+        let input = "📦 👶 m = 🧳 🧵util/math🧵 💪 📞 m🪆quad 2 🧑‍🦲";
+        assert_eq!(eval_in(&mut env, input), Ok(Val::Number(16)));
+    }
+
+    #[test]
+    fn import_once() {
+        let dir = write_files(
+            "once",
+            &[("counted.🆖", "📦 ♻️ loads = loads + 1 💪 👶 x = 1 🧑‍🦲")],
+        );
+        let mut env = Env::test();
+        env.set_main_file(dir.join("main.🆖"));
+        env.store_binding("loads".to_string(), Val::Number(0));
+
+        let input = "📦
+                👶 a = 🧳 🧵counted🧵 💪
+                👶 b = 🧳 🧵counted.🆖🧵 💪
+                b🪆x + loads * 10
+            🧑‍🦲";
+        assert_eq!(eval_in(&mut env, input), Ok(Val::Number(11)));
+    }
+
+    #[test]
+    fn import_lib_dir() {
+        let dir = write_files(
+            "lib_dir",
+            &[
+                ("app/main.🆖", ""),
+                ("lib/shared.🆖", "📦 👶 x = 7 🧑‍🦲"),
+                ("app/shared.🆖", "📦 👶 x = 8 🧑‍🦲"),
+                ("lib/other.🆖", "📦 👶 x = 9 🧑‍🦲"),
+            ],
+        );
+        let mut env = Env::test();
+        env.set_main_file(dir.join("app/main.🆖"));
+        env.set_lib_dir(dir.join("lib"));
+
+        let input = "📦 👶 a = 🧳 🧵shared🧵 💪 👶 b = 🧳 🧵other🧵 💪 b🪆x + a🪆x * 10 🧑‍🦲";
+        assert_eq!(eval_in(&mut env, input), Ok(Val::Number(89)));
+
+        let result = eval_in(&mut env, "🧳 🧵missing🧵");
+        let expected = RuntimeError::IoError {
+            file: "missing".into(),
+            reason: "module not found".into(),
+        };
+        assert_eq!(result, Err(expected.into()));
+    }
+
+    #[test]
+    fn import_cycle() {
+        let dir = write_files(
+            "cycle",
+            &[
+                ("a.🆖", "📦 👶 b = 🧳 🧵b🧵 🧑‍🦲"),
+                ("b.🆖", "📦 👶 a = 🧳 🧵a🧵 🧑‍🦲"),
+            ],
+        );
+        let mut env = Env::test();
+        env.set_main_file(dir.join("main.🆖"));
+
+        let path = dir.join("a.🆖").canonicalize().unwrap();
+        let expected = RuntimeError::ImportCycle(path.display().to_string());
+        assert_eq!(eval_in(&mut env, "🧳 🧵a🧵"), Err(expected.into()));
+    }
+
+    #[test]
+    fn import_errors() {
+        let dir = write_files(
+            "errors",
+            &[
+                ("unclosed.🆖", "📦 👶 x = 1"),
+                ("unresolved.🆖", "📦\n    👶 x = 1 💪\n    y\n🧑‍🦲"),
+            ],
+        );
+        let mut env = Env::test();
+        env.set_main_file(dir.join("main.🆖"));
+
+        let file = |name: &str| dir.join(name).canonicalize().unwrap().display().to_string();
+        let expected = RuntimeError::ModuleError {
+            file: file("unclosed.🆖"),
+            reason: "Expected tag 🧑‍🦲 at 1:10\n1 | 📦 👶 x = 1\n  |            ^".into(),
+        };
+        assert_eq!(eval_in(&mut env, "🧳 🧵unclosed🧵"), Err(expected.into()));
+
+        let expected = RuntimeError::ModuleError {
+            file: file("unresolved.🆖"),
+            reason: "Unresolved name y at 3:5\n3 |     y\n  |     ^".into(),
+        };
+        assert_eq!(eval_in(&mut env, "🧳 🧵unresolved🧵"), Err(expected.into()));
+    }
+
+    #[test]
+    fn format() {
+        let (_, import_e) = Import::new("🧳🧵utils🧵").unwrap();
+        assert_eq!(
+            format!("{}", crate::expr::Display(&import_e)),
+            "🧳 🧵utils🧵"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::env::{Env, Eval};
-use crate::error::ParseError;
+use crate::error::{ParseError, ParseErrorKind, RuntimeError};
 use crate::expr::Expr;
 use crate::utils::{self, kwords};
 use crate::val::Val;
@@ -14,27 +14,28 @@ impl Index {
     pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
 
-        let idx = s.find(kwords::INDEX).ok_or(ParseError::ExpectedIndex)?;
+        let idx = s
+            .find(kwords::INDEX)
+            .ok_or_else(|| ParseErrorKind::ExpectedIndex.at(s))?;
         let expr_s = &s[0..idx];
         let rest_s = &s[idx..];
 
-        let (expr_rem, root) = Expr::new(expr_s)?;
+        // errors within the truncated root are reported at its beginning, the
+        // same input is going to be tried by other expression parsers anyway.
+        let (expr_rem, mut root) = Expr::new(expr_s).map_err(|e| e.at(s))?;
         let (maybe_empty, _) = utils::extract_whitespace(expr_rem);
         if !maybe_empty.is_empty() {
-            return Err(ParseError::ExpectedExpr);
+            return Err(ParseErrorKind::ExpectedExpr.at(s));
         }
+        root.untruncate(rest_s.len());
 
         let s = utils::tag(kwords::INDEX, rest_s)?;
         let (s, id0) = utils::extract_ident(s)?;
 
         let mut idents = vec![id0.to_string()];
         let mut s = s;
-        loop {
-            if let Ok(new_s) = utils::tag(kwords::INDEX, s) {
-                s = new_s;
-            } else {
-                break;
-            }
+        while let Ok(new_s) = utils::tag(kwords::INDEX, s) {
+            s = new_s;
 
             if let Ok((new_s, ident)) = utils::extract_ident(s) {
                 s = new_s;
@@ -48,27 +49,43 @@ impl Index {
     }
 }
 
+/// Looks up the member `ident` of `val`, which has to be an object.
+pub(crate) fn member(val: Val, ident: &str) -> Result<Val, RuntimeError> {
+    // captured objects, and objects held by members, are references.
+    if let Val::Ref(_) | Val::Weak(_) = val {
+        return val.apply_to_root(|root| member(root.clone(), ident))?;
+    }
+
+    #[cfg(feature = "web")]
+    if let Val::JsValue(ref jv) = val {
+        if jv.is_object() {
+            return Val::convert_from_jv(jv.clone())
+                .as_object()?
+                .0
+                .member(ident);
+        }
+
+        return Ok(val);
+    }
+
+    let obj = val.as_object()?;
+    obj.0.member(ident)
+}
+
+/// Sets the member `ident` of `val`, which has to be an object allowing it.
+pub(crate) fn set_member(val: Val, ident: &str, new_val: Val) -> Result<(), RuntimeError> {
+    if let Val::Ref(_) | Val::Weak(_) = val {
+        return val.apply_to_root(|root| set_member(root.clone(), ident, new_val))?;
+    }
+
+    val.as_object()?.0.set_member(ident, new_val)
+}
+
 impl Eval for Index {
     fn eval(&self, env: &mut Env) -> Result<Val, Val> {
         let mut val = env.eval(&self.root)?;
         for ident in &self.idents {
-            #[cfg(feature = "web")]
-            if let Val::JsValue(ref jv) = val {
-                if jv.is_object() {
-                    val = Val::convert_from_jv(jv.clone())
-                        .as_object()?
-                        .0
-                        .member(ident.as_ref())?;
-                }
-            } else {
-                let obj = val.as_object()?;
-                val = obj.0.member(ident.as_ref())?;
-            }
-            #[cfg(not(feature = "web"))]
-            {
-                let obj = val.as_object()?;
-                val = obj.0.member(ident.as_ref())?;
-            }
+            val = member(val, ident)?;
         }
 
         Ok(val)
@@ -98,6 +115,9 @@ mod tests {
         let expected = Index {
             root: Expr::BindingUsage(BindingUsage {
                 name: "a".to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
             }),
             idents: vec!["b".to_string()],
         };
@@ -111,6 +131,9 @@ mod tests {
         let expected = Index {
             root: Expr::BindingUsage(BindingUsage {
                 name: "a".to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
             }),
             idents: vec![
                 "b".to_string(),
@@ -128,11 +151,21 @@ mod tests {
     #[test]
     fn index0_error() {
         let idx_e = Index::new("a");
-        let expected = Err(ParseError::ExpectedIndex);
+        let expected = Err(ParseErrorKind::ExpectedIndex.at("a"));
 
         assert_eq!(idx_e, expected);
     }
 
+    #[test]
+    fn member_through_ref() {
+        let (_, class_e) = Expr::new("🧑‍🏫 👶 x = 1 🧑‍🦲").unwrap();
+        let obj = Env::test().eval(&class_e).unwrap();
+        let obj_ref = Val::Ref(std::rc::Rc::new(std::cell::RefCell::new(obj)));
+
+        let result = member(obj_ref, "x").and_then(|x| x.apply_to_root(Val::clone));
+        assert_eq!(result, Ok(Val::Number(1)));
+    }
+
     #[test]
     fn parse_call_indexed() {
         let call_e = Expr::new("📞rng🪆next");
@@ -140,10 +173,15 @@ mod tests {
             func: Expr::Index(Box::new(Index {
                 root: Expr::BindingUsage(BindingUsage {
                     name: "rng".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 }),
                 idents: vec!["next".to_string()],
             })),
             args: Vec::new(),
+            rem: "📞rng🪆next".len(),
+            span: None,
         }));
 
         assert_eq!(call_e, Ok(("", expected)));
@@ -1,4 +1,4 @@
-use crate::error::ParseError;
+use crate::error::{ParseError, ParseErrorKind};
 use crate::utils;
 use crate::val::Val;
 
@@ -9,7 +9,57 @@ impl Number {
     fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
         let (s, number) = utils::extract_digits(s)?;
-        Ok((s, Self(number.parse().unwrap())))
+        // the error is past the digits so that it isn't mistaken for a
+        // different kind of expression.
+        let number = number
+            .parse()
+            .map_err(|_| ParseErrorKind::NumberOutOfRange.at(s))?;
+
+        Ok((s, Self(number)))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Float(f64);
+
+impl Float {
+    fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+
+        // floats that aren't finite are read the way they're displayed,
+        // negative ones are negated literals.
+        match utils::extract_ident(s) {
+            Ok((rem, "NaN")) => return Ok((rem, Self(f64::NAN))),
+            Ok((rem, "inf")) => return Ok((rem, Self(f64::INFINITY))),
+            _ => {}
+        }
+
+        let (rem, _) = utils::extract_digits(s)?;
+
+        let (rem, fraction) = match utils::tag(".", rem).and_then(utils::extract_digits) {
+            Ok((rem, _)) => (rem, true),
+            Err(_) => (rem, false),
+        };
+        let (rem, exponent) = match Self::exponent(rem) {
+            Ok(rem) => (rem, true),
+            Err(_) => (rem, false),
+        };
+        if !fraction && !exponent {
+            return Err(ParseErrorKind::ExpectedTag(".").at(rem));
+        }
+
+        let lit = &s[..s.len() - rem.len()];
+        Ok((rem, Self(lit.parse().unwrap())))
+    }
+
+    fn exponent(s: &str) -> Result<&str, ParseError> {
+        let s = utils::tag("e", s)?;
+        let s = utils::tag("-", s)
+            .or_else(|_| utils::tag("+", s))
+            .unwrap_or(s);
+        let (s, _) = utils::extract_digits(s)?;
+
+        Ok(s)
     }
 }
 
@@ -20,7 +70,10 @@ impl Char {
     fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
         let s = utils::tag(utils::kwords::CHAR_LIT, s)?;
-        let c = s.chars().next().ok_or(ParseError::UnexpectedEof)?;
+        let c = s
+            .chars()
+            .next()
+            .ok_or_else(|| ParseErrorKind::UnexpectedEof.at(s))?;
         let s = &s[c.len_utf8()..];
         let s = utils::tag(utils::kwords::CHAR_LIT, s)?;
 
@@ -40,7 +93,7 @@ impl StringLiteral {
         let (s, lit) = utils::take_while(|c| !STR_LIT.starts_with(c), s);
         let s = utils::tag(STR_LIT, s)?;
 
-        Ok((s, Self(Val::from(lit.as_ref()))))
+        Ok((s, Self(Val::from(lit))))
     }
 }
 
@@ -57,7 +110,7 @@ impl Bool {
         } else if let Ok(s) = utils::tag(FALSE, s) {
             Ok((s, Self(false)))
         } else {
-            Err(ParseError::ExpectedBool)
+            Err(ParseErrorKind::ExpectedBool.at(s))
         }
     }
 }
@@ -67,11 +120,30 @@ pub struct Literal(pub(crate) Val);
 
 impl Literal {
     pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
-        Number::new(s)
-            .map(|(s, number)| (s, Self(Val::Number(number.0))))
-            .or_else(|_| Char::new(s).map(|(s, char_lit)| (s, Self(Val::Char(char_lit.0)))))
-            .or_else(|_| Bool::new(s).map(|(s, bool_lit)| (s, Self(Val::Bool(bool_lit.0)))))
-            .or_else(|_| StringLiteral::new(s).map(|(s, str_lit)| (s, Self(str_lit.0))))
+        let number = Float::new(s)
+            .map(|(s, float)| (s, Self(Val::Float(float.0))))
+            .or_else(|_| Number::new(s).map(|(s, number)| (s, Self(Val::Number(number.0)))));
+
+        match number {
+            Err(e) if e.kind == ParseErrorKind::NumberOutOfRange => Err(e),
+            Err(_) => Char::new(s)
+                .map(|(s, char_lit)| (s, Self(Val::Char(char_lit.0))))
+                .or_else(|_| Bool::new(s).map(|(s, bool_lit)| (s, Self(Val::Bool(bool_lit.0)))))
+                .or_else(|_| StringLiteral::new(s).map(|(s, str_lit)| (s, Self(str_lit.0)))),
+            ok => ok,
+        }
+    }
+}
+
+impl crate::expr::Format for Literal {
+    fn format(&self, w: &mut dyn std::fmt::Write, _depth: usize) -> std::fmt::Result {
+        use utils::kwords::{CHAR_LIT, STR_LIT};
+
+        match &self.0 {
+            Val::Char(c) => write!(w, "{}{}{}", CHAR_LIT, c, CHAR_LIT),
+            Val::Str(s) => write!(w, "{}{}{}", STR_LIT, s, STR_LIT),
+            v => write!(w, "{}", v),
+        }
     }
 }
 
@@ -86,6 +158,60 @@ mod tests {
         assert_eq!(Number::new("123"), Ok(("", Number(123))));
     }
 
+    #[test]
+    fn parse_float() {
+        assert_eq!(Float::new("1.5"), Ok(("", Float(1.5))));
+        assert_eq!(Float::new("2e3"), Ok(("", Float(2000.0))));
+        assert_eq!(Float::new("2.5e-1"), Ok(("", Float(0.25))));
+        assert_eq!(
+            Float::new("1.x"),
+            Err(ParseErrorKind::ExpectedTag(".").at(".x"))
+        );
+        assert_eq!(
+            Float::new("12"),
+            Err(ParseErrorKind::ExpectedTag(".").at(""))
+        );
+    }
+
+    #[test]
+    fn parse_literal_float() {
+        assert_eq!(Literal::new("0.125"), Ok(("", Literal(Val::Float(0.125)))));
+        assert_eq!(Literal::new("3."), Ok((".", Literal(Val::Number(3)))));
+    }
+
+    #[test]
+    fn format_float_round_trip() {
+        for x in [1.0, 0.1, 2.5e-7, 1e21, 123.456, f64::INFINITY] {
+            let (_, expr) = Expr::new(&format!("{}", Val::Float(x))).unwrap();
+            assert_eq!(expr, Expr::Literal(Literal(Val::Float(x))));
+
+            let formatted = format!("{}", crate::expr::Display(&expr));
+            assert_eq!(Expr::new(&formatted), Ok(("", expr)));
+        }
+    }
+
+    #[test]
+    fn displayed_float_round_trip() {
+        let values = [-1.5, -0.0, -2.5e-7, f64::NAN, f64::NEG_INFINITY, f64::MAX];
+        for x in values {
+            let shown = format!("{}", Val::Float(x));
+            let (_, expr) = Expr::new(&shown).unwrap();
+            assert_eq!(format!("{}", crate::expr::Display(&expr)), shown);
+
+            match Env::test().eval(&expr) {
+                Ok(Val::Float(y)) => assert_eq!(x.to_bits(), y.to_bits(), "{}", shown),
+                other => panic!("{} evaluated to {:?}", shown, other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_non_finite_float() {
+        assert_eq!(Float::new("inf"), Ok(("", Float(f64::INFINITY))));
+        assert!(matches!(Float::new("NaN 💪"), Ok((" 💪", Float(x))) if x.is_nan()));
+        assert!(Float::new("info").is_err());
+    }
+
     #[test]
     fn parse_char() {
         assert_eq!(Char::new("🔡x🔡"), Ok(("", Char('x'))));
@@ -93,7 +219,7 @@ mod tests {
         assert_eq!(Char::new("🔡💈🔡"), Ok(("", Char('💈'))));
         assert_eq!(
             Char::new("🔡💈y🔡"),
-            Err(ParseError::ExpectedTag("🔡".into()))
+            Err(ParseErrorKind::ExpectedTag("🔡").at("y🔡"))
         );
     }
 
@@ -152,18 +278,29 @@ mod tests {
                     val: Expr::Func(Box::new(Func {
                         args: Vec::new(),
                         body: Block {
-                            exprs: vec![Expr::Literal(Literal(Val::Deque(Box::new(
-                                "Hello World".chars().map(|c| Val::Char(c)).collect(),
-                            ))))],
+                            exprs: vec![Expr::Literal(Literal(Val::from("Hello World")))],
                         },
+                        captures: Vec::new(),
+                        resolved: None,
+                        generator: false,
+                        evals: false,
+                        compiled: Default::default(),
                     })),
                     mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })),
                 Expr::Call(Box::new(Call {
                     func: Expr::BindingUsage(BindingUsage {
                         name: "hello".to_string(),
+                        slot: None,
+                        rem: 0,
+                        span: None,
                     }),
                     args: Vec::new(),
+                    rem: "📞 hello\n        🧑‍🦲".len(),
+                    span: None,
                 })),
             ],
         });
@@ -183,11 +320,9 @@ mod tests {
         🧑‍🦲",
         )
         .unwrap();
-        let expected = "Hello World".chars().map(|c| Val::Char(c)).collect();
-
         let mut env = Env::test();
         let result = env.eval(&expr_e);
 
-        assert_eq!(result, Ok(Val::Deque(Box::new(expected))));
+        assert!(matches!(result, Ok(Val::Str(ref s)) if s.as_ref() == "Hello World"));
     }
 }
@@ -26,6 +26,7 @@ impl Eval for Loop {
         Ok(loop {
             match env.eval(&self.body)? {
                 Val::Break(inner_box) => break *inner_box,
+                ret @ Val::Return(_) => break ret,
                 _ => continue,
             }
         })
@@ -94,6 +95,9 @@ mod tests {
                             lhs: Box::new(Expr::Literal(Literal(Val::Number(0)))),
                             rhs: Box::new(Expr::BindingUsage(BindingUsage {
                                 name: "a".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             op: Op::Sub,
                         },
@@ -102,6 +106,9 @@ mod tests {
                                 body: Block {
                                     exprs: vec![Expr::BindingUsage(BindingUsage {
                                         name: "fact".to_string(),
+                                        slot: None,
+                                        rem: 0,
+                                        span: None,
                                     })],
                                 },
                             }))],
@@ -114,24 +121,39 @@ mod tests {
                         val: Expr::Operation {
                             lhs: Box::new(Expr::BindingUsage(BindingUsage {
                                 name: "fact".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             rhs: Box::new(Expr::BindingUsage(BindingUsage {
                                 name: "a".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             op: Op::Mul,
                         },
                         mode: Mode::CreateLocal,
+                        slot: None,
+                        rem: 0,
+                        span: None,
                     })),
                     Expr::BindingUpdate(Box::new(BindingUpdate {
                         name: "a".to_string(),
                         val: Expr::Operation {
                             lhs: Box::new(Expr::BindingUsage(BindingUsage {
                                 name: "a".to_string(),
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             rhs: Box::new(Expr::Literal(Literal(Val::Number(1)))),
                             op: Op::Sub,
                         },
                         mode: Mode::CreateLocal,
+                        slot: None,
+                        rem: 0,
+                        span: None,
                     })),
                 ],
             },
@@ -164,11 +186,17 @@ mod tests {
                     name: "fact".to_string(),
                     val: Expr::Literal(Literal(Val::Number(1))),
                     mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })),
                 Expr::BindingUpdate(Box::new(BindingUpdate {
                     name: "a".to_string(),
                     val: Expr::Literal(Literal(Val::Number(5))),
                     mode: Mode::CreateLocal,
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })),
                 Expr::Loop(Box::new(Loop {
                     body: Block {
@@ -178,6 +206,9 @@ mod tests {
                                     lhs: Box::new(Expr::Literal(Literal(Val::Number(0)))),
                                     rhs: Box::new(Expr::BindingUsage(BindingUsage {
                                         name: "a".to_string(),
+                                        slot: None,
+                                        rem: 0,
+                                        span: None,
                                     })),
                                     op: Op::Sub,
                                 },
@@ -186,6 +217,9 @@ mod tests {
                                         body: Block {
                                             exprs: vec![Expr::BindingUsage(BindingUsage {
                                                 name: "fact".to_string(),
+                                                slot: None,
+                                                rem: 0,
+                                                span: None,
                                             })],
                                         },
                                     }))],
@@ -198,24 +232,39 @@ mod tests {
                                 val: Expr::Operation {
                                     lhs: Box::new(Expr::BindingUsage(BindingUsage {
                                         name: "fact".to_string(),
+                                        slot: None,
+                                        rem: 0,
+                                        span: None,
                                     })),
                                     rhs: Box::new(Expr::BindingUsage(BindingUsage {
                                         name: "a".to_string(),
+                                        slot: None,
+                                        rem: 0,
+                                        span: None,
                                     })),
                                     op: Op::Mul,
                                 },
                                 mode: Mode::Set,
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                             Expr::BindingUpdate(Box::new(BindingUpdate {
                                 name: "a".to_string(),
                                 val: Expr::Operation {
                                     lhs: Box::new(Expr::BindingUsage(BindingUsage {
                                         name: "a".to_string(),
+                                        slot: None,
+                                        rem: 0,
+                                        span: None,
                                     })),
                                     rhs: Box::new(Expr::Literal(Literal(Val::Number(1)))),
                                     op: Op::Sub,
                                 },
                                 mode: Mode::Set,
+                                slot: None,
+                                rem: 0,
+                                span: None,
                             })),
                         ],
                     },
@@ -0,0 +1,146 @@
+use crate::env::{Env, Eval};
+use crate::error::ParseError;
+use crate::expr::Expr;
+use crate::utils::{self, kwords};
+use crate::val::{Key, Val};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    pub(crate) entries: Vec<(Expr, Expr)>,
+}
+
+impl Map {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let mut s = utils::tag(kwords::MAP, s)?;
+        let mut entries = Vec::new();
+
+        loop {
+            let (new_s, _) = utils::extract_whitespace(s);
+            if let Ok(new_s) = utils::tag(kwords::BLOCK_CLOSE, new_s) {
+                return Ok((new_s, Map { entries }));
+            }
+
+            let (new_s, key) = Expr::new(new_s)?;
+            let (new_s, _) = utils::extract_whitespace(new_s);
+            let new_s = utils::tag(kwords::FUNC_SEP, new_s)?;
+            let (new_s, val) = Expr::new(new_s)?;
+            entries.push((key, val));
+
+            let (new_s, _) = utils::extract_whitespace(new_s);
+            s = match utils::tag(kwords::EXPR_SEP, new_s) {
+                Ok(new_s) => new_s,
+                Err(_) => {
+                    let new_s = utils::tag(kwords::BLOCK_CLOSE, new_s)?;
+                    return Ok((new_s, Map { entries }));
+                }
+            };
+        }
+    }
+}
+
+impl Eval for Map {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        let mut map = crate::val::Map::default();
+        for (key_e, val_e) in &self.entries {
+            let key = Key::try_from(&env.eval(key_e)?)?;
+            let val = env.eval(val_e)?;
+            map.insert(key, val);
+        }
+
+        Ok(Val::Map(Box::new(map)))
+    }
+}
+
+impl crate::expr::Format for Map {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        write!(w, "{}", kwords::MAP)?;
+        if self.entries.is_empty() {
+            write!(w, "{}", kwords::BLOCK_CLOSE)?;
+            return Ok(());
+        }
+
+        writeln!(w)?;
+        for (i, (key, val)) in self.entries.iter().enumerate() {
+            Self::indent(w, depth + 1)?;
+            key.format(w, depth + 1)?;
+            write!(w, " {} ", kwords::FUNC_SEP)?;
+            val.format(w, depth + 1)?;
+            if i != self.entries.len() - 1 {
+                writeln!(w, " {}", kwords::EXPR_SEP)?;
+            } else {
+                writeln!(w)?;
+            }
+        }
+        Self::indent(w, depth)?;
+        write!(w, "{}", kwords::BLOCK_CLOSE)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RuntimeError;
+    use crate::expr::{BindingUsage, Display, Literal};
+
+    #[test]
+    fn parse_map() {
+        let map_e = Map::new("🗺️ 1 ➡️ a 💪 🧵b🧵 ➡️ 2🧑‍🦲");
+        let expected = Map {
+            entries: vec![
+                (
+                    Expr::Literal(Literal(Val::Number(1))),
+                    Expr::BindingUsage(BindingUsage {
+                        name: "a".to_string(),
+                        slot: None,
+                        rem: 0,
+                        span: None,
+                    }),
+                ),
+                (
+                    Expr::Literal(Literal(Val::from("b"))),
+                    Expr::Literal(Literal(Val::Number(2))),
+                ),
+            ],
+        };
+
+        assert_eq!(map_e, Ok(("", expected)));
+        assert_eq!(Map::new("🗺️🧑‍🦲"), Ok(("", Map { entries: vec![] })));
+    }
+
+    #[test]
+    fn eval_map() {
+        let (_, map_e) = Expr::new("🗺️ 1 ➡️ 2 + 3 💪 🔡x🔡 ➡️ 🙆‍♀️ 💪 1 ➡️ 4 🧑‍🦲").unwrap();
+        let result = Env::test().eval(&map_e);
+
+        let mut expected = crate::val::Map::default();
+        expected.insert(Key::Number(1), Val::Number(4));
+        expected.insert(Key::Char('x'), Val::Bool(true));
+
+        assert_eq!(result, Ok(Val::Map(Box::new(expected))));
+    }
+
+    #[test]
+    fn eval_map_unhashable_key() {
+        let (_, map_e) = Expr::new("🗺️ 1.5 ➡️ 2 🧑‍🦲").unwrap();
+        let result = Env::test().eval(&map_e);
+        let expected = RuntimeError::CastError {
+            from: "🛟".into(),
+            to: "🔑".into(),
+        };
+
+        assert_eq!(result, Err(expected.into()));
+    }
+
+    #[test]
+    fn format() {
+        let input = "🗺️ 1 ➡️ a 💪 🧵b🧵 ➡️ 🗺️🧑‍🦲🧑‍🦲";
+        let expected = "🗺️\n    1 ➡️ a 💪\n    🧵b🧵 ➡️ 🗺️🧑‍🦲\n🧑‍🦲";
+
+        let (_, map_e) = Expr::new(input).unwrap();
+
+        assert_eq!(format!("{}", Display(&map_e)), expected);
+    }
+}
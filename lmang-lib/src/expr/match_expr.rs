@@ -0,0 +1,608 @@
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, ParseErrorKind, RuntimeError};
+use crate::expr::block::{Block, FormatImplicit};
+use crate::expr::{Expr, Format, Literal};
+use crate::utils::{self, kwords};
+use crate::val::Val;
+use std::collections::VecDeque;
+
+/// Pattern a value is matched against. Identifiers bind the matched value,
+/// except for `_`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Bind(String),
+    Literal(Val),
+    /// Items of a deque or the characters of a string, the rest, if any,
+    /// being matched as a whole after them.
+    Deque {
+        items: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+    },
+    Named(String, Box<Pattern>),
+    /// Members of an object.
+    Object(Vec<(String, Pattern)>),
+}
+
+impl Pattern {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+
+        if let Ok(s) = utils::tag(kwords::DEQUE, s) {
+            return Self::new_deque(s);
+        }
+        if let Ok(s) = utils::tag(kwords::CLASS, s) {
+            return Self::new_object(s);
+        }
+        if let Ok((s, name)) = utils::extract_ident(s) {
+            if let Ok(s) = utils::tag(kwords::NAMED, s) {
+                let (s, inner) = Self::new(s)?;
+                return Ok((s, Self::Named(name.to_string(), Box::new(inner))));
+            }
+
+            let pattern = match name {
+                "_" => Self::Wildcard,
+                name => Self::Bind(name.to_string()),
+            };
+            return Ok((s, pattern));
+        }
+
+        let (lit_s, neg) = match utils::tag(kwords::SUB, s) {
+            Ok(lit_s) => (lit_s, true),
+            Err(_) => (s, false),
+        };
+        let (lit_s, Literal(val)) =
+            Literal::new(lit_s).map_err(|_| ParseErrorKind::ExpectedPattern.at(s))?;
+        let val = match (neg, val) {
+            (true, Val::Number(n)) => Val::Number(-n),
+            (true, Val::Float(x)) => Val::Float(-x),
+            (true, _) => return Err(ParseErrorKind::ExpectedPattern.at(s)),
+            (false, val) => val,
+        };
+
+        Ok((lit_s, Self::Literal(val)))
+    }
+
+    fn new_deque(s: &str) -> Result<(&str, Self), ParseError> {
+        let mut s = s;
+        let mut items = Vec::new();
+
+        loop {
+            let (new_s, _) = utils::extract_whitespace(s);
+            if let Ok(new_s) = utils::tag(kwords::BLOCK_CLOSE, new_s) {
+                return Ok((new_s, Self::Deque { items, rest: None }));
+            }
+
+            // the rest has to come last.
+            if let Ok(new_s) = utils::tag(kwords::VARIADIC, new_s) {
+                let (new_s, rest) = Self::new(new_s)?;
+                let (new_s, _) = utils::extract_whitespace(new_s);
+                let new_s = utils::tag(kwords::BLOCK_CLOSE, new_s)?;
+                let rest = Some(Box::new(rest));
+
+                return Ok((new_s, Self::Deque { items, rest }));
+            }
+
+            let (new_s, item) = Self::new(new_s)?;
+            items.push(item);
+
+            let (new_s, _) = utils::extract_whitespace(new_s);
+            s = match utils::tag(kwords::EXPR_SEP, new_s) {
+                Ok(new_s) => new_s,
+                Err(_) => {
+                    let new_s = utils::tag(kwords::BLOCK_CLOSE, new_s)?;
+                    return Ok((new_s, Self::Deque { items, rest: None }));
+                }
+            };
+        }
+    }
+
+    fn new_object(s: &str) -> Result<(&str, Self), ParseError> {
+        let mut s = s;
+        let mut members = Vec::new();
+
+        loop {
+            let (new_s, _) = utils::extract_whitespace(s);
+            if let Ok(new_s) = utils::tag(kwords::BLOCK_CLOSE, new_s) {
+                return Ok((new_s, Self::Object(members)));
+            }
+
+            // a lone member name binds the member to the same name.
+            let (new_s, name) = utils::extract_ident(new_s)?;
+            let (new_s, pattern) = match utils::tag(kwords::NAMED, new_s) {
+                Ok(new_s) => Self::new(new_s)?,
+                Err(_) => (new_s, Self::Bind(name.to_string())),
+            };
+            members.push((name.to_string(), pattern));
+
+            let (new_s, _) = utils::extract_whitespace(new_s);
+            s = match utils::tag(kwords::EXPR_SEP, new_s) {
+                Ok(new_s) => new_s,
+                Err(_) => {
+                    let new_s = utils::tag(kwords::BLOCK_CLOSE, new_s)?;
+                    return Ok((new_s, Self::Object(members)));
+                }
+            };
+        }
+    }
+
+    /// Names bound by the pattern, in the order `matches` yields their values.
+    pub(crate) fn bindings(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Self::Wildcard | Self::Literal(_) => {}
+            Self::Bind(name) => names.push(name),
+            Self::Deque { items, rest } => {
+                for item in items {
+                    item.collect_bindings(names);
+                }
+                if let Some(rest) = rest {
+                    rest.collect_bindings(names);
+                }
+            }
+            Self::Named(_, inner) => inner.collect_bindings(names),
+            Self::Object(members) => {
+                for (_, pattern) in members {
+                    pattern.collect_bindings(names);
+                }
+            }
+        }
+    }
+
+    /// Whether `val` matches, pushing the values of the bindings if so.
+    pub(crate) fn matches(&self, val: &Val, bound: &mut Vec<Val>) -> Result<bool, RuntimeError> {
+        // patterns look through references.
+        if let Val::Ref(_) | Val::Weak(_) = val {
+            let root = val.apply_to_root(Val::clone)?;
+            return self.matches(&root, bound);
+        }
+
+        Ok(match (self, val) {
+            (Self::Wildcard, _) => true,
+            (Self::Bind(_), val) => {
+                bound.push(val.clone());
+                true
+            }
+            (Self::Literal(lit), val) => lit == val,
+            (Self::Deque { items, rest }, Val::Str(s)) => {
+                let chars: VecDeque<_> = s.chars().map(Val::Char).collect();
+                Self::matches_items(items, rest, chars, bound)?
+            }
+            (Self::Deque { items, rest }, Val::Deque(dq)) => {
+                Self::matches_items(items, rest, dq.as_ref().clone(), bound)?
+            }
+            (Self::Named(name, inner), Val::Named((val_name, val))) => {
+                name == val_name && inner.matches(val, bound)?
+            }
+            (Self::Object(members), Val::Object(obj)) => {
+                for (name, pattern) in members {
+                    let member = match obj.0.member(name) {
+                        Ok(member) => member,
+                        Err(_) => return Ok(false),
+                    };
+                    if !pattern.matches(&member, bound)? {
+                        return Ok(false);
+                    }
+                }
+
+                true
+            }
+            _ => false,
+        })
+    }
+
+    fn matches_items(
+        items: &[Pattern],
+        rest: &Option<Box<Pattern>>,
+        mut vals: VecDeque<Val>,
+        bound: &mut Vec<Val>,
+    ) -> Result<bool, RuntimeError> {
+        let fits = match rest {
+            Some(_) => vals.len() >= items.len(),
+            None => vals.len() == items.len(),
+        };
+        if !fits {
+            return Ok(false);
+        }
+
+        let tail = vals.split_off(items.len());
+        for (item, val) in items.iter().zip(&vals) {
+            if !item.matches(val, bound)? {
+                return Ok(false);
+            }
+        }
+
+        match rest {
+            Some(rest) => rest.matches(&Val::Deque(Box::new(tail)), bound),
+            None => Ok(true),
+        }
+    }
+}
+
+impl Format for Pattern {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        match self {
+            Self::Wildcard => write!(w, "_"),
+            Self::Bind(name) => write!(w, "{}", name),
+            Self::Literal(val) => Literal(val.clone()).format(w, depth),
+            Self::Deque { items, rest } => {
+                write!(w, "{}", kwords::DEQUE)?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(w, " ")?;
+                    item.format(w, depth)?;
+                    if i != items.len() - 1 || rest.is_some() {
+                        write!(w, " {}", kwords::EXPR_SEP)?;
+                    }
+                }
+                if let Some(rest) = rest {
+                    write!(w, " {}", kwords::VARIADIC)?;
+                    rest.format(w, depth)?;
+                }
+                write!(w, " {}", kwords::BLOCK_CLOSE)
+            }
+            Self::Named(name, inner) => {
+                write!(w, "{}{}", name, kwords::NAMED)?;
+                inner.format(w, depth)
+            }
+            Self::Object(members) => {
+                write!(w, "{}", kwords::CLASS)?;
+                for (i, (name, pattern)) in members.iter().enumerate() {
+                    match pattern {
+                        Self::Bind(bound) if bound == name => write!(w, " {}", name)?,
+                        pattern => {
+                            write!(w, " {}{}", name, kwords::NAMED)?;
+                            pattern.format(w, depth)?;
+                        }
+                    }
+                    if i != members.len() - 1 {
+                        write!(w, " {}", kwords::EXPR_SEP)?;
+                    }
+                }
+                write!(w, " {}", kwords::BLOCK_CLOSE)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Arm {
+    pub(crate) pattern: Pattern,
+    pub(crate) guard: Option<Expr>,
+    pub(crate) body: Block,
+    /// Slots of the pattern's bindings, once resolved.
+    pub(crate) slots: Option<Vec<Slot>>,
+}
+
+impl Arm {
+    fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::CASE, s)?;
+        let (s, pattern) = Pattern::new(s)?;
+
+        let (s, _) = utils::extract_whitespace(s);
+        let (s, guard) = match utils::tag(kwords::IF, s) {
+            Ok(s) => {
+                let (s, guard) = Expr::new(s)?;
+                (s, Some(guard))
+            }
+            Err(_) => (s, None),
+        };
+
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::FUNC_SEP, s)?;
+        let (s, body) = Block::implicit(s)?;
+
+        let arm = Arm {
+            pattern,
+            guard,
+            body,
+            slots: None,
+        };
+
+        Ok((s, arm))
+    }
+
+    /// Evaluates the body if `val` matches the pattern and passes the guard.
+    fn eval(&self, env: &mut Env, val: &Val) -> Result<Option<Val>, Val> {
+        let mut bound = Vec::new();
+        if !self.pattern.matches(val, &mut bound)? {
+            return Ok(None);
+        }
+
+        // the bindings get a frame of their own around the guard and body.
+        env.push();
+        let result = self.eval_bound(env, bound);
+        env.pop();
+
+        result
+    }
+
+    fn eval_bound(&self, env: &mut Env, bound: Vec<Val>) -> Result<Option<Val>, Val> {
+        match &self.slots {
+            Some(slots) => {
+                for (slot, val) in slots.iter().zip(bound) {
+                    env.store_local(slot.index, val);
+                }
+            }
+            None => {
+                for (name, val) in self.pattern.bindings().into_iter().zip(bound) {
+                    env.store_binding(name.to_string(), val);
+                }
+            }
+        }
+
+        if let Some(guard) = &self.guard {
+            if !*env.eval(guard)?.as_bool()? {
+                return Ok(None);
+            }
+        }
+
+        env.eval(&self.body).map(Some)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match {
+    pub(crate) val: Expr,
+    pub(crate) arms: Vec<Arm>,
+}
+
+impl Match {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::MATCH, s)?;
+        let (mut s, val) = Expr::new(s)?;
+
+        let mut arms = Vec::new();
+        loop {
+            let (new_s, _) = utils::extract_whitespace(s);
+            if let Ok(new_s) = utils::tag(kwords::BLOCK_CLOSE, new_s) {
+                return Ok((new_s, Match { val, arms }));
+            }
+
+            let (new_s, arm) = Arm::new(new_s)?;
+            arms.push(arm);
+            s = new_s;
+        }
+    }
+}
+
+impl Eval for Match {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        let val = env.eval(&self.val)?;
+        if let Val::Break(_) | Val::Return(_) = val {
+            return Ok(val);
+        }
+
+        for arm in &self.arms {
+            if let Some(result) = arm.eval(env, &val)? {
+                return Ok(result);
+            }
+        }
+
+        Err(RuntimeError::NoMatch(val.to_string()).into())
+    }
+}
+
+impl Format for Match {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        write!(w, "{} ", kwords::MATCH)?;
+        self.val.format(w, depth)?;
+        writeln!(w)?;
+
+        for arm in &self.arms {
+            Self::indent(w, depth + 1)?;
+            write!(w, "{} ", kwords::CASE)?;
+            arm.pattern.format(w, depth + 1)?;
+            if let Some(guard) = &arm.guard {
+                write!(w, " {} ", kwords::IF)?;
+                guard.format(w, depth + 1)?;
+            }
+            writeln!(w, " {}", kwords::FUNC_SEP)?;
+            FormatImplicit(&arm.body).format(w, depth + 1)?;
+            writeln!(w)?;
+        }
+        Self::indent(w, depth)?;
+        write!(w, "{}", kwords::BLOCK_CLOSE)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Display;
+    use crate::test_utils::{eval, eval_resolved};
+
+    #[test]
+    fn parse_pattern() {
+        let cases = [
+            ("_", Pattern::Wildcard),
+            ("x", Pattern::Bind("x".into())),
+            ("-3", Pattern::Literal(Val::Number(-3))),
+            ("🧵ab🧵", Pattern::Literal(Val::from("ab"))),
+            (
+                "😵‍💫 1 💪 👨‍👨‍👦t 🧑‍🦲",
+                Pattern::Deque {
+                    items: vec![Pattern::Literal(Val::Number(1))],
+                    rest: Some(Box::new(Pattern::Bind("t".into()))),
+                },
+            ),
+            (
+                "😵‍💫🧑‍🦲",
+                Pattern::Deque {
+                    items: vec![],
+                    rest: None,
+                },
+            ),
+            (
+                "k:_",
+                Pattern::Named("k".into(), Box::new(Pattern::Wildcard)),
+            ),
+            (
+                "🧑‍🏫 x 💪 y:0 🧑‍🦲",
+                Pattern::Object(vec![
+                    ("x".into(), Pattern::Bind("x".into())),
+                    ("y".into(), Pattern::Literal(Val::Number(0))),
+                ]),
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(Pattern::new(input), Ok(("", expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_rest_must_be_last() {
+        assert_eq!(
+            Pattern::new("😵‍💫 👨‍👨‍👦t 💪 1 🧑‍🦲"),
+            Err(ParseErrorKind::ExpectedTag("🧑‍🦲").at("💪 1 🧑‍🦲"))
+        );
+    }
+
+    #[test]
+    fn eval_literals() {
+        let input = |v| {
+            format!(
+                "🧩 {} 👉 1 ➡️ 1 🧑‍🦲 👉 🔡a🔡 ➡️ 2 🧑‍🦲 👉 🧵ab🧵 ➡️ 3 🧑‍🦲 👉 -1 ➡️ 4 🧑‍🦲 👉 _ ➡️ 5 🧑‍🦲 🧑‍🦲",
+                v
+            )
+        };
+        let cases = [("1", 1), ("🔡a🔡", 2), ("🧵ab🧵", 3), ("- 1", 4), ("2", 5)];
+
+        for (val, expected) in cases {
+            assert_eq!(eval(&input(val)), Ok(Val::Number(expected)), "{}", val);
+        }
+    }
+
+    #[test]
+    fn eval_deque() {
+        let input = |v| {
+            format!(
+                "🧩 {}
+                    👉 😵‍💫🧑‍🦲 ➡️ 0 🧑‍🦲
+                    👉 😵‍💫 a 🧑‍🦲 ➡️ 1 🧑‍🦲
+                    👉 😵‍💫 🔡x🔡 💪 👨‍👨‍👦t 🧑‍🦲 ➡️ 📞 f t 🧑‍🦲
+                    👉 😵‍💫 a 💪 b 💪 👨‍👨‍👦_ 🧑‍🦲 ➡️ 3 🧑‍🦲
+                🧑‍🦲",
+                v
+            )
+        };
+        let cases = [
+            ("🧵🧵", Val::Number(0)),
+            ("🧵z🧵", Val::Number(1)),
+            ("🧵xyz🧵", Val::from("yz")),
+            ("🧵abc🧵", Val::Number(3)),
+        ];
+
+        for (val, expected) in cases {
+            let mut env = Env::test();
+            let (_, f) = Expr::new("👶 f = 🧰 s ➡️ s 🧑‍🦲").unwrap();
+            env.eval(&f).unwrap();
+            let (_, expr) = Expr::new(&input(val)).unwrap();
+
+            assert_eq!(env.eval(&expr), Ok(expected), "{}", val);
+        }
+    }
+
+    #[test]
+    fn eval_named_and_object() {
+        let result = eval(
+            "📦
+                👶 p = 🧑‍🏫 👶 x = 1 💪 👶 y = 2 🧑‍🦲 💪
+                🧩 p
+                    👉 🧑‍🏫 x 💪 z 🧑‍🦲 ➡️ 0 🧑‍🦲
+                    👉 🧑‍🏫 x:2 🧑‍🦲 ➡️ 1 🧑‍🦲
+                    👉 🧑‍🏫 x 💪 y:b 🧑‍🦲 ➡️ x + b 🧑‍🦲
+                🧑‍🦲
+            🧑‍🦲",
+        );
+        assert_eq!(result, Ok(Val::Number(3)));
+
+        let result = eval("🧩 k:5 👉 j:v ➡️ 0 🧑‍🦲 👉 k:v ➡️ v 🧑‍🦲 🧑‍🦲");
+        assert_eq!(result, Ok(Val::Number(5)));
+    }
+
+    #[test]
+    fn eval_error_object() {
+        let result = eval(
+            "👩‍🚒 x 🧑‍🦲 🤡 e ➡️
+                🧩 e 👉 🧑‍🏫 type:🧵NoBinding🧵 💪 binding 🧑‍🦲 ➡️ binding 🧑‍🦲 🧑‍🦲
+            🧑‍🦲",
+        );
+
+        assert_eq!(result, Ok(Val::from("x")));
+    }
+
+    #[test]
+    fn eval_guard() {
+        let input = |v| {
+            format!(
+                "🧩 {} 👉 n ❓ n > 10 ➡️ 🧵big🧵 🧑‍🦲 👉 n ❓ n > 0 ➡️ 🧵small🧵 🧑‍🦲 🧑‍🦲",
+                v
+            )
+        };
+
+        assert_eq!(eval(&input(11)), Ok(Val::from("big")));
+        assert_eq!(eval_resolved(&input(11)), Ok(Val::from("big")));
+        assert_eq!(eval(&input(1)), Ok(Val::from("small")));
+        assert_eq!(
+            eval(&input(0)),
+            Err(RuntimeError::NoMatch("0".into()).into())
+        );
+    }
+
+    #[test]
+    fn eval_break_and_return() {
+        let input = "📦
+                👶 n = 0 💪
+                👶 found = 🔁
+                    ♻️ n = n + 1 💪
+                    🧩 n 👉 3 ➡️ 💔 n * 10 🧑‍🦲 🧑‍🦲 👉 _ ➡️ 🧑‍🦲 🧑‍🦲
+                🧑‍🦲 💪
+                👶 early = 🔁 🧩 💔 500 🧑‍🦲 👉 _ ➡️ 0 🧑‍🦲 🧑‍🦲 🧑‍🦲 💪
+                👶 f = 🧰 x ➡️
+                    🧩 x 👉 0 ➡️ 🔙 7000 🧑‍🦲 🧑‍🦲 👉 _ ➡️ 🧑‍🦲 🧑‍🦲 💪
+                    x * 2
+                🧑‍🦲 💪
+                👶 a = 📞 f 0 💪
+                👶 b = 📞 f 4 💪
+                found + early + a + b
+            🧑‍🦲";
+
+        assert_eq!(eval(input), Ok(Val::Number(7538)));
+        assert_eq!(eval_resolved(input), Ok(Val::Number(7538)));
+    }
+
+    #[test]
+    fn bindings_are_scoped() {
+        let input = "📦 👶 a = 1 💪 🧩 🧵🧵 👉 😵‍💫 👨‍👨‍👦a 🧑‍🦲 ➡️ 🧑‍🦲 🧑‍🦲 💪 a 🧑‍🦲";
+
+        assert_eq!(eval(input), Ok(Val::Number(1)));
+        assert_eq!(eval_resolved(input), Ok(Val::Number(1)));
+    }
+
+    #[test]
+    fn format() {
+        let input = "🧩 v 👉 😵‍💫 a 💪 👨‍👨‍👦_ 🧑‍🦲 ❓ a > 1 ➡️ a 🧑‍🦲 👉 🧑‍🏫 x 💪 y:🔡c🔡 🧑‍🦲 ➡️ 🧑‍🦲 🧑‍🦲";
+        let expected = "🧩 v
+    👉 😵‍💫 a 💪 👨‍👨‍👦_ 🧑‍🦲 ❓ a > 1 ➡️
+        a
+    🧑‍🦲
+    👉 🧑‍🏫 x 💪 y:🔡c🔡 🧑‍🦲 ➡️
+    🧑‍🦲
+🧑‍🦲";
+        let (_, match_e) = Expr::new(input).unwrap();
+
+        assert_eq!(format!("{}", Display(&match_e)), expected);
+        assert_eq!(Expr::new(expected), Ok(("", match_e)));
+    }
+}
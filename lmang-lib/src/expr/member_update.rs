@@ -0,0 +1,161 @@
+use crate::env::{Env, Eval};
+use crate::error::{ParseError, ParseErrorKind};
+use crate::expr::index::{self, Index};
+use crate::expr::{BindingUsage, Expr, Format};
+use crate::utils::{self, kwords};
+use crate::val::Val;
+
+/// Sets a member of an object, like `♻️ obj🪆field = value`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MemberUpdate {
+    /// Object whose member is set, the root along with all but the last
+    /// index.
+    pub(crate) object: Expr,
+    pub(crate) member: String,
+    pub(crate) val: Expr,
+}
+
+impl MemberUpdate {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let start = s;
+        let s = utils::tag(kwords::SET, s)?;
+
+        let (s, _) = utils::extract_whitespace(s);
+        let (mut s, root) = BindingUsage::new(s)?;
+
+        let mut idents = Vec::new();
+        while let Ok(new_s) = utils::tag(kwords::INDEX, s) {
+            let (new_s, ident) = utils::extract_ident(new_s)?;
+            idents.push(ident.to_string());
+            s = new_s;
+        }
+        // without an index this is a plain binding update.
+        let member = idents
+            .pop()
+            .ok_or_else(|| ParseErrorKind::ExpectedIndex.at(start))?;
+
+        let root = Expr::BindingUsage(root);
+        let object = if idents.is_empty() {
+            root
+        } else {
+            Expr::Index(Box::new(Index { root, idents }))
+        };
+
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::UPDATE_SEP, s)?;
+        if s.starts_with(kwords::UPDATE_SEP) {
+            return Err(ParseErrorKind::UnexpectedEquals.at(s));
+        }
+
+        let (s, val) = Expr::new(s)?;
+        let member_update = MemberUpdate {
+            object,
+            member,
+            val,
+        };
+
+        Ok((s, member_update))
+    }
+}
+
+impl Eval for MemberUpdate {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        let value = env.eval(&self.val)?;
+        if let Val::Break(_) | Val::Return(_) = value {
+            return Ok(value);
+        }
+
+        let object = env.eval(&self.object)?;
+        index::set_member(object, &self.member, value)?;
+
+        Ok(Val::Unit)
+    }
+}
+
+impl Format for MemberUpdate {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        write!(w, "{} ", kwords::SET)?;
+        self.object.format(w, depth)?;
+        write!(w, "{}{} = ", kwords::INDEX, self.member)?;
+        self.val.format(w, depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RuntimeError;
+    use crate::expr::{Display, Literal};
+    use crate::test_utils::eval;
+
+    #[test]
+    fn parse_member_update() {
+        let expected = MemberUpdate {
+            object: Expr::Index(Box::new(Index {
+                root: Expr::BindingUsage(BindingUsage {
+                    name: "a".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
+                }),
+                idents: vec!["b".to_string()],
+            })),
+            member: "c".to_string(),
+            val: Expr::Literal(Literal(Val::Number(1))),
+        };
+
+        assert_eq!(MemberUpdate::new("♻️ a🪆b🪆c = 1"), Ok(("", expected)));
+    }
+
+    #[test]
+    fn parse_binding_update() {
+        let result = MemberUpdate::new("♻️ a = 1");
+        assert_eq!(result, Err(ParseErrorKind::ExpectedIndex.at("♻️ a = 1")));
+    }
+
+    #[test]
+    fn set_member() {
+        let input = "📦
+                👶 obj = 🧑‍🏫 👶 x = 1 💪 👶 get = 🧰 ➡️ x 🧑‍🦲 🧑‍🦲 💪
+                ♻️ obj🪆x = 5 💪
+                📞 obj🪆get
+            🧑‍🦲";
+        let result = eval(input).map(|x| x.apply_to_root(Val::clone).unwrap());
+        assert_eq!(result, Ok(Val::Number(5)));
+    }
+
+    #[test]
+    fn set_nested_member() {
+        let input = "📦
+                👶 outer = 🧑‍🏫 👶 inner = 🧑‍🏫 👶 x = 1 🧑‍🦲 🧑‍🦲 💪
+                👶 alias = outer🪆inner 💪
+                ♻️ outer🪆inner🪆x = 7 💪
+                alias🪆x
+            🧑‍🦲";
+        let result = eval(input).map(|x| x.apply_to_root(Val::clone).unwrap());
+        assert_eq!(result, Ok(Val::Number(7)));
+    }
+
+    #[test]
+    fn set_missing_member() {
+        let input = "📦 👶 obj = 🧑‍🏫 👶 x = 1 🧑‍🦲 💪 ♻️ obj🪆y = 5 🧑‍🦲";
+        assert_eq!(eval(input), Err(RuntimeError::NoKey("y".into()).into()));
+    }
+
+    #[test]
+    fn set_read_only_member() {
+        let input = "📦 👶 err = 👩‍🚒 x 🧑‍🦲 🤡 e ➡️ e 🧑‍🦲 💪 ♻️ err🪆binding = 5 🧑‍🦲";
+        let expected = RuntimeError::ReadOnlyMember("binding".into());
+        assert_eq!(eval(input), Err(expected.into()));
+    }
+
+    #[test]
+    fn format() {
+        let (_, update_e) = Expr::new("♻️ a🪆b🪆c=📦1🧑‍🦲").unwrap();
+        assert_eq!(
+            format!("{}", Display(&update_e)),
+            "♻️ a🪆b🪆c = 📦\n    1\n🧑‍🦲"
+        );
+    }
+}
@@ -4,17 +4,28 @@ pub mod block;
 pub mod break_expr;
 pub mod call;
 pub mod class;
+pub mod for_expr;
 pub mod func;
 pub mod if_expr;
+pub mod import;
 pub mod index;
 pub mod literal;
 pub mod loop_expr;
+pub mod map;
+pub mod match_expr;
+pub mod member_update;
 pub mod named;
 pub mod ref_expr;
+pub(crate) mod resolve;
+pub mod return_expr;
+pub mod throw;
 pub mod try_expr;
+pub mod while_expr;
+pub mod yield_expr;
 
+use crate::builtins::EVAL;
 use crate::env::{Env, Eval};
-use crate::error::{ParseError, RuntimeError};
+use crate::error::{ParseError, ParseErrorKind, RuntimeError, Span, TraceFrame};
 use crate::utils::{self, kwords};
 use crate::val::Val;
 use binding_update::BindingUpdate;
@@ -23,14 +34,23 @@ use block::{Block, FormatExplicit};
 use break_expr::Break;
 use call::Call;
 use class::Class;
-use func::Func;
+use for_expr::{For, Iterable};
+use func::{Arg, Func};
 use if_expr::If;
+use import::Import;
 use index::Index;
 use literal::Literal;
 use loop_expr::Loop;
+use map::Map;
+use match_expr::Match;
+use member_update::MemberUpdate;
 use named::Named;
 use ref_expr::Ref;
+use return_expr::Return;
+use throw::Throw;
 use try_expr::Try;
+use while_expr::While;
+use yield_expr::Yield;
 
 pub trait Format {
     fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result;
@@ -56,12 +76,16 @@ pub enum Op {
     Sub,
     Mul,
     Div,
+    Mod,
     Greater,
     GreaterEq,
     Eq,
     LessEq,
     Less,
+    NotEq,
     FuzzyEq,
+    And,
+    Or,
 }
 
 impl Op {
@@ -71,12 +95,97 @@ impl Op {
             .or_else(|_| utils::tag(kwords::SUB, s).map(|s| (s, Self::Sub)))
             .or_else(|_| utils::tag(kwords::MUL, s).map(|s| (s, Self::Mul)))
             .or_else(|_| utils::tag(kwords::DIV, s).map(|s| (s, Self::Div)))
+            .or_else(|_| utils::tag(kwords::MOD, s).map(|s| (s, Self::Mod)))
             .or_else(|_| utils::tag(kwords::GE, s).map(|s| (s, Self::GreaterEq)))
             .or_else(|_| utils::tag(kwords::GT, s).map(|s| (s, Self::Greater)))
             .or_else(|_| utils::tag(kwords::LE, s).map(|s| (s, Self::LessEq)))
             .or_else(|_| utils::tag(kwords::LT, s).map(|s| (s, Self::Less)))
             .or_else(|_| utils::tag(kwords::EQ, s).map(|s| (s, Self::Eq)))
+            .or_else(|_| utils::tag(kwords::NE, s).map(|s| (s, Self::NotEq)))
             .or_else(|_| utils::tag(kwords::FE, s).map(|s| (s, Self::FuzzyEq)))
+            .or_else(|_| utils::tag(kwords::AND, s).map(|s| (s, Self::And)))
+            .or_else(|_| utils::tag(kwords::OR, s).map(|s| (s, Self::Or)))
+    }
+
+    /// Result of a logical operation decided by its left operand alone, in
+    /// which case the right one is not evaluated. A left operand that isn't a
+    /// boolean is rejected before the right one is evaluated too.
+    pub(crate) fn short_circuit(&self, lhs: &Val) -> Result<Option<Val>, RuntimeError> {
+        let (decided, op) = match self {
+            Op::And => (false, kwords::AND),
+            Op::Or => (true, kwords::OR),
+            _ => return Ok(None),
+        };
+
+        lhs.apply_to_root(|v| match v {
+            Val::Bool(b) if *b == decided => Ok(Some(Val::Bool(decided))),
+            Val::Bool(_) => Ok(None),
+            _ => Err(RuntimeError::InvalidOp {
+                lhs: v.variant_name().into(),
+                op: op.into(),
+                rhs: "".into(),
+            }),
+        })?
+    }
+
+    /// Member of class objects overloading the operator, and whether its
+    /// result is negated.
+    fn overload(&self) -> Option<(&'static str, bool)> {
+        Some(match self {
+            Op::Add => (class::ADD, false),
+            Op::Sub => (class::SUB, false),
+            Op::Mul => (class::MUL, false),
+            Op::Div => (class::DIV, false),
+            Op::Less => (class::LT, false),
+            Op::Greater => (class::GT, false),
+            Op::Eq => (class::EQ, false),
+            Op::NotEq => (class::EQ, true),
+            Op::FuzzyEq => (class::FUZZY_EQ, false),
+            _ => return None,
+        })
+    }
+
+    /// Applies the operator, through the member overloading it if either
+    /// operand is a class object with one. The member is called with both
+    /// operands, the left one first.
+    pub(crate) fn eval(&self, lhs: &Val, rhs: &Val, env: &mut Env) -> Result<Val, Val> {
+        if let Some((name, negated)) = self.overload() {
+            let method = class::class_member(lhs, name).or_else(|| class::class_member(rhs, name));
+            if let Some(method) = method {
+                let mut args = [lhs.clone(), rhs.clone()];
+                let result = call::call_val(&method, &mut args, env, || TraceFrame {
+                    name: name.to_string(),
+                    span: None,
+                })?;
+
+                return Ok(if negated { (!&result)? } else { result });
+            }
+        }
+
+        Ok(self.apply(lhs, rhs)?)
+    }
+
+    pub(crate) fn apply(&self, lhs: &Val, rhs: &Val) -> Result<Val, RuntimeError> {
+        Ok(match self {
+            Op::Add => (lhs + rhs)?,
+            Op::Sub => (lhs - rhs)?,
+            Op::Mul => (lhs * rhs)?,
+            Op::Div => (lhs / rhs)?,
+            Op::Mod => (lhs % rhs)?,
+            Op::Greater => lhs.try_gt(rhs)?,
+            Op::GreaterEq => lhs.try_ge(rhs)?,
+            Op::Eq => Val::Bool(lhs.equals(rhs)),
+            Op::NotEq => Val::Bool(!lhs.equals(rhs)),
+            Op::FuzzyEq => {
+                let b = lhs.apply_to_root(|v1| rhs.apply_to_root(|v2| v1.equals(v2)))??;
+
+                Val::Bool(b)
+            }
+            Op::LessEq => lhs.try_le(rhs)?,
+            Op::Less => lhs.try_lt(rhs)?,
+            Op::And => lhs.try_and(rhs)?,
+            Op::Or => lhs.try_or(rhs)?,
+        })
     }
 }
 
@@ -92,12 +201,16 @@ impl crate::expr::Format for Op {
                 Sub => kwords::SUB,
                 Mul => kwords::MUL,
                 Div => kwords::DIV,
+                Mod => kwords::MOD,
                 GreaterEq => kwords::GE,
                 Greater => kwords::GT,
                 LessEq => kwords::LE,
                 Less => kwords::LT,
                 Eq => kwords::EQ,
+                NotEq => kwords::NE,
                 FuzzyEq => kwords::FE,
+                And => kwords::AND,
+                Or => kwords::OR,
             }
         )?;
 
@@ -105,6 +218,36 @@ impl crate::expr::Format for Op {
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        utils::tag(kwords::SUB, s)
+            .map(|s| (s, Self::Neg))
+            .or_else(|_| utils::tag(kwords::NOT, s).map(|s| (s, Self::Not)))
+    }
+
+    pub(crate) fn apply(&self, val: &Val) -> Result<Val, RuntimeError> {
+        match self {
+            UnaryOp::Neg => -val,
+            UnaryOp::Not => !val,
+        }
+    }
+}
+
+impl crate::expr::Format for UnaryOp {
+    fn format(&self, w: &mut dyn std::fmt::Write, _depth: usize) -> std::fmt::Result {
+        match self {
+            UnaryOp::Neg => write!(w, "{}", kwords::SUB),
+            UnaryOp::Not => write!(w, "{}", kwords::NOT),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
     Operation {
@@ -112,44 +255,242 @@ pub enum Expr {
         rhs: Box<Expr>,
         op: Op,
     },
+    UnaryOperation {
+        operand: Box<Expr>,
+        op: UnaryOp,
+    },
     Literal(Literal),
     BindingUpdate(Box<BindingUpdate>),
+    MemberUpdate(Box<MemberUpdate>),
     BindingUsage(BindingUsage),
     Block(Block),
     Class(Box<Class>),
     If(Box<If>),
     Index(Box<Index>),
     Break(Box<Break>),
+    Return(Box<Return>),
+    Yield(Box<Yield>),
     Loop(Box<Loop>),
+    For(Box<For>),
+    While(Box<While>),
+    Map(Box<Map>),
     Func(Box<Func>),
     Call(Box<Call>),
     Ref(Ref),
     Try(Box<Try>),
+    Match(Box<Match>),
+    Throw(Box<Throw>),
+    Import(Box<Import>),
     Named(Box<Named>),
 }
 
+type ExprParser = fn(&str) -> Result<(&str, Expr), ParseError>;
+
 impl Expr {
     pub fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
 
-        BindingUpdate::new(s)
-            .map(|(s, update)| (s, Self::BindingUpdate(Box::new(update))))
-            .or_else(|_| {
-                Named::new(s).map(|(s, named_expr)| (s, Self::Named(Box::new(named_expr))))
-            })
-            .or_else(|_| Self::new_operation(s))
-            .or_else(|_| Block::explicit(s).map(|(s, block)| (s, Self::Block(block))))
-            .or_else(|_| Call::new(s).map(|(s, call_e)| (s, Self::Call(Box::new(call_e)))))
-            .or_else(|_| Class::new(s).map(|(s, class_e)| (s, Self::Class(Box::new(class_e)))))
-            .or_else(|_| If::new(s).map(|(s, if_e)| (s, Self::If(Box::new(if_e)))))
-            .or_else(|_| Index::new(s).map(|(s, index_e)| (s, Self::Index(Box::new(index_e)))))
-            .or_else(|_| Break::new(s).map(|(s, break_e)| (s, Self::Break(Box::new(break_e)))))
-            .or_else(|_| Loop::new(s).map(|(s, loop_e)| (s, Self::Loop(Box::new(loop_e)))))
-            .or_else(|_| Try::new(s).map(|(s, try_e)| (s, Self::Try(Box::new(try_e)))))
-            .or_else(|_| Func::new(s).map(|(s, func_e)| (s, Self::Func(Box::new(func_e)))))
-            .or_else(|_| Literal::new(s).map(|(s, literal)| (s, Self::Literal(literal))))
-            .or_else(|_| BindingUsage::new(s).map(|(s, usage)| (s, Self::BindingUsage(usage))))
-            .or_else(|_| Ref::new(s).map(|(s, ref_expr)| (s, Self::Ref(ref_expr))))
+        let parsers: [ExprParser; 25] = [
+            |s| MemberUpdate::new(s).map(|(s, update)| (s, Self::MemberUpdate(Box::new(update)))),
+            |s| BindingUpdate::new(s).map(|(s, update)| (s, Self::BindingUpdate(Box::new(update)))),
+            |s| Named::new(s).map(|(s, named_expr)| (s, Self::Named(Box::new(named_expr)))),
+            Self::new_unary_operation,
+            Self::new_operation,
+            |s| Block::explicit(s).map(|(s, block)| (s, Self::Block(block))),
+            |s| Call::new(s).map(|(s, call_e)| (s, Self::Call(Box::new(call_e)))),
+            |s| Class::new(s).map(|(s, class_e)| (s, Self::Class(Box::new(class_e)))),
+            |s| If::new(s).map(|(s, if_e)| (s, Self::If(Box::new(if_e)))),
+            |s| Index::new(s).map(|(s, index_e)| (s, Self::Index(Box::new(index_e)))),
+            |s| Break::new(s).map(|(s, break_e)| (s, Self::Break(Box::new(break_e)))),
+            |s| Return::new(s).map(|(s, return_e)| (s, Self::Return(Box::new(return_e)))),
+            |s| Yield::new(s).map(|(s, yield_e)| (s, Self::Yield(Box::new(yield_e)))),
+            |s| Loop::new(s).map(|(s, loop_e)| (s, Self::Loop(Box::new(loop_e)))),
+            |s| For::new(s).map(|(s, for_e)| (s, Self::For(Box::new(for_e)))),
+            |s| While::new(s).map(|(s, while_e)| (s, Self::While(Box::new(while_e)))),
+            |s| Map::new(s).map(|(s, map_e)| (s, Self::Map(Box::new(map_e)))),
+            |s| Try::new(s).map(|(s, try_e)| (s, Self::Try(Box::new(try_e)))),
+            |s| Match::new(s).map(|(s, match_e)| (s, Self::Match(Box::new(match_e)))),
+            |s| Throw::new(s).map(|(s, throw_e)| (s, Self::Throw(Box::new(throw_e)))),
+            |s| Import::new(s).map(|(s, import_e)| (s, Self::Import(Box::new(import_e)))),
+            |s| Func::new(s).map(|(s, func_e)| (s, Self::Func(Box::new(func_e)))),
+            |s| Literal::new(s).map(|(s, literal)| (s, Self::Literal(literal))),
+            |s| BindingUsage::new(s).map(|(s, usage)| (s, Self::BindingUsage(usage))),
+            |s| Ref::new(s).map(|(s, ref_expr)| (s, Self::Ref(ref_expr))),
+        ];
+
+        for parser in parsers {
+            match parser(s) {
+                Ok(parsed) => return Ok(parsed),
+                // a parser that failed past the first token recognized its
+                // expression, so the input is malformed rather than different.
+                Err(e) if e.progressed_past(s) => return Err(e),
+                Err(_) => continue,
+            }
+        }
+
+        Err(ParseErrorKind::ExpectedExpr.at(s))
+    }
+
+    /// Parses a complete program, attaching source positions to errors and
+    /// call sites.
+    pub fn parse(source: &str) -> Result<(&str, Self), ParseError> {
+        let (s, mut expr) = Self::new(source).map_err(|e| e.locate(source))?;
+        let locate = |rem| Some(Span::locate(source, source.len() - rem));
+        expr.visit_mut(&mut |e| match e {
+            Self::Call(call_e) => call_e.span = locate(call_e.rem),
+            Self::BindingUsage(usage) => usage.span = locate(usage.rem),
+            Self::BindingUpdate(bu) => bu.span = locate(bu.rem),
+            Self::Ref(ref_e) => ref_e.span = locate(ref_e.rem),
+            _ => {}
+        });
+
+        Ok((s, expr))
+    }
+
+    /// Resolves locals to slots ahead of evaluation in `env`, failing on names
+    /// bound neither locally nor globally.
+    pub fn resolve(&mut self, env: &Env) -> Result<(), ParseError> {
+        // code run by 🪞 sees the locals of its caller, which then have to be
+        // kept by name.
+        if self.calls_eval() {
+            return Ok(());
+        }
+
+        resolve::Resolver::new(self).expr(self, env)
+    }
+
+    /// Whether the expression uses `🪞`, leaving out the bodies of the
+    /// functions defined in it.
+    pub(crate) fn calls_eval(&mut self) -> bool {
+        let mut found = false;
+        self.walk(&mut |e| found |= e.is_eval(), false);
+
+        found
+    }
+
+    fn is_eval(&self) -> bool {
+        match self {
+            Self::BindingUsage(usage) => usage.name == EVAL,
+            Self::Ref(ref_e) => ref_e.ident == EVAL,
+            _ => false,
+        }
+    }
+
+    /// Calls `f` on this expression and every expression nested in it.
+    pub(crate) fn visit_mut(&mut self, f: &mut dyn FnMut(&mut Expr)) {
+        self.walk(f, true);
+    }
+
+    /// Visits like `visit_mut`, leaving out the bodies of the functions
+    /// defined in the expression unless `funcs` is set.
+    fn walk(&mut self, f: &mut dyn FnMut(&mut Expr), funcs: bool) {
+        f(self);
+
+        match self {
+            Self::Operation { lhs, rhs, .. } => {
+                lhs.walk(f, funcs);
+                rhs.walk(f, funcs);
+            }
+            Self::UnaryOperation { operand, .. } => operand.walk(f, funcs),
+            Self::Literal(_) | Self::BindingUsage(_) | Self::Ref(_) => {}
+            Self::BindingUpdate(bu) => bu.val.walk(f, funcs),
+            Self::MemberUpdate(mu) => {
+                mu.object.walk(f, funcs);
+                mu.val.walk(f, funcs);
+            }
+            Self::Block(block) => block.walk(f, funcs),
+            Self::Class(class) => {
+                if let Some(base) = &mut class.base {
+                    base.walk(f, funcs);
+                }
+                class.body.walk(f, funcs);
+            }
+            Self::If(if_e) => {
+                if_e.cond.walk(f, funcs);
+                if_e.body.walk(f, funcs);
+                for (cond, body) in &mut if_e.elifs {
+                    cond.walk(f, funcs);
+                    body.walk(f, funcs);
+                }
+                if let Some(body_else) = &mut if_e.body_else {
+                    body_else.walk(f, funcs);
+                }
+            }
+            Self::Index(index_e) => index_e.root.walk(f, funcs),
+            Self::Break(break_e) => break_e.body.walk(f, funcs),
+            Self::Return(return_e) => return_e.body.walk(f, funcs),
+            Self::Yield(yield_e) => yield_e.body.walk(f, funcs),
+            Self::Throw(throw_e) => throw_e.body.walk(f, funcs),
+            Self::Import(import_e) => import_e.path.walk(f, funcs),
+            Self::Loop(loop_e) => loop_e.body.walk(f, funcs),
+            Self::For(for_e) => {
+                match &mut for_e.iterable {
+                    Iterable::Items(expr) => expr.walk(f, funcs),
+                    Iterable::Range(start, end) => {
+                        start.walk(f, funcs);
+                        end.walk(f, funcs);
+                    }
+                }
+                for_e.body.walk(f, funcs);
+            }
+            Self::While(while_e) => {
+                while_e.cond.walk(f, funcs);
+                while_e.body.walk(f, funcs);
+            }
+            Self::Map(map_e) => {
+                for (key, val) in &mut map_e.entries {
+                    key.walk(f, funcs);
+                    val.walk(f, funcs);
+                }
+            }
+            Self::Func(func_e) => {
+                for arg in &mut func_e.args {
+                    if let Arg::Default(_, default) = arg {
+                        default.walk(f, funcs);
+                    }
+                }
+                if funcs {
+                    func_e.body.walk(f, funcs);
+                }
+            }
+            Self::Call(call_e) => {
+                call_e.func.walk(f, funcs);
+                for arg in &mut call_e.args {
+                    arg.walk(f, funcs);
+                }
+            }
+            Self::Try(try_e) => {
+                try_e.try_block.walk(f, funcs);
+                for (_, handler) in &mut try_e.except_blocks {
+                    handler.body.walk(f, funcs);
+                }
+                if let Some(handler) = &mut try_e.except_any_block {
+                    handler.body.walk(f, funcs);
+                }
+                if let Some(body) = &mut try_e.finally_block {
+                    body.walk(f, funcs);
+                }
+            }
+            Self::Match(match_e) => {
+                match_e.val.walk(f, funcs);
+                for arm in &mut match_e.arms {
+                    if let Some(guard) = &mut arm.guard {
+                        guard.walk(f, funcs);
+                    }
+                    arm.body.walk(f, funcs);
+                }
+            }
+            Self::Named(named_e) => named_e.expr.walk(f, funcs),
+        }
+    }
+
+    /// Accounts for `n` bytes cut off the input an expression was parsed from.
+    pub(crate) fn untruncate(&mut self, n: usize) {
+        self.visit_mut(&mut |e| match e {
+            Self::Call(call_e) => call_e.rem += n,
+            Self::BindingUsage(usage) => usage.rem += n,
+            _ => {}
+        });
     }
 
     fn new_operation(s: &str) -> Result<(&str, Self), ParseError> {
@@ -162,18 +503,24 @@ impl Expr {
                 break;
             }
 
-            let c = s.chars().nth(op_c_idx).ok_or(ParseError::UnexpectedEof)?;
+            let c = s
+                .chars()
+                .nth(op_c_idx)
+                .ok_or_else(|| ParseErrorKind::UnexpectedEof.at(s))?;
 
             op_b_idx += c.len_utf8();
             op_c_idx += 1;
         }
 
-        let (sub, lhs) = Expr::new(&s[0..op_b_idx])?;
+        // errors within the truncated lhs are reported at its beginning, the
+        // same input is going to be tried by other expression parsers anyway.
+        let (sub, mut lhs) = Expr::new(&s[0..op_b_idx]).map_err(|e| e.at(s))?;
         let (sub, _) = utils::extract_whitespace(sub);
 
         if !sub.is_empty() {
-            return Err(ParseError::ExpectedExpr);
+            return Err(ParseErrorKind::ExpectedExpr.at(s));
         }
+        lhs.untruncate(s.len() - op_b_idx);
 
         let s = &s[op_b_idx..];
 
@@ -191,6 +538,22 @@ impl Expr {
             },
         ))
     }
+
+    // like binary operators, unary ones apply to everything to their right.
+    fn new_unary_operation(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, op) = UnaryOp::new(s)?;
+        let (s, _) = utils::extract_whitespace(s);
+
+        let (s, operand) = Expr::new(s)?;
+
+        Ok((
+            s,
+            Self::UnaryOperation {
+                operand: Box::new(operand),
+                op,
+            },
+        ))
+    }
 }
 
 impl Eval for Expr {
@@ -198,46 +561,44 @@ impl Eval for Expr {
         let result = match self {
             Self::Operation { lhs, rhs, op } => {
                 let lhs = env.eval(lhs.as_ref())?;
+                if let Some(result) = op.short_circuit(&lhs)? {
+                    return Ok(result);
+                }
                 let rhs = env.eval(rhs.as_ref())?;
 
-                Ok(match op {
-                    Op::Add => (&lhs + &rhs)?,
-                    Op::Sub => (&lhs - &rhs)?,
-                    Op::Mul => (&lhs * &rhs)?,
-                    Op::Div => (&lhs / &rhs)?,
-                    Op::Greater => lhs.try_gt(&rhs)?,
-                    Op::GreaterEq => lhs.try_ge(&rhs)?,
-                    Op::Eq => Val::Bool(lhs == rhs),
-                    Op::FuzzyEq => {
-                        let b = lhs.apply_to_root(|v1| rhs.apply_to_root(|v2| v1 == v2))??;
-
-                        Val::Bool(b)
-                    }
-                    Op::LessEq => lhs.try_le(&rhs)?,
-                    Op::Less => lhs.try_lt(&rhs)?,
-                })
+                op.eval(&lhs, &rhs, env)
+            }
+            Self::UnaryOperation { operand, op } => {
+                let operand = env.eval(operand.as_ref())?;
+
+                Ok(op.apply(&operand)?)
             }
             Self::BindingUpdate(bu) => env.eval(bu.as_ref()),
+            Self::MemberUpdate(mu) => env.eval(mu.as_ref()),
             Self::BindingUsage(bu) => env.eval(bu),
             Self::Block(block) => env.eval(block),
             Self::Class(class) => env.eval(class.as_ref()),
             Self::If(if_e) => env.eval(if_e.as_ref()),
             Self::Index(index_e) => env.eval(index_e.as_ref()),
             Self::Break(break_e) => env.eval(break_e.as_ref()),
+            Self::Return(return_e) => env.eval(return_e.as_ref()),
+            Self::Yield(yield_e) => env.eval(yield_e.as_ref()),
+            Self::Throw(throw_e) => env.eval(throw_e.as_ref()),
+            Self::Import(import_e) => env.eval(import_e.as_ref()),
             Self::Loop(loop_e) => env.eval(loop_e.as_ref()),
+            Self::For(for_e) => env.eval(for_e.as_ref()),
+            Self::While(while_e) => env.eval(while_e.as_ref()),
+            Self::Map(map_e) => env.eval(map_e.as_ref()),
             Self::Func(func_e) => env.eval(func_e.as_ref()),
             Self::Call(call_e) => env.eval(call_e.as_ref()),
             Self::Literal(lit) => Ok(lit.0.clone()),
             Self::Ref(ref_expr) => env.eval(ref_expr),
             Self::Try(try_expr) => env.eval(try_expr.as_ref()),
+            Self::Match(match_e) => env.eval(match_e.as_ref()),
             Self::Named(named_expr) => env.eval(named_expr.as_ref()),
         };
 
-        let weak_err = RuntimeError::Dangling;
-        match result {
-            Ok(Val::Weak(ref wk)) => Ok(Val::Ref(wk.upgrade().ok_or(weak_err)?)),
-            other => other,
-        }
+        Ok(result?.upgrade()?)
     }
 }
 
@@ -251,19 +612,32 @@ impl crate::expr::Format for Expr {
                 write!(w, " ")?;
                 rhs.as_ref().format(w, depth)?;
             }
+            Self::UnaryOperation { operand, op } => {
+                op.format(w, depth)?;
+                operand.as_ref().format(w, depth)?;
+            }
             Self::BindingUpdate(bu) => bu.as_ref().format(w, depth)?,
+            Self::MemberUpdate(mu) => mu.as_ref().format(w, depth)?,
             Self::BindingUsage(bu) => bu.format(w, depth)?,
             Self::Block(block) => FormatExplicit(block).format(w, depth)?,
             Self::Class(class) => class.as_ref().format(w, depth)?,
             Self::If(if_e) => if_e.as_ref().format(w, depth)?,
             Self::Index(index_e) => index_e.as_ref().format(w, depth)?,
             Self::Break(break_e) => break_e.as_ref().format(w, depth)?,
+            Self::Return(return_e) => return_e.as_ref().format(w, depth)?,
+            Self::Yield(yield_e) => yield_e.as_ref().format(w, depth)?,
+            Self::Throw(throw_e) => throw_e.as_ref().format(w, depth)?,
+            Self::Import(import_e) => import_e.as_ref().format(w, depth)?,
             Self::Loop(loop_e) => loop_e.as_ref().format(w, depth)?,
+            Self::For(for_e) => for_e.as_ref().format(w, depth)?,
+            Self::While(while_e) => while_e.as_ref().format(w, depth)?,
+            Self::Map(map_e) => map_e.as_ref().format(w, depth)?,
             Self::Func(func_e) => func_e.as_ref().format(w, depth)?,
             Self::Call(call_e) => call_e.as_ref().format(w, depth)?,
-            Self::Literal(v) => write!(w, "{}", v.0)?,
+            Self::Literal(v) => v.format(w, depth)?,
             Self::Ref(ref_expr) => ref_expr.format(w, depth)?,
             Self::Try(try_expr) => try_expr.as_ref().format(w, depth)?,
+            Self::Match(match_e) => match_e.as_ref().format(w, depth)?,
             Self::Named(named_expr) => named_expr.as_ref().format(w, depth)?,
         };
 
@@ -422,6 +796,9 @@ mod tests {
                 "",
                 Expr::BindingUsage(BindingUsage {
                     name: "bar".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 }),
             )),
         );
@@ -442,10 +819,12 @@ mod tests {
 
     #[test]
     fn eval_cmp() {
+        type CmpFn = Box<dyn Fn(i32, i32) -> bool>;
+
         let nums: Vec<_> = (0..10).collect();
         for n1 in &nums {
             for n2 in &nums {
-                let ops: [(&'static str, Box<dyn Fn(i32, i32) -> bool>); 5] = [
+                let ops: [(&'static str, CmpFn); 5] = [
                     (">", Box::new(|a, b| a > b)),
                     (">=", Box::new(|a, b| a >= b)),
                     ("==", Box::new(|a, b| a == b)),
@@ -467,6 +846,216 @@ mod tests {
         }
     }
 
+    #[test]
+    fn eval_float_arith() {
+        let cases = [
+            ("1.5 + 2", Val::Float(3.5)),
+            ("2 - 0.5", Val::Float(1.5)),
+            ("0.5 * 0.5", Val::Float(0.25)),
+            ("1 / 4.0", Val::Float(0.25)),
+            ("7 / 2", Val::Number(3)),
+            ("1.5 > 1", Val::Bool(true)),
+            ("2 <= 1.5", Val::Bool(false)),
+            ("0.1 < 0.2", Val::Bool(true)),
+            ("1.0 == 1", Val::Bool(true)),
+            ("2 != 2.5", Val::Bool(true)),
+        ];
+
+        for (input, expected) in cases {
+            let (_, expr) = Expr::new(input).unwrap();
+            let result = Env::test().eval(&expr);
+
+            assert_eq!(result, Ok(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn eval_float_invalid() {
+        let (_, expr) = Expr::new("1.5 + 🙆‍♀️").unwrap();
+        let result = Env::test().eval(&expr);
+        let expected = RuntimeError::CastError {
+            from: "🤨".into(),
+            to: "🔢".into(),
+        };
+
+        assert_eq!(result, Err(expected.into()));
+    }
+
+    #[test]
+    fn eval_int_overflow() {
+        let cases = [
+            "2147483647 + 1",
+            "📦 👶 m = 0 - 2 💪 m - 2147483647 🧑‍🦲",
+            "65536 * 65536",
+            "📦 👶 m = 0 - 2147483647 💪 ♻️ m = m - 1 💪 m / 📦 0 - 1 🧑‍🦲 🧑‍🦲",
+        ];
+
+        for input in cases {
+            let (_, expr) = Expr::new(input).unwrap();
+            let result = Env::test().eval(&expr);
+
+            assert_eq!(result, Err(RuntimeError::Overflow.into()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn eval_div_by_zero() {
+        let (_, expr) = Expr::new("1 / 0").unwrap();
+        let result = Env::test().eval(&expr);
+        assert_eq!(result, Err(RuntimeError::DivisionByZero.into()));
+
+        let (_, expr) = Expr::new("1 / 0.0").unwrap();
+        let result = Env::test().eval(&expr);
+        assert_eq!(result, Ok(Val::Float(f64::INFINITY)));
+    }
+
+    #[test]
+    fn catch_overflow() {
+        let (_, expr) = Expr::new("👩‍🚒 2147483647 * 2 🧑‍🦲 🤡 Overflow 0 🧑‍🦲").unwrap();
+        let result = Env::test().eval(&expr);
+
+        assert_eq!(result, Ok(Val::Number(0)));
+    }
+
+    #[test]
+    fn parse_number_out_of_range() {
+        let result = Expr::new("📦 1 + 2147483648 🧑‍🦲");
+        let expected = ParseErrorKind::NumberOutOfRange.at(" 🧑‍🦲");
+
+        assert_eq!(result, Err(expected));
+    }
+
+    #[test]
+    fn parse_logic_ops() {
+        assert_eq!(Op::new("!="), Ok(("", Op::NotEq)));
+        assert_eq!(Op::new("%"), Ok(("", Op::Mod)));
+        assert_eq!(Op::new("🤝"), Ok(("", Op::And)));
+        assert_eq!(Op::new("🔀"), Ok(("", Op::Or)));
+        assert_eq!(UnaryOp::new("🚫"), Ok(("", UnaryOp::Not)));
+        assert_eq!(UnaryOp::new("-"), Ok(("", UnaryOp::Neg)));
+    }
+
+    #[test]
+    fn parse_unary_applies_to_the_right() {
+        assert_eq!(
+            Expr::new("- 1 + 2"),
+            Ok((
+                "",
+                Expr::UnaryOperation {
+                    operand: Box::new(Expr::Operation {
+                        lhs: Box::new(Expr::Literal(Literal(Val::Number(1)))),
+                        rhs: Box::new(Expr::Literal(Literal(Val::Number(2)))),
+                        op: Op::Add,
+                    }),
+                    op: UnaryOp::Neg,
+                },
+            )),
+        );
+    }
+
+    #[test]
+    fn eval_logic_ops() {
+        let cases = [
+            ("1 != 2", Val::Bool(true)),
+            ("🧵a🧵 != 🧵a🧵", Val::Bool(false)),
+            ("7 % 3", Val::Number(1)),
+            ("7.5 % 2", Val::Float(1.5)),
+            ("🙆‍♀️ 🤝 🙅‍♀️", Val::Bool(false)),
+            ("🙆‍♀️ 🤝 🙆‍♀️", Val::Bool(true)),
+            ("🙅‍♀️ 🔀 🙆‍♀️", Val::Bool(true)),
+            ("🙅‍♀️ 🔀 🙅‍♀️", Val::Bool(false)),
+            ("🚫 🙅‍♀️", Val::Bool(true)),
+            ("🚫 🙆‍♀️ 🔀 🙆‍♀️", Val::Bool(false)),
+            ("- 3", Val::Number(-3)),
+            ("4 - -3", Val::Number(7)),
+            ("-1.5", Val::Float(-1.5)),
+        ];
+
+        for (input, expected) in cases {
+            let (_, expr) = Expr::new(input).unwrap();
+            let result = Env::test().eval(&expr);
+
+            assert_eq!(result, Ok(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn eval_short_circuit() {
+        let cases = [
+            ("🙅‍♀️ 🤝 1 / 0", Val::Bool(false)),
+            ("🙆‍♀️ 🔀 1 / 0", Val::Bool(true)),
+        ];
+
+        for (input, expected) in cases {
+            let (_, expr) = Expr::new(input).unwrap();
+            let result = Env::test().eval(&expr);
+
+            assert_eq!(result, Ok(expected), "{}", input);
+        }
+
+        let (_, expr) = Expr::new("🙆‍♀️ 🤝 1 / 0").unwrap();
+        let result = Env::test().eval(&expr);
+
+        assert_eq!(result, Err(RuntimeError::DivisionByZero.into()));
+    }
+
+    #[test]
+    fn eval_logic_invalid() {
+        let invalid = |lhs: &str, op: &str, rhs: &str| {
+            Err(RuntimeError::InvalidOp {
+                lhs: lhs.into(),
+                op: op.into(),
+                rhs: rhs.into(),
+            }
+            .into())
+        };
+        let cases = [
+            ("1 🤝 🙆‍♀️", invalid("🔢", "🤝", "")),
+            ("🧵no🧵 🔀 1 / 0", invalid("🧵", "🔀", "")),
+            ("🙆‍♀️ 🤝 1", invalid("🤨", "🤝", "🔢")),
+            ("🙅‍♀️ 🔀 1.5", invalid("🤨", "🔀", "🛟")),
+            ("🚫 0", invalid("", "🚫", "🔢")),
+            ("- 🙆‍♀️", invalid("", "-", "🤨")),
+        ];
+
+        for (input, expected) in cases {
+            let (_, expr) = Expr::new(input).unwrap();
+            let result = Env::test().eval(&expr);
+
+            assert_eq!(result, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn eval_mod_neg_errors() {
+        let cases = [
+            ("1 % 0", RuntimeError::DivisionByZero),
+            (
+                "📦 👶 m = -2147483647 💪 - m - 1 🧑‍🦲",
+                RuntimeError::Overflow,
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let (_, expr) = Expr::new(input).unwrap();
+            let result = Env::test().eval(&expr);
+
+            assert_eq!(result, Err(expected.into()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn format_logic_ops() {
+        let input = "🚫 a 🤝 b != - c % 2";
+        let expected = "🚫a 🤝 b != -c % 2";
+
+        let (_, expr) = Expr::new(input).unwrap();
+        let formatted = format!("{}", Display(&expr));
+
+        assert_eq!(formatted, expected);
+        assert_eq!(Expr::new(&formatted), Ok(("", expr)));
+    }
+
     #[test]
     fn format() {
         let input = "📦🔁📞🗣️📞👂🧑‍🦲🧑‍🦲";
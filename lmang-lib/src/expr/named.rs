@@ -13,8 +13,10 @@ pub struct Named {
 impl Named {
     pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
         let (s, _) = utils::extract_whitespace(s);
+        let start = s;
         let (s, name) = utils::extract_ident(s)?;
-        let s = utils::tag(kwords::NAMED, s)?;
+        // a plain identifier isn't a malformed named expression.
+        let s = utils::tag(kwords::NAMED, s).map_err(|e| e.at(start))?;
         let (s, expr) = Expr::new(s)?;
 
         Ok((
@@ -55,6 +57,9 @@ mod tests {
             name: "arg".to_string(),
             expr: Expr::BindingUsage(BindingUsage {
                 name: "value".to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
             }),
         };
 
@@ -69,6 +74,9 @@ mod tests {
             expr: Expr::Operation {
                 lhs: Box::new(Expr::BindingUsage(BindingUsage {
                     name: "a".to_string(),
+                    slot: None,
+                    rem: 0,
+                    span: None,
                 })),
                 rhs: Box::new(Expr::Literal(Literal(Val::Number(3)))),
                 op: Op::Mul,
@@ -1,11 +1,17 @@
-use crate::env::{Env, Eval};
-use crate::error::ParseError;
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, Span};
 use crate::utils;
 use crate::val::Val;
+use std::fmt;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct Ref {
     pub(crate) ident: String,
+    pub(crate) slot: Option<Slot>,
+    /// Length of the input remaining at the name, resolved into `span` once
+    /// the whole program is parsed.
+    pub(crate) rem: usize,
+    pub(crate) span: Option<Span>,
 }
 
 impl Ref {
@@ -14,20 +20,43 @@ impl Ref {
         let s = utils::tag("🔖", s)?;
 
         let (s, _) = utils::extract_whitespace(s);
+        let rem = s.len();
         let (s, ident) = utils::extract_ident(s)?;
 
         Ok((
             s,
             Ref {
                 ident: ident.to_string(),
+                slot: None,
+                rem,
+                span: None,
             },
         ))
     }
 }
 
+// like for `BindingUsage`, the position is left out of comparisons.
+impl PartialEq for Ref {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident && self.slot == other.slot
+    }
+}
+
+impl fmt::Debug for Ref {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ref")
+            .field("ident", &self.ident)
+            .field("slot", &self.slot)
+            .finish()
+    }
+}
+
 impl Eval for Ref {
     fn eval(&self, env: &mut Env) -> Result<Val, Val> {
-        Ok(env.take_ref(&self.ident)?)
+        match self.slot {
+            Some(slot) => Ok(env.ref_local(slot, &self.ident)?),
+            None => Ok(env.take_ref(&self.ident)?),
+        }
     }
 }
 
@@ -48,6 +77,9 @@ mod tests {
         let ref_e = Ref::new("🔖x");
         let expected = Ref {
             ident: "x".to_string(),
+            slot: None,
+            rem: 0,
+            span: None,
         };
 
         assert_eq!(ref_e, Ok(("", expected)))
@@ -58,6 +90,9 @@ mod tests {
         let ref_e = Ref::new("🔖🔥🔥");
         let expected = Ref {
             ident: "🔥🔥".to_string(),
+            slot: None,
+            rem: 0,
+            span: None,
         };
 
         assert_eq!(ref_e, Ok(("", expected)))
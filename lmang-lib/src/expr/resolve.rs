@@ -0,0 +1,531 @@
+use crate::env::{Env, Slot};
+use crate::error::{ParseError, ParseErrorKind};
+use crate::expr::binding_update::{BindingUpdate, Mode};
+use crate::expr::binding_usage::BindingUsage;
+use crate::expr::block::Block;
+use crate::expr::class::{Class, BASE};
+use crate::expr::for_expr::Iterable;
+use crate::expr::func::{Arg, Func};
+use crate::expr::match_expr::Arm;
+use crate::expr::try_expr::Handler;
+use crate::expr::Expr;
+use std::collections::HashSet;
+
+/// Assigns slots to locals so that they needn't be looked up by name, and
+/// rejects names that are neither locals nor globals.
+pub(crate) struct Resolver {
+    globals: HashSet<String>,
+    /// Names bound in each frame of the function being resolved, indexed by
+    /// slot, and whether they are declared yet. Innermost last, empty at the
+    /// top level.
+    frames: Vec<Vec<(String, bool)>>,
+}
+
+impl Resolver {
+    pub(crate) fn new(expr: &mut Expr) -> Self {
+        let mut globals = HashSet::new();
+        expr.visit_mut(&mut |e| {
+            if let Expr::BindingUpdate(bu) = e {
+                if bu.mode == Mode::CreateGlobal {
+                    globals.insert(bu.name.clone());
+                }
+            }
+        });
+
+        Resolver {
+            globals,
+            frames: Vec::new(),
+        }
+    }
+
+    fn declare(&mut self, name: &str) -> Slot {
+        let frame = self.frames.last_mut().expect("no frame");
+        let index = match frame.iter().position(|(n, _)| n == name) {
+            Some(index) => {
+                frame[index].1 = true;
+                index
+            }
+            None => {
+                frame.push((name.to_string(), true));
+                frame.len() - 1
+            }
+        };
+
+        Slot { depth: 0, index }
+    }
+
+    /// Finds the slot of a local, also considering the ones which are only
+    /// going to be declared later if `reserved` is set.
+    fn local(&self, name: &str, reserved: bool) -> Option<Slot> {
+        self.frames
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, frame)| {
+                let index = frame
+                    .iter()
+                    .position(|(n, declared)| n == name && (*declared || reserved))?;
+                Some(Slot { depth, index })
+            })
+    }
+
+    /// Slot of `name`, or `None` if it's a global.
+    fn lookup(&self, name: &str, env: &Env) -> Result<Option<Slot>, ParseErrorKind> {
+        match self.local(name, false) {
+            Some(slot) => Ok(Some(slot)),
+            None if self.globals.contains(name) || env.is_global(name) => Ok(None),
+            None => Err(ParseErrorKind::UnresolvedName(name.to_string())),
+        }
+    }
+
+    /// Slot of the name `usage` refers to, failing where it is used.
+    fn usage(&self, usage: &BindingUsage, env: &Env) -> Result<Option<Slot>, ParseError> {
+        self.lookup(&usage.name, env)
+            .map_err(|kind| kind.at_expr(usage.rem, usage.span))
+    }
+
+    pub(crate) fn expr(&mut self, expr: &mut Expr, env: &Env) -> Result<(), ParseError> {
+        match expr {
+            Expr::Operation { lhs, rhs, .. } => {
+                self.expr(lhs, env)?;
+                self.expr(rhs, env)?;
+            }
+            Expr::UnaryOperation { operand, .. } => self.expr(operand, env)?,
+            Expr::BindingUpdate(bu) => self.binding_update(bu, env)?,
+            Expr::MemberUpdate(mu) => {
+                self.expr(&mut mu.val, env)?;
+                self.expr(&mut mu.object, env)?;
+            }
+            Expr::BindingUsage(usage) => usage.slot = self.usage(usage, env)?,
+            Expr::Block(block) => self.block(block, env)?,
+            Expr::Class(class) => self.class(class, env)?,
+            Expr::If(if_e) => {
+                self.expr(&mut if_e.cond, env)?;
+                self.block(&mut if_e.body, env)?;
+                for (cond, body) in &mut if_e.elifs {
+                    self.expr(cond, env)?;
+                    self.block(body, env)?;
+                }
+                if let Some(body) = &mut if_e.body_else {
+                    self.block(body, env)?;
+                }
+            }
+            Expr::Index(index) => self.expr(&mut index.root, env)?,
+            Expr::Break(break_e) => self.block(&mut break_e.body, env)?,
+            Expr::Return(return_e) => self.block(&mut return_e.body, env)?,
+            Expr::Yield(yield_e) => self.block(&mut yield_e.body, env)?,
+            Expr::Throw(throw_e) => self.block(&mut throw_e.body, env)?,
+            Expr::Import(import_e) => self.expr(&mut import_e.path, env)?,
+            Expr::Loop(loop_e) => self.block(&mut loop_e.body, env)?,
+            Expr::For(for_e) => {
+                match &mut for_e.iterable {
+                    Iterable::Items(expr) => self.expr(expr, env)?,
+                    Iterable::Range(start, end) => {
+                        self.expr(start, env)?;
+                        self.expr(end, env)?;
+                    }
+                }
+                // the variable has a frame of its own around the body.
+                self.frames.push(Vec::new());
+                for_e.slot = Some(self.declare(&for_e.var));
+                let result = self.block(&mut for_e.body, env);
+                self.frames.pop();
+                result?;
+            }
+            Expr::While(while_e) => {
+                self.expr(&mut while_e.cond, env)?;
+                self.block(&mut while_e.body, env)?;
+            }
+            Expr::Map(map_e) => {
+                for (key, val) in &mut map_e.entries {
+                    self.expr(key, env)?;
+                    self.expr(val, env)?;
+                }
+            }
+            Expr::Func(func) => self.func(func, env)?,
+            Expr::Call(call) => {
+                for arg in &mut call.args {
+                    self.expr(arg, env)?;
+                }
+                self.expr(&mut call.func, env)?;
+            }
+            Expr::Literal(_) => {}
+            Expr::Ref(ref_e) => {
+                ref_e.slot = self
+                    .lookup(&ref_e.ident, env)
+                    .map_err(|kind| kind.at_expr(ref_e.rem, ref_e.span))?
+            }
+            Expr::Try(try_e) => {
+                self.block(&mut try_e.try_block, env)?;
+                for (_, handler) in &mut try_e.except_blocks {
+                    self.handler(handler, env)?;
+                }
+                if let Some(handler) = &mut try_e.except_any_block {
+                    self.handler(handler, env)?;
+                }
+                if let Some(body) = &mut try_e.finally_block {
+                    self.block(body, env)?;
+                }
+            }
+            Expr::Match(match_e) => {
+                self.expr(&mut match_e.val, env)?;
+                for arm in &mut match_e.arms {
+                    self.arm(arm, env)?;
+                }
+            }
+            Expr::Named(named) => self.expr(&mut named.expr, env)?,
+        }
+
+        Ok(())
+    }
+
+    fn binding_update(&mut self, bu: &mut BindingUpdate, env: &Env) -> Result<(), ParseError> {
+        match bu.mode {
+            // bindings outside of any block are globals.
+            Mode::CreateLocal if self.frames.is_empty() => {
+                self.globals.insert(bu.name.clone());
+                self.expr(&mut bu.val, env)?;
+            }
+            // a local function is bound before it's created so that it can
+            // capture itself.
+            Mode::CreateLocal if matches!(bu.val, Expr::Func(_)) => {
+                bu.slot = Some(self.declare(&bu.name));
+                self.expr(&mut bu.val, env)?;
+            }
+            Mode::CreateLocal => {
+                self.expr(&mut bu.val, env)?;
+                bu.slot = Some(self.declare(&bu.name));
+            }
+            Mode::CreateGlobal => self.expr(&mut bu.val, env)?,
+            Mode::Set => {
+                self.expr(&mut bu.val, env)?;
+                bu.slot = self
+                    .lookup(&bu.name, env)
+                    .map_err(|kind| kind.at_expr(bu.rem, bu.span))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, block: &mut Block, env: &Env) -> Result<(), ParseError> {
+        // empty blocks don't get a frame.
+        if block.exprs.is_empty() {
+            return Ok(());
+        }
+
+        self.frames.push(Vec::new());
+        for expr in &mut block.exprs {
+            self.expr(expr, env)?;
+        }
+        self.frames.pop();
+
+        Ok(())
+    }
+
+    fn class(&mut self, class: &mut Class, env: &Env) -> Result<(), ParseError> {
+        if let Some(base) = &mut class.base {
+            self.expr(base, env)?;
+        } else if class.body.exprs.is_empty() {
+            return Ok(());
+        }
+
+        // methods see every member, including those declared after them, and
+        // the base comes before all of them.
+        let base = class.base.as_ref().map(|_| (BASE.to_string(), true));
+        let members = class.body.exprs.iter().filter_map(|expr| match expr {
+            Expr::BindingUpdate(bu) if bu.mode == Mode::CreateLocal => {
+                Some((bu.name.clone(), false))
+            }
+            _ => None,
+        });
+        self.frames.push(base.into_iter().chain(members).collect());
+        if class.base.is_some() {
+            class.slot = Some(Slot { depth: 0, index: 0 });
+        }
+
+        let result = class
+            .body
+            .exprs
+            .iter_mut()
+            .try_for_each(|expr| self.expr(expr, env));
+        self.frames.pop();
+
+        result
+    }
+
+    /// The error an except clause binds has a frame of its own around the
+    /// body.
+    fn handler(&mut self, handler: &mut Handler, env: &Env) -> Result<(), ParseError> {
+        let name = match &handler.binding {
+            Some(name) => name,
+            None => return self.block(&mut handler.body, env),
+        };
+
+        self.frames.push(vec![(name.clone(), true)]);
+        handler.slot = Some(Slot { depth: 0, index: 0 });
+        let result = self.block(&mut handler.body, env);
+        self.frames.pop();
+
+        result
+    }
+
+    /// The bindings of a pattern have a frame of their own around the guard
+    /// and the body.
+    fn arm(&mut self, arm: &mut Arm, env: &Env) -> Result<(), ParseError> {
+        self.frames.push(Vec::new());
+        let names = arm.pattern.bindings();
+        arm.slots = Some(names.into_iter().map(|name| self.declare(name)).collect());
+
+        let result = arm
+            .guard
+            .iter_mut()
+            .try_for_each(|guard| self.expr(guard, env))
+            .and_then(|_| self.block(&mut arm.body, env));
+        self.frames.pop();
+
+        result
+    }
+
+    fn func(&mut self, func: &mut Func, env: &Env) -> Result<(), ParseError> {
+        // defaults are evaluated where the function is created.
+        for arg in &mut func.args {
+            if let Arg::Default(_, default) = arg {
+                self.expr(default, env)?;
+            }
+        }
+
+        // only locals of the defining function are captured, other names
+        // refer to globals.
+        let (captures, slots): (Vec<_>, Vec<_>) = func
+            .captures
+            .drain(..)
+            .filter_map(|name| {
+                let slot = self.local(&name, true)?;
+                Some((name, slot))
+            })
+            .unzip();
+
+        let names = func
+            .args
+            .iter()
+            .map(|arg| arg.name())
+            .chain(captures.iter().map(String::as_str));
+        let frame = names.map(|name| (name.to_string(), true)).collect();
+        func.captures = captures;
+        func.resolved = Some(slots);
+
+        // the code 🪞 runs looks up the locals of the body by name, and can
+        // bind names of its own.
+        if func.evals {
+            return Ok(());
+        }
+
+        // the body is evaluated in the same frame as the arguments.
+        let outer = std::mem::replace(&mut self.frames, vec![frame]);
+        let result = func
+            .body
+            .exprs
+            .iter_mut()
+            .try_for_each(|expr| self.expr(expr, env));
+        self.frames = outer;
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtins::Builtins;
+    use crate::expr::binding_usage::BindingUsage;
+    use crate::system;
+    use crate::test_utils::eval_resolved;
+    use crate::val::Val;
+    use crate::vm::Program;
+
+    fn resolved(s: &str) -> Result<Expr, ParseError> {
+        let (_, mut expr) = Expr::parse(s).unwrap();
+        expr.resolve(&Env::test())?;
+
+        Ok(expr)
+    }
+
+    #[test]
+    fn locals_get_slots() {
+        let expr = resolved("📦 👶 a = 1 💪 👶 b = 2 💪 📦 a 🧑‍🦲 🧑‍🦲").unwrap();
+        let exprs = match expr {
+            Expr::Block(block) => block.exprs,
+            _ => unreachable!(),
+        };
+
+        let usage = match &exprs[2] {
+            Expr::Block(inner) => inner.exprs[0].clone(),
+            _ => unreachable!(),
+        };
+        let expected = Expr::BindingUsage(BindingUsage {
+            name: "a".to_string(),
+            slot: Some(Slot { depth: 1, index: 0 }),
+            rem: 0,
+            span: None,
+        });
+
+        assert_eq!(usage, expected);
+    }
+
+    #[test]
+    fn globals_stay_named() {
+        let expr = resolved("👶 a = 1").unwrap();
+
+        match expr {
+            Expr::BindingUpdate(bu) => assert_eq!(bu.slot, None),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn unresolved_name() {
+        let code = "📦
+    👶 a = 1 💪
+    a + b
+🧑‍🦲";
+        let err = resolved(code).unwrap_err();
+        let span = err.span.unwrap();
+
+        assert_eq!(err.kind, ParseErrorKind::UnresolvedName("b".to_string()));
+        assert_eq!((span.line, span.column), (3, 9));
+        assert_eq!(
+            err.render(code),
+            "Unresolved name b at 3:9\n3 |     a + b\n  |         ^"
+        );
+
+        let err = resolved("📦 👶 a = 1 💪 ♻️ b = a 🧑‍🦲").unwrap_err();
+        let span = err.span.unwrap();
+        assert_eq!(err.kind, ParseErrorKind::UnresolvedName("b".to_string()));
+        assert_eq!((span.line, span.column), (1, 15));
+
+        let err = resolved("📦 👶 a = 1 💪\n📞 f 🔖c 🧑‍🦲").unwrap_err();
+        let span = err.span.unwrap();
+        assert_eq!(err.kind, ParseErrorKind::UnresolvedName("c".to_string()));
+        assert_eq!((span.line, span.column), (2, 6));
+    }
+
+    #[test]
+    fn shadowing() {
+        let result = eval_resolved("📦 👶 a = 1 💪 📦 👶 a = a + 1 💪 a 🧑‍🦲 🧑‍🦲");
+        assert_eq!(result, Ok(Val::Number(2)));
+    }
+
+    #[test]
+    fn closure_captures() {
+        let result = eval_resolved(
+            "📦
+                👶 n = 1 💪
+                👶 add = 🧰 x ➡️ x + n 🧑‍🦲 💪
+                ♻️ n = 10 💪
+                📞 add 5
+            🧑‍🦲",
+        );
+
+        assert_eq!(result, Ok(Val::Number(15)));
+    }
+
+    #[test]
+    fn recursion() {
+        let result = eval_resolved(
+            "📦
+                👶 f = 🧰 n ➡️
+                    ❓ n < 1
+                        0
+                    🧑‍🦲 😡
+                        n + 📞 f 📦 n - 1 🧑‍🦲
+                    🧑‍🦲
+                🧑‍🦲 💪
+                📞 f 4
+            🧑‍🦲",
+        );
+
+        assert_eq!(result, Ok(Val::Number(10)));
+    }
+
+    #[test]
+    fn class_member_shadows_outer() {
+        let result = eval_resolved(
+            "📦
+                👶 n = 🧰 n ➡️ 🧑‍🏫 👶 n = n 💪 👶 get = 🧰 ➡️ n 🧑‍🦲 🧑‍🦲 🧑‍🦲 💪
+                👶 obj = 📞 n 7 💪
+                0 + 📞 obj🪆get
+            🧑‍🦲",
+        );
+
+        assert_eq!(result, Ok(Val::Number(7)));
+    }
+
+    #[test]
+    fn class_method_sees_later_member() {
+        let result = eval_resolved(
+            "📦
+                👶 obj = 🧑‍🏫 👶 get = 🧰 ➡️ x 🧑‍🦲 💪 👶 x = 3 🧑‍🦲 💪
+                0 + 📞 obj🪆get
+            🧑‍🦲",
+        );
+
+        assert_eq!(result, Ok(Val::Number(3)));
+    }
+
+    #[test]
+    fn eval_sees_caller_locals() {
+        let (_, mut expr) = Expr::parse(
+            "📦
+                👶 a = 41 💪
+                👶 f = 🧰 x ➡️
+                    👶 y = x 💪
+                    📞 🪞 🧵♻️ y = y * 2🧵 💪
+                    📞 🪞 🧵👶 z = 1🧵 💪
+                    y + z
+                🧑‍🦲 💪
+                📞 🪞 🧵♻️ a = a + 1🧵 💪
+                📞 🪞 🧵👶 b = a🧵 💪
+                b + 📞 f 100
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        let env = || {
+            let mut env = Env::test();
+            let (test_sys, _) = system::Test::new(&[], &[]);
+            env.eval(&Builtins::new(test_sys)).unwrap();
+            env
+        };
+        expr.resolve(&env()).unwrap();
+
+        assert_eq!(env().eval(&expr), Ok(Val::Number(243)));
+        let vm = env().eval(&Program::compile(&expr));
+        assert_eq!(vm, Ok(Val::Number(243)));
+    }
+
+    #[test]
+    fn eval_in_function_keeps_names() {
+        // names the code of 🪞 binds can't be checked ahead of time.
+        let expr = resolved(
+            "📦
+                👶 n = 1 💪
+                👶 f = 🧰 ➡️ 📞 🪞 🧵👶 m = 2🧵 💪 n + m 🧑‍🦲 💪
+                📞 f
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        let mut slots = Vec::new();
+        let mut funcs = Vec::new();
+        let mut expr = expr;
+        expr.visit_mut(&mut |e| match e {
+            Expr::BindingUsage(usage) if usage.name != "🪞" => slots.push(usage.slot),
+            Expr::Func(func) => funcs.push(func.resolved.clone()),
+            _ => {}
+        });
+
+        assert_eq!(slots, vec![None, None, Some(Slot { depth: 0, index: 1 })]);
+        assert_eq!(funcs, vec![Some(vec![Slot { depth: 0, index: 0 }])]);
+    }
+}
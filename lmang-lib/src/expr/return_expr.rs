@@ -0,0 +1,101 @@
+use crate::env::{Env, Eval};
+use crate::error::ParseError;
+use crate::expr::block::{Block, FormatImplicit};
+use crate::utils::{self, kwords};
+use crate::val::Val;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Return {
+    pub(crate) body: Block,
+}
+
+impl Return {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::RETURN, s)?;
+
+        let (s, body) = Block::implicit(s)?;
+
+        Ok((s, Return { body }))
+    }
+}
+
+impl Eval for Return {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        Ok(Val::Return(Box::new(env.eval(&self.body)?)))
+    }
+}
+
+impl crate::expr::Format for Return {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        writeln!(w, "{}", kwords::RETURN)?;
+        FormatImplicit(&self.body).format(w, depth)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RuntimeError;
+    use crate::expr::{Expr, Literal};
+    use crate::test_utils::eval;
+
+    #[test]
+    fn parse_return() {
+        let expected = Return {
+            body: Block {
+                exprs: vec![Expr::Literal(Literal(Val::Number(1)))],
+            },
+        };
+
+        assert_eq!(Return::new("🔙 1 🧑‍🦲"), Ok(("", expected)));
+    }
+
+    #[test]
+    fn return_from_loop() {
+        let input = "📦
+                👶 find = 🧰 n ➡️
+                    👶 i = 0 💪
+                    🔁
+                        ❓ n == i * i 🔙 i 🧑‍🦲 🧑‍🦲 💪
+                        ❓ n < i * i 🔙 - 1 🧑‍🦲 🧑‍🦲 💪
+                        ♻️ i = i + 1
+                    🧑‍🦲 💪
+                    🧵unreachable🧵
+                🧑‍🦲 💪
+                📞 find 16
+            🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(4)));
+    }
+
+    #[test]
+    fn return_through_blocks() {
+        let input = "📦
+                👶 f = 🧰 a ➡️
+                    👶 b = 📦 ❓ a 🔙 1 🧑‍🦲 🧑‍🦲 💪 2 🧑‍🦲 💪
+                    b + 10
+                🧑‍🦲 💪
+                👶 x = 📞 f 🙆‍♀️ 💪
+                👶 y = 📞 f 🙅‍♀️ 💪
+                x + y
+            🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(13)));
+    }
+
+    #[test]
+    fn stray_break() {
+        let input = "📞 🧰 ➡️ 💔 1 🧑‍🦲 🧑‍🦲";
+        assert_eq!(eval(input), Err(RuntimeError::BreakOutsideLoop.into()));
+    }
+
+    #[test]
+    fn format() {
+        let (_, return_e) = Return::new("🔙📦a/2🧑‍🦲").unwrap();
+        assert_eq!(
+            format!("{}", crate::expr::Display(&return_e)),
+            "🔙\n    a / 2\n🧑‍🦲"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::env::{Env, Eval};
+use crate::error::ParseError;
+use crate::expr::block::{Block, FormatImplicit};
+use crate::utils::{self, kwords};
+use crate::val::Val;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Throw {
+    pub(crate) body: Block,
+}
+
+impl Throw {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::THROW, s)?;
+
+        let (s, body) = Block::implicit(s)?;
+
+        Ok((s, Throw { body }))
+    }
+}
+
+impl Eval for Throw {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        Err(env.eval(&self.body)?)
+    }
+}
+
+impl crate::expr::Format for Throw {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        writeln!(w, "{}", kwords::THROW)?;
+        FormatImplicit(&self.body).format(w, depth)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{Expr, Literal};
+
+    #[test]
+    fn parse_throw() {
+        let expected = Throw {
+            body: Block {
+                exprs: vec![Expr::Literal(Literal(Val::Number(1)))],
+            },
+        };
+
+        assert_eq!(Throw::new("🧨 1 🧑‍🦲"), Ok(("", expected)));
+    }
+
+    #[test]
+    fn eval_throw() {
+        let (_, throw_e) = Expr::new("🧨 🧵oops🧵 🧑‍🦲").unwrap();
+        let result = Env::test().eval(&throw_e);
+
+        assert_eq!(result, Err(Val::from("oops")));
+    }
+
+    #[test]
+    fn catch_user_error() {
+        let (_, try_e) = Expr::new(
+            "📦
+                👶 NotFound = 🧰 what ➡️ 🧑‍🏫 👶 type = 🧵NotFound🧵 💪 👶 what = what 🧑‍🦲 🧑‍🦲 💪
+                👶 find = 🧰 ➡️ 🧨 📞 NotFound 🧵key🧵 🧑‍🦲 🧑‍🦲 💪
+                👩‍🚒 📞 find 🧑‍🦲 🤡 NoBinding 1 🧑‍🦲 🤡 NotFound 2 🧑‍🦲 🤡 3 🧑‍🦲
+            🧑‍🦲",
+        )
+        .unwrap();
+
+        assert_eq!(Env::test().eval(&try_e), Ok(Val::Number(2)));
+    }
+
+    #[test]
+    fn catch_any_non_object() {
+        let (_, try_e) = Expr::new("👩‍🚒 🧨 5 🧑‍🦲 🧑‍🦲 🤡 NoBinding 1 🧑‍🦲 🤡 2 🧑‍🦲").unwrap();
+        assert_eq!(Env::test().eval(&try_e), Ok(Val::Number(2)));
+
+        let (_, try_e) = Expr::new("👩‍🚒 📞 🧰 ➡️ 🧨 5 🧑‍🦲 🧑‍🦲 🧑‍🦲 🤡 NoBinding 1 🧑‍🦲").unwrap();
+        let result = Env::test().eval(&try_e);
+        assert_eq!(result, Err(Val::Number(5)));
+    }
+
+    #[test]
+    fn format() {
+        let (_, throw_e) = Expr::new("🧨 📞 f 1 🧑‍🦲").unwrap();
+        let expected = "🧨\n    📞 f 1\n🧑‍🦲";
+
+        assert_eq!(format!("{}", crate::expr::Display(&throw_e)), expected);
+    }
+}
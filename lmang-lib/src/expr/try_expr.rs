@@ -1,14 +1,71 @@
-use crate::env::{Env, Eval};
-use crate::error::ParseError;
+use crate::env::{Env, Eval, Slot};
+use crate::error::{ParseError, Traced};
 use crate::expr::block::{Block, FormatImplicit};
+use crate::expr::Format;
 use crate::utils::{self, kwords};
 use crate::val::Val;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Try {
-    try_block: Block,
-    except_blocks: Vec<(String, Block)>,
-    except_any_block: Option<Block>,
+    pub(crate) try_block: Block,
+    pub(crate) except_blocks: Vec<(String, Handler)>,
+    pub(crate) except_any_block: Option<Handler>,
+    /// Runs last whatever the outcome, its own value is discarded.
+    pub(crate) finally_block: Option<Block>,
+}
+
+/// Body of an except clause, which may bind the caught error.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Handler {
+    pub(crate) binding: Option<String>,
+    pub(crate) slot: Option<Slot>,
+    pub(crate) body: Block,
+}
+
+impl Handler {
+    fn new(binding: Option<&str>, body: Block) -> Self {
+        Handler {
+            binding: binding.map(str::to_string),
+            slot: None,
+            body,
+        }
+    }
+
+    fn eval(&self, env: &mut Env, err: Val) -> Result<Val, Val> {
+        let name = match &self.binding {
+            Some(name) => name,
+            None => return env.eval(&self.body),
+        };
+
+        // the error gets a frame of its own around the body.
+        env.push();
+        match self.slot {
+            Some(slot) => env.store_local(slot.index, err),
+            None => env.store_binding(name.clone(), err),
+        }
+        let result = env.eval(&self.body);
+        env.pop();
+
+        result
+    }
+
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        match &self.binding {
+            Some(name) => writeln!(w, " {} {}", name, kwords::FUNC_SEP)?,
+            None => writeln!(w)?,
+        }
+        FormatImplicit(&self.body).format(w, depth)
+    }
+}
+
+/// Parses the `name ➡️` an except clause binds the error with.
+fn binding(s: &str) -> Option<(&str, &str)> {
+    let (s, _) = utils::extract_whitespace(s);
+    let (s, name) = utils::extract_ident(s).ok()?;
+    let (s, _) = utils::extract_whitespace(s);
+    let s = utils::tag(kwords::FUNC_SEP, s).ok()?;
+
+    Some((s, name))
 }
 
 impl Try {
@@ -27,71 +84,121 @@ impl Try {
             let new_s = if let Ok(new_s) = utils::tag(kwords::EXCEPT, new_s) {
                 new_s
             } else {
-                s = new_s;
                 break;
             };
 
+            // `🤡 name ➡️` binds any error.
+            if let Some((new_s, name)) = binding(new_s) {
+                let (new_s, except_block) = Block::implicit(new_s)?;
+                except_any_block = Some(Handler::new(Some(name), except_block));
+                s = new_s;
+                break;
+            }
+
             let (new_s, _) = utils::extract_whitespace(new_s);
             let (new_s, error_type) = match utils::extract_ident(new_s) {
                 Ok((new_s, error_type)) => (new_s, error_type),
                 Err(_) => {
                     let (new_s, except_block) = Block::implicit(new_s)?;
-                    except_any_block = Some(except_block);
+                    except_any_block = Some(Handler::new(None, except_block));
                     s = new_s;
                     break;
                 }
             };
 
+            let (new_s, name) = match binding(new_s) {
+                Some((new_s, name)) => (new_s, Some(name)),
+                None => (new_s, None),
+            };
             let (new_s, except_block) = Block::implicit(new_s)?;
 
             s = new_s;
 
-            except_blocks.push((error_type.into(), except_block));
+            except_blocks.push((error_type.into(), Handler::new(name, except_block)));
         }
 
+        let (new_s, _) = utils::extract_whitespace(s);
+        let finally_block = match utils::tag(kwords::FINALLY, new_s) {
+            Ok(new_s) => {
+                let (new_s, finally_block) = Block::implicit(new_s)?;
+                s = new_s;
+                Some(finally_block)
+            }
+            Err(_) => None,
+        };
+
         let try_e = Try {
             try_block: body,
             except_blocks,
             except_any_block,
+            finally_block,
         };
 
         Ok((s, try_e))
     }
+
+    fn eval_except(&self, env: &mut Env) -> Result<Val, Val> {
+        let trace_len = env.trace_len();
+        let err = match env.eval(&self.try_block) {
+            Ok(val) => return Ok(val),
+            Err(err) => err,
+        };
+
+        let typed = error_type(&err).and_then(|ty| {
+            self.except_blocks
+                .iter()
+                .find(|(name, _)| *name == ty)
+                .map(|(_, handler)| handler)
+        });
+
+        match typed.or(self.except_any_block.as_ref()) {
+            Some(handler) => {
+                let trace = env.split_trace(trace_len);
+                handler.eval(env, Traced::caught(err, trace))
+            }
+            None => Err(err),
+        }
+    }
+}
+
+/// The `type` member of an error, which typed handlers match against. Errors
+/// that aren't objects with a `type` are only caught by the catch-all handler.
+pub(crate) fn error_type(err: &Val) -> Option<String> {
+    let ty = err.as_object().ok()?.0.member("type").ok()?;
+    ty.apply_to_root(|ty| ty.to_string()).ok()
 }
 
 impl Eval for Try {
     fn eval(&self, env: &mut Env) -> Result<Val, Val> {
-        match env.eval(&self.try_block) {
-            Ok(val) => Ok(val),
-            Err(err) => {
-                for excepts in self.except_blocks.iter() {
-                    if excepts.0 == err.as_object()?.0.member("type")?.to_string() {
-                        return env.eval(&excepts.1);
-                    }
-                }
-                if let Some(catch_all) = &self.except_any_block {
-                    return env.eval(catch_all);
-                }
-
-                Err(err)
-            }
+        // a break passing through is a value like any other, so it only
+        // needs errors to be held back.
+        let result = self.eval_except(env);
+        if let Some(finally_block) = &self.finally_block {
+            env.eval(finally_block)?;
         }
+
+        result
     }
 }
 
-impl crate::expr::Format for Try {
+impl Format for Try {
     fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
         writeln!(w, "{}", kwords::TRY)?;
         FormatImplicit(&self.try_block).format(w, depth)?;
 
-        for (exception, body) in &self.except_blocks {
-            writeln!(w, " {} {}", kwords::EXCEPT, exception)?;
-            FormatImplicit(body).format(w, depth)?;
+        for (exception, handler) in &self.except_blocks {
+            write!(w, " {} {}", kwords::EXCEPT, exception)?;
+            handler.format(w, depth)?;
+        }
+
+        if let Some(handler) = &self.except_any_block {
+            write!(w, " {}", kwords::EXCEPT)?;
+            handler.format(w, depth)?;
         }
 
-        if let Some(body_else) = &self.except_any_block {
-            writeln!(w, " {}", kwords::EXCEPT)?;
-            FormatImplicit(body_else).format(w, depth)?;
+        if let Some(body) = &self.finally_block {
+            writeln!(w, " {}", kwords::FINALLY)?;
+            FormatImplicit(body).format(w, depth)?;
         }
 
         Ok(())
@@ -101,6 +208,8 @@ impl crate::expr::Format for Try {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::RuntimeError;
+    use crate::expr::{BindingUsage, Expr};
 
     #[test]
     fn parse_try_nop() {
@@ -109,6 +218,7 @@ mod tests {
             try_block: Block { exprs: Vec::new() },
             except_blocks: Vec::default(),
             except_any_block: None,
+            finally_block: None,
         };
 
         assert_eq!(parse, Ok(("", expected)));
@@ -117,16 +227,15 @@ mod tests {
     #[test]
     fn parse_except_1() {
         let parse = Try::new("👩‍🚒 🧑‍🦲 🤡 OutOfBounds 🧑‍🦲");
-        let blocks = {
-            let mut tmp = Vec::default();
-            tmp.push(("OutOfBounds".into(), Block { exprs: Vec::new() }));
-
-            tmp
-        };
+        let blocks = vec![(
+            "OutOfBounds".into(),
+            Handler::new(None, Block { exprs: Vec::new() }),
+        )];
         let expected = Try {
             try_block: Block { exprs: Vec::new() },
             except_blocks: blocks,
             except_any_block: None,
+            finally_block: None,
         };
 
         assert_eq!(parse, Ok(("", expected)));
@@ -135,17 +244,21 @@ mod tests {
     #[test]
     fn parse_except_2() {
         let parse = Try::new("👩‍🚒 🧑‍🦲 🤡 Timeout 🧑‍🦲 🤡 CastError 🧑‍🦲");
-        let blocks = {
-            let mut tmp = Vec::default();
-            tmp.push(("Timeout".into(), Block { exprs: Vec::new() }));
-            tmp.push(("CastError".into(), Block { exprs: Vec::new() }));
-
-            tmp
-        };
+        let blocks = vec![
+            (
+                "Timeout".into(),
+                Handler::new(None, Block { exprs: Vec::new() }),
+            ),
+            (
+                "CastError".into(),
+                Handler::new(None, Block { exprs: Vec::new() }),
+            ),
+        ];
         let expected = Try {
             try_block: Block { exprs: Vec::new() },
             except_blocks: blocks,
             except_any_block: None,
+            finally_block: None,
         };
 
         assert_eq!(parse, Ok(("", expected)));
@@ -154,17 +267,21 @@ mod tests {
     #[test]
     fn parse_except_2_all() {
         let parse = Try::new("👩‍🚒 🧑‍🦲 🤡 Timeout 🧑‍🦲 🤡 CastError 🧑‍🦲 🤡 🧑‍🦲");
-        let blocks = {
-            let mut tmp = Vec::default();
-            tmp.push(("Timeout".into(), Block { exprs: Vec::new() }));
-            tmp.push(("CastError".into(), Block { exprs: Vec::new() }));
-
-            tmp
-        };
+        let blocks = vec![
+            (
+                "Timeout".into(),
+                Handler::new(None, Block { exprs: Vec::new() }),
+            ),
+            (
+                "CastError".into(),
+                Handler::new(None, Block { exprs: Vec::new() }),
+            ),
+        ];
         let expected = Try {
             try_block: Block { exprs: Vec::new() },
             except_blocks: blocks,
-            except_any_block: Some(Block { exprs: Vec::new() }),
+            except_any_block: Some(Handler::new(None, Block { exprs: Vec::new() })),
+            finally_block: None,
         };
 
         assert_eq!(parse, Ok(("", expected)));
@@ -174,14 +291,13 @@ mod tests {
     fn reject_default_in_middle() {
         let parse = Try::new("👩‍🚒 🧑‍🦲 🤡 NoKey 🧑‍🦲 🤡 🧑‍🦲 🤡 CastError 🧑‍🦲");
 
-        assert!(!matches!(parse, Err(_)));
+        assert!(parse.is_ok());
     }
 
     #[test]
     fn test_ok() {
         let mut env = Env::test();
-        let (_, parse) =
-            Try::new("👩‍🚒 10 🧑‍🦲 🤡 WrongArgsN 🧑‍🦲 🤡 IoError 🧑‍🦲 🤡 🧑‍🦲").unwrap();
+        let (_, parse) = Try::new("👩‍🚒 10 🧑‍🦲 🤡 WrongArgsN 🧑‍🦲 🤡 IoError 🧑‍🦲 🤡 🧑‍🦲").unwrap();
 
         assert_eq!(env.eval(&parse), Ok(Val::Number(10)));
     }
@@ -194,6 +310,100 @@ mod tests {
         assert_eq!(env.eval(&parse), Ok(Val::Number(12)));
     }
 
+    #[test]
+    fn test_traced_error() {
+        let mut env = Env::test();
+        let (_, parse) = Try::new("👩‍🚒 📞 🧰 ➡️ x 🧑‍🦲 🧑‍🦲 🤡 NoBinding 12 🧑‍🦲").unwrap();
+
+        assert_eq!(env.eval(&parse), Ok(Val::Number(12)));
+    }
+
+    #[test]
+    fn parse_binding() {
+        let (_, parse) = Try::new("👩‍🚒 🧑‍🦲 🤡 NoKey e ➡️ e 🧑‍🦲 🤡 NoBinding b 🧑‍🦲 🤡 e ➡️ 🧑‍🦲").unwrap();
+        let usage = |name: &str| Block {
+            exprs: vec![Expr::BindingUsage(BindingUsage {
+                name: name.to_string(),
+                slot: None,
+                rem: 0,
+                span: None,
+            })],
+        };
+
+        assert_eq!(
+            parse.except_blocks,
+            vec![
+                ("NoKey".into(), Handler::new(Some("e"), usage("e"))),
+                ("NoBinding".into(), Handler::new(None, usage("b"))),
+            ]
+        );
+        let empty = Block { exprs: Vec::new() };
+        assert_eq!(parse.except_any_block, Some(Handler::new(Some("e"), empty)));
+    }
+
+    #[test]
+    fn test_binding() {
+        let cases = [
+            "👩‍🚒 x 🧑‍🦲 🤡 NoBinding e ➡️ e🪆binding 🧑‍🦲",
+            "👩‍🚒 x 🧑‍🦲 🤡 CastError 1 🧑‍🦲 🤡 e ➡️ e🪆binding 🧑‍🦲",
+            "👩‍🚒 🧨 🧵x🧵 🧑‍🦲 🧑‍🦲 🤡 e ➡️ e 🧑‍🦲",
+        ];
+
+        for input in cases {
+            let (_, parse) = Expr::new(input).unwrap();
+            assert_eq!(Env::test().eval(&parse), Ok(Val::from("x")), "{}", input);
+        }
+
+        let (_, mut parse) =
+            Expr::new("📦 👶 a = 1 💪 👩‍🚒 🧨 a 🧑‍🦲 🧑‍🦲 🤡 e ➡️ 👶 b = 2 💪 e + a + b 🧑‍🦲 🧑‍🦲").unwrap();
+        parse.resolve(&Env::test()).unwrap();
+        assert_eq!(Env::test().eval(&parse), Ok(Val::Number(4)));
+    }
+
+    #[test]
+    fn binding_is_scoped() {
+        let (_, parse) = Expr::new("📦 👩‍🚒 x 🧑‍🦲 🤡 e ➡️ 1 🧑‍🦲 💪 e 🧑‍🦲").unwrap();
+        let result = Env::test().eval(&parse);
+
+        assert_eq!(result, Err(RuntimeError::NoBinding("e".into()).into()));
+    }
+
+    #[test]
+    fn test_finally() {
+        let cases = [
+            // ok, caught and uncaught errors.
+            ("👩‍🚒 1 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲", Ok(Val::Number(1))),
+            ("👩‍🚒 x 🧑‍🦲 🤡 2 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲", Ok(Val::Number(2))),
+            (
+                "👩‍🚒 x 🧑‍🦲 🤡 CastError 2 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲",
+                Err(RuntimeError::NoBinding("x".into()).into()),
+            ),
+            // raised by a handler.
+            (
+                "👩‍🚒 x 🧑‍🦲 🤡 🧨 3 🧑‍🦲 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲",
+                Err(Val::Number(3)),
+            ),
+            // a break passing through.
+            ("🔁 👩‍🚒 💔 4 🧑‍🦲 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲 🧑‍🦲", Ok(Val::Number(4))),
+        ];
+
+        for (input, expected) in cases {
+            let mut env = Env::test();
+            env.store_binding("n".to_string(), Val::Number(0));
+            let (_, parse) = Expr::new(input).unwrap();
+
+            assert_eq!(env.eval(&parse), expected, "{}", input);
+            assert_eq!(env.get_binding("n"), Ok(Val::Number(1)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn finally_error_wins() {
+        let (_, parse) = Try::new("👩‍🚒 🧨 1 🧑‍🦲 🧑‍🦲 🧹 🧨 2 🧑‍🦲 🧑‍🦲").unwrap();
+
+        assert_eq!(Env::test().eval(&parse), Err(Val::Number(2)));
+    }
+
     #[test]
     fn format() {
         let (_, parse) = Try::new("👩‍🚒 x 🧑‍🦲 🤡 NoBinding 12 🧑‍🦲").unwrap();
@@ -201,4 +411,13 @@ mod tests {
 
         assert_eq!(format!("{}", crate::expr::Display(&parse)), expected);
     }
+
+    #[test]
+    fn format_binding_finally() {
+        let (_, parse) = Try::new("👩‍🚒 x 🧑‍🦲 🤡 NoBinding e ➡️ 1 🧑‍🦲 🤡 e ➡️ 2 🧑‍🦲 🧹 3 🧑‍🦲").unwrap();
+        let expected =
+            "👩‍🚒\n    x\n🧑‍🦲 🤡 NoBinding e ➡️\n    1\n🧑‍🦲 🤡 e ➡️\n    2\n🧑‍🦲 🧹\n    3\n🧑‍🦲";
+
+        assert_eq!(format!("{}", crate::expr::Display(&parse)), expected);
+    }
 }
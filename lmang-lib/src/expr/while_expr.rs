@@ -0,0 +1,88 @@
+use crate::env::{Env, Eval};
+use crate::error::ParseError;
+use crate::expr::block::{Block, FormatImplicit};
+use crate::expr::{Expr, Format};
+use crate::utils::{self, kwords};
+use crate::val::Val;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct While {
+    pub(crate) cond: Expr,
+    pub(crate) body: Block,
+}
+
+impl While {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::WHILE, s)?;
+
+        let (s, cond) = Expr::new(s)?;
+        let (s, body) = Block::implicit(s)?;
+
+        Ok((s, While { cond, body }))
+    }
+}
+
+impl Eval for While {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        while *env.eval(&self.cond)?.as_bool()? {
+            match env.eval(&self.body)? {
+                Val::Break(inner_box) => return Ok(*inner_box),
+                ret @ Val::Return(_) => return Ok(ret),
+                _ => {}
+            }
+        }
+
+        Ok(Val::Unit)
+    }
+}
+
+impl Format for While {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        write!(w, "{} ", kwords::WHILE)?;
+        self.cond.format(w, depth)?;
+        writeln!(w)?;
+        FormatImplicit(&self.body).format(w, depth)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RuntimeError;
+    use crate::expr::Display;
+    use crate::test_utils::eval;
+
+    #[test]
+    fn eval_while() {
+        let input = "📦 👶 a = 0 💪 ⏳ a < 5 ♻️ a = a + 2 🧑‍🦲 💪 a 🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(6)));
+
+        assert_eq!(eval("⏳ 🙅‍♀️ 1 🧑‍🦲"), Ok(Val::Unit));
+    }
+
+    #[test]
+    fn eval_break() {
+        let input = "📦 👶 a = 0 💪 ⏳ 🙆‍♀️ ♻️ a = a + 1 💪 ❓ a == 3 💔 a * 2 🧑‍🦲 🧑‍🦲 🧑‍🦲 🧑‍🦲";
+        assert_eq!(eval(input), Ok(Val::Number(6)));
+    }
+
+    #[test]
+    fn eval_cond_not_bool() {
+        let expected = RuntimeError::CastError {
+            from: "🔢".into(),
+            to: "😵‍💫".into(),
+        };
+        assert_eq!(eval("⏳ 1 🧑‍🦲"), Err(expected.into()));
+    }
+
+    #[test]
+    fn format() {
+        let (_, while_e) = Expr::new("⏳ a < 5 ♻️ a = a + 1 🧑‍🦲").unwrap();
+        let expected = "⏳ a < 5\n    ♻️ a = a + 1\n🧑‍🦲";
+
+        assert_eq!(format!("{}", Display(&while_e)), expected);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::env::{Env, Eval};
+use crate::error::{ParseError, RuntimeError};
+use crate::expr::block::{Block, FormatImplicit};
+use crate::utils::{self, kwords};
+use crate::val::Val;
+
+/// Hands a value to whoever resumed the generator the expression is in,
+/// evaluating to `📦🧑‍🦲` once the generator is resumed again.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Yield {
+    pub(crate) body: Block,
+}
+
+impl Yield {
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(kwords::YIELD, s)?;
+
+        let (s, body) = Block::implicit(s)?;
+
+        Ok((s, Yield { body }))
+    }
+}
+
+impl Eval for Yield {
+    fn eval(&self, _env: &mut Env) -> Result<Val, Val> {
+        // generator bodies always run compiled, so any yield evaluated here
+        // is outside of one.
+        Err(RuntimeError::YieldOutsideGenerator.into())
+    }
+}
+
+impl crate::expr::Format for Yield {
+    fn format(&self, w: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        writeln!(w, "{}", kwords::YIELD)?;
+        FormatImplicit(&self.body).format(w, depth)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{Expr, Literal};
+    use crate::test_utils::eval;
+
+    #[test]
+    fn parse_yield() {
+        let expected = Yield {
+            body: Block {
+                exprs: vec![Expr::Literal(Literal(Val::Number(1)))],
+            },
+        };
+
+        assert_eq!(Yield::new("🐣 1 🧑‍🦲"), Ok(("", expected)));
+    }
+
+    #[test]
+    fn yield_outside_generator() {
+        let expected = RuntimeError::YieldOutsideGenerator;
+        assert_eq!(eval("📦 🐣 1 🧑‍🦲 🧑‍🦲"), Err(expected.into()));
+    }
+
+    #[test]
+    fn format() {
+        let (_, yield_e) = Yield::new("🐣📦a/2🧑‍🦲").unwrap();
+        assert_eq!(
+            format!("{}", crate::expr::Display(&yield_e)),
+            "🐣\n    a / 2\n🧑‍🦲"
+        );
+    }
+}
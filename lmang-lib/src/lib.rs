@@ -1,8 +1,12 @@
 mod utils;
 
+#[cfg(test)]
+mod test_utils;
+
 pub mod builtins;
 pub mod env;
 pub mod error;
 pub mod expr;
 pub mod system;
 pub mod val;
+pub mod vm;
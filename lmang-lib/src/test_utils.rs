@@ -0,0 +1,43 @@
+//! Helpers shared by the unit tests.
+
+use crate::env::Env;
+use crate::expr::for_expr::{done, DONE};
+use crate::expr::Expr;
+use crate::val::Val;
+
+/// Parses the whole of `s` and evaluates it in a fresh test environment.
+pub(crate) fn eval(s: &str) -> Result<Val, Val> {
+    eval_in(&mut Env::test(), s)
+}
+
+/// Parses the whole of `s` and evaluates it in `env`, copying the result out
+/// of any reference so it compares by value.
+pub(crate) fn eval_in(env: &mut Env, s: &str) -> Result<Val, Val> {
+    let (rem, expr) = Expr::new(s).unwrap();
+    assert_eq!(rem, "");
+
+    env.eval(&expr)
+        .map(|v| v.apply_to_root(Val::clone).unwrap_or(v))
+}
+
+/// Parses the whole of `s`, resolves its locals and evaluates it in a fresh
+/// test environment, checking it gives the same result as when unresolved.
+pub(crate) fn eval_resolved(s: &str) -> Result<Val, Val> {
+    let (rem, mut expr) = Expr::new(s).unwrap();
+    assert_eq!(rem, "");
+
+    let unresolved = Env::test().eval(&expr);
+    expr.resolve(&Env::test()).unwrap();
+    let resolved = Env::test().eval(&expr);
+    assert_eq!(unresolved, resolved);
+
+    resolved
+}
+
+/// Test environment with the builtin ending iterations.
+pub(crate) fn iter_env() -> Env {
+    let mut env = Env::test();
+    env.store_binding(DONE.to_string(), done());
+
+    env
+}
@@ -1,4 +1,4 @@
-use crate::error::ParseError;
+use crate::error::{ParseError, ParseErrorKind};
 
 pub mod kwords {
     pub const BLOCK_OPEN: &str = "📦";
@@ -10,7 +10,13 @@ pub mod kwords {
     pub const ELSE: &str = "😡";
 
     pub const BREAK: &str = "💔";
+    pub const RETURN: &str = "🔙";
+    pub const YIELD: &str = "🐣";
     pub const LOOP: &str = "🔁";
+    pub const FOR: &str = "🔂";
+    pub const IN: &str = "📥";
+    pub const RANGE: &str = "⏩";
+    pub const WHILE: &str = "⏳";
 
     pub const LET: &str = "👶";
     pub const SET: &str = "♻️";
@@ -32,16 +38,27 @@ pub mod kwords {
     pub const REF: &str = "🔖";
 
     pub const CLASS: &str = "🧑‍🏫";
+    pub const INHERIT: &str = "🧬";
+    pub const MAP: &str = "🗺️";
 
     pub const NAMED: &str = ":";
 
     pub const TRY: &str = "👩‍🚒";
     pub const EXCEPT: &str = "🤡";
+    pub const THROW: &str = "🧨";
+    pub const FINALLY: &str = "🧹";
+
+    pub const IMPORT: &str = "🧳";
+
+    pub const MATCH: &str = "🧩";
+    pub const CASE: &str = "👉";
+    pub const DEQUE: &str = "😵‍💫";
 
     pub const ADD: &str = "+";
     pub const SUB: &str = "-";
     pub const MUL: &str = "*";
     pub const DIV: &str = "/";
+    pub const MOD: &str = "%";
 
     pub const GT: &str = ">";
     pub const GE: &str = ">=";
@@ -51,7 +68,11 @@ pub mod kwords {
     pub const NE: &str = "!=";
     pub const FE: &str = "~=";
 
-    pub const ALL: [&str; 37] = [
+    pub const AND: &str = "🤝";
+    pub const OR: &str = "🔀";
+    pub const NOT: &str = "🚫";
+
+    pub const ALL: [&str; 55] = [
         BLOCK_OPEN,
         BLOCK_CLOSE,
         EXPR_SEP,
@@ -59,7 +80,13 @@ pub mod kwords {
         ELIF,
         ELSE,
         BREAK,
+        RETURN,
+        YIELD,
         LOOP,
+        FOR,
+        IN,
+        RANGE,
+        WHILE,
         LET,
         SET,
         GLOB,
@@ -75,13 +102,22 @@ pub mod kwords {
         INDEX,
         REF,
         CLASS,
+        INHERIT,
+        MAP,
         NAMED,
         TRY,
         EXCEPT,
+        THROW,
+        FINALLY,
+        IMPORT,
+        MATCH,
+        CASE,
+        DEQUE,
         ADD,
         SUB,
         MUL,
         DIV,
+        MOD,
         GT,
         GE,
         EQ,
@@ -89,6 +125,9 @@ pub mod kwords {
         LT,
         NE,
         FE,
+        AND,
+        OR,
+        NOT,
     ];
 }
 
@@ -96,7 +135,7 @@ pub(crate) fn take_while(accept: impl Fn(char) -> bool, s: &str) -> (&str, &str)
     let extracted_end = s
         .char_indices()
         .find_map(|(idx, c)| if accept(c) { None } else { Some(idx) })
-        .unwrap_or_else(|| s.len());
+        .unwrap_or(s.len());
 
     let extracted = &s[..extracted_end];
     let remainder = &s[extracted_end..];
@@ -107,19 +146,19 @@ pub(crate) fn take_while(accept: impl Fn(char) -> bool, s: &str) -> (&str, &str)
 pub(crate) fn take_while1(
     accept: impl Fn(char) -> bool,
     s: &str,
-    error: ParseError,
+    error: ParseErrorKind,
 ) -> Result<(&str, &str), ParseError> {
     let (remainder, extracted) = take_while(accept, s);
 
     if extracted.is_empty() {
-        Err(error)
+        Err(error.at(s))
     } else {
         Ok((remainder, extracted))
     }
 }
 
 pub(crate) fn extract_digits(s: &str) -> Result<(&str, &str), ParseError> {
-    take_while1(|c| c.is_ascii_digit(), s, ParseError::ExpectedDigits)
+    take_while1(|c| c.is_ascii_digit(), s, ParseErrorKind::ExpectedDigits)
 }
 
 fn is_whitespace(c: char) -> bool {
@@ -132,11 +171,11 @@ pub(crate) fn extract_whitespace(s: &str) -> (&str, &str) {
 
 #[allow(unused)]
 pub(crate) fn extract_whitespace1(s: &str) -> Result<(&str, &str), ParseError> {
-    take_while1(is_whitespace, s, ParseError::ExpectedWhitespace)
+    take_while1(is_whitespace, s, ParseErrorKind::ExpectedWhitespace)
 }
 
 pub(crate) fn extract_ident(s: &str) -> Result<(&str, &str), ParseError> {
-    let not_ident_err = Err(ParseError::ExpectedIdent);
+    let not_ident_err = Err(ParseErrorKind::ExpectedIdent.at(s));
     if s.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(true) {
         return not_ident_err;
     }
@@ -176,7 +215,21 @@ pub(crate) fn tag<'a>(starting_text: &'static str, s: &'a str) -> Result<&'a str
     if let Some(stripped) = s.strip_prefix(starting_text) {
         Ok(stripped)
     } else {
-        Err(ParseError::ExpectedTag(starting_text))
+        Err(ParseErrorKind::ExpectedTag(starting_text).at(s))
+    }
+}
+
+/// Blank space as wide as a terminal renders the grapheme `g`, used to align
+/// carets under emoji-heavy source lines.
+pub(crate) fn pad_for(g: &str) -> &'static str {
+    let wide = g
+        .chars()
+        .any(|c| c as u32 >= 0x1F000 || ('\u{2300}'..='\u{2BFF}').contains(&c));
+
+    if wide {
+        "  "
+    } else {
+        " "
     }
 }
 
@@ -196,7 +249,10 @@ mod tests {
 
     #[test]
     fn do_not_extract_digits_when_input_is_invalid() {
-        assert_eq!(extract_digits("abcd"), Err(ParseError::ExpectedDigits));
+        assert_eq!(
+            extract_digits("abcd"),
+            Err(ParseErrorKind::ExpectedDigits.at("abcd"))
+        );
     }
 
     #[test]
@@ -213,7 +269,7 @@ mod tests {
     fn do_not_extract_spaces1_when_input_does_not_start_with_them() {
         assert_eq!(
             extract_whitespace1("blah"),
-            Err(ParseError::ExpectedWhitespace),
+            Err(ParseErrorKind::ExpectedWhitespace.at("blah")),
         );
     }
 
@@ -229,8 +285,11 @@ mod tests {
 
     #[test]
     fn extract_reject_empty_ident() {
-        assert_eq!(extract_ident("  "), Err(ParseError::ExpectedIdent));
-        assert_eq!(extract_ident(""), Err(ParseError::ExpectedIdent));
+        assert_eq!(
+            extract_ident("  "),
+            Err(ParseErrorKind::ExpectedIdent.at("  "))
+        );
+        assert_eq!(extract_ident(""), Err(ParseErrorKind::ExpectedIdent.at("")));
     }
 
     #[test]
@@ -240,7 +299,10 @@ mod tests {
 
     #[test]
     fn cannot_extract_ident_beginning_with_number() {
-        assert_eq!(extract_ident("123abc"), Err(ParseError::ExpectedIdent),);
+        assert_eq!(
+            extract_ident("123abc"),
+            Err(ParseErrorKind::ExpectedIdent.at("123abc")),
+        );
     }
 
     #[test]
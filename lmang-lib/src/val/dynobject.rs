@@ -1,6 +1,7 @@
 use crate::error::RuntimeError;
 use crate::utils::kwords;
 use crate::val::Val;
+use std::any::Any;
 use std::fmt;
 
 pub trait Object {
@@ -9,6 +10,13 @@ pub trait Object {
     fn clone_box(&self) -> Box<dyn Object>;
     fn dyn_debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
     fn name(&self) -> &str;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Replaces the value of a member. Objects are copied along with values,
+    /// so ones that allow this keep their members behind shared references.
+    fn set_member(&self, name: &str, _val: Val) -> Result<(), RuntimeError> {
+        Err(RuntimeError::ReadOnlyMember(name.to_string()))
+    }
 }
 
 pub struct DynObject(pub Box<dyn Object>);
@@ -38,6 +46,10 @@ impl fmt::Debug for DynObject {
 
 impl fmt::Display for DynObject {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(shown) = crate::expr::class::display(self.0.as_ref()) {
+            return write!(f, "{}", shown);
+        }
+
         write!(f, "{}{}", kwords::CLASS, self.0.name())?;
         write!(f, " (")?;
         for m in &self.0.member_names() {
@@ -67,6 +79,9 @@ pub fn placeholder_object() -> DynObject {
         fn name(&self) -> &str {
             todo!()
         }
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            unreachable!()
+        }
     }
 
     DynObject(Box::new(ImplDetail))
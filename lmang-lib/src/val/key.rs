@@ -0,0 +1,95 @@
+use crate::error::RuntimeError;
+use crate::val::Val;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+pub type Map = HashMap<Key, Val, ahash::RandomState>;
+
+/// Value a map can be keyed by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Number(i32),
+    Char(char),
+    Bool(bool),
+    Str(Rc<str>),
+}
+
+impl TryFrom<&Val> for Key {
+    type Error = RuntimeError;
+
+    fn try_from(val: &Val) -> Result<Self, Self::Error> {
+        val.apply_to_root(|v| match v {
+            Val::Number(n) => Ok(Key::Number(*n)),
+            Val::Char(c) => Ok(Key::Char(*c)),
+            Val::Bool(b) => Ok(Key::Bool(*b)),
+            Val::Str(s) => Ok(Key::Str(s.clone())),
+            // a deque of characters equals the string, so it has to find
+            // the same entry.
+            Val::Deque(dq) if dq.iter().all(|v| v.as_char().is_ok()) => {
+                let s: String = dq.iter().map(|v| *v.as_char().unwrap()).collect();
+                Ok(Key::Str(s.into()))
+            }
+            _ => Err(RuntimeError::CastError {
+                from: v.variant_name().to_string(),
+                to: "🔑".into(),
+            }),
+        })?
+    }
+}
+
+impl From<Key> for Val {
+    fn from(key: Key) -> Val {
+        match key {
+            Key::Number(n) => Val::Number(n),
+            Key::Char(c) => Val::Char(c),
+            Key::Bool(b) => Val::Bool(b),
+            Key::Str(s) => Val::Str(s),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Val::from(self.clone()))
+    }
+}
+
+/// Entries of `map` ordered by key, so that programs don't depend on the
+/// hasher.
+pub fn sorted_entries(map: &Map) -> Vec<(&Key, &Val)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_unstable_by_key(|(k, _)| *k);
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_from_val() {
+        let cases = [
+            (Val::Number(1), Key::Number(1)),
+            (Val::Char('a'), Key::Char('a')),
+            (Val::Bool(true), Key::Bool(true)),
+            (Val::from("ab"), Key::Str("ab".into())),
+            (Val::from("ab").make_ref(), Key::Str("ab".into())),
+            (
+                Val::Deque(Box::new([Val::Char('a'), Val::Char('b')].into())),
+                Key::Str("ab".into()),
+            ),
+        ];
+
+        for (val, expected) in cases {
+            assert_eq!(Key::try_from(&val), Ok(expected));
+        }
+
+        let expected = RuntimeError::CastError {
+            from: "🛟".into(),
+            to: "🔑".into(),
+        };
+        assert_eq!(Key::try_from(&Val::Float(1.0)), Err(expected));
+    }
+}
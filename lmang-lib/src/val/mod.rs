@@ -1,5 +1,6 @@
 mod dynfunc;
 mod dynobject;
+mod key;
 pub mod view;
 
 #[cfg(feature = "web")]
@@ -7,6 +8,7 @@ mod web;
 
 pub use dynfunc::{placeholder_func, Callee, DynFunc};
 pub use dynobject::{placeholder_object, DynObject, Object};
+pub use key::{sorted_entries, Key, Map};
 
 #[cfg(feature = "web")]
 pub use web::{JsFunc, JsObj};
@@ -17,7 +19,7 @@ use std::cell::RefCell;
 use std::cmp::{Ordering, PartialEq, PartialOrd};
 use std::collections::VecDeque;
 use std::fmt;
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::{Add, Div, Mul, Neg, Not, Rem, Sub};
 use std::rc::{Rc, Weak};
 
 #[derive(Debug, Clone)]
@@ -39,16 +41,20 @@ impl PartialEq for WeakWrapper {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Val {
     // base types
     Number(i32),
+    Float(f64),
     Char(char),
+    Str(Rc<str>),
     Bool(bool),
     Unit,
     // collections
     Break(Box<Val>),
+    Return(Box<Val>),
     Deque(Box<VecDeque<Val>>),
+    Map(Box<Map>),
     // special
     Func(DynFunc),
     Object(DynObject),
@@ -61,7 +67,7 @@ pub enum Val {
 }
 
 fn pretty_print_deque(dq: &VecDeque<Val>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    let all_chars = dq.iter().map(|v| v.as_char()).all(|v| matches!(v, Ok(_)));
+    let all_chars = dq.iter().all(|v| v.as_char().is_ok());
 
     if all_chars {
         for v in dq.iter() {
@@ -78,15 +84,32 @@ fn pretty_print_deque(dq: &VecDeque<Val>, f: &mut fmt::Formatter<'_>) -> fmt::Re
     Ok(())
 }
 
+fn pretty_print_map(map: &Map, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", kwords::MAP)?;
+    for (i, (k, v)) in sorted_entries(map).into_iter().enumerate() {
+        if i != 0 {
+            write!(f, "{}", kwords::EXPR_SEP)?;
+        }
+        write!(f, "{}{}{}", k, kwords::FUNC_SEP, v)?;
+    }
+    write!(f, "{}", kwords::BLOCK_CLOSE)
+}
+
 impl fmt::Display for Val {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Number(n) => write!(f, "{}", n),
+            // debug formatting keeps the decimal point, so that the output
+            // reads back as a float, as do `NaN`, `inf` and negations.
+            Self::Float(x) => write!(f, "{:?}", x),
             Self::Char(c) => write!(f, "{}", c),
+            Self::Str(s) => write!(f, "{}", s),
             Self::Bool(b) => write!(f, "{}", if *b { kwords::TRUE } else { kwords::FALSE }),
             Self::Unit => write!(f, "📦🧑‍🦲"),
             Self::Break(val) => write!(f, "💔{}", val.as_ref()),
+            Self::Return(val) => write!(f, "🔙{}", val.as_ref()),
             Self::Deque(vals) => pretty_print_deque(vals, f),
+            Self::Map(map) => pretty_print_map(map, f),
             Self::Func(df) => write!(f, "{}", df),
             Self::Object(obj) => write!(f, "{}", obj),
             Self::Ref(rc) => write!(f, "🔖{}", rc.borrow()),
@@ -113,11 +136,15 @@ impl Val {
 
         match self {
             Number(_) => "🔢",
+            Float(_) => "🛟",
             Char(_) => "🔡",
+            Str(_) => "🧵",
             Bool(_) => "🤨",
             Unit => "📦🧑‍🦲",
             Break(_) => "💔",
+            Return(_) => "🔙",
             Deque(_) => "😵‍💫😵‍💫",
+            Map(_) => "🗺️",
             Func(_) => "🧰",
             Object(_) => "🧑‍🏫",
             Ref(_) => "🔖",
@@ -138,6 +165,18 @@ impl Val {
         }
     }
 
+    /// Value of an integer or a float as a float.
+    pub fn to_f64(&self) -> Result<f64, RuntimeError> {
+        match self {
+            Val::Number(n) => Ok(*n as f64),
+            Val::Float(x) => Ok(*x),
+            _ => Err(RuntimeError::CastError {
+                from: self.variant_name().to_string(),
+                to: "🔢".into(),
+            }),
+        }
+    }
+
     pub fn as_char(&self) -> Result<&char, RuntimeError> {
         match self {
             Val::Char(c) => Ok(c),
@@ -148,6 +187,16 @@ impl Val {
         }
     }
 
+    pub fn as_str(&self) -> Result<&str, RuntimeError> {
+        match self {
+            Val::Str(s) => Ok(s),
+            _ => Err(RuntimeError::CastError {
+                from: self.variant_name().to_string(),
+                to: "🧵".into(),
+            }),
+        }
+    }
+
     pub fn as_bool(&self) -> Result<&bool, RuntimeError> {
         match self {
             Val::Bool(b) => Ok(b),
@@ -188,6 +237,28 @@ impl Val {
         }
     }
 
+    pub fn as_map(&self) -> Result<&Map, RuntimeError> {
+        match self {
+            Self::Map(map) => Ok(map.as_ref()),
+            _ => Err(RuntimeError::CastError {
+                from: self.variant_name().to_string(),
+                to: "🗺️".into(),
+            }),
+        }
+    }
+
+    /// Elements of a deque, or characters of a string as a deque.
+    pub fn to_deque(&self) -> Result<VecDeque<Val>, RuntimeError> {
+        match self {
+            Self::Str(s) => Ok(s.chars().map(Val::Char).collect()),
+            Self::Deque(dq) => Ok(dq.as_ref().clone()),
+            _ => Err(RuntimeError::CastError {
+                from: self.variant_name().to_string(),
+                to: "😵‍💫😵‍💫".into(),
+            }),
+        }
+    }
+
     pub fn as_func(&self) -> Result<&DynFunc, RuntimeError> {
         match self {
             Val::Func(f) => Ok(f),
@@ -274,6 +345,16 @@ impl Val {
         }
     }
 
+    pub fn as_map_mut(&mut self) -> Result<&mut Map, RuntimeError> {
+        match self {
+            Self::Map(map) => Ok(map.as_mut()),
+            _ => Err(RuntimeError::CastError {
+                from: self.variant_name().to_string(),
+                to: "🗺️".into(),
+            }),
+        }
+    }
+
     pub fn as_deque_mut(&mut self) -> Result<&mut VecDeque<Val>, RuntimeError> {
         match self {
             Self::Deque(obj) => Ok(obj.as_mut()),
@@ -330,6 +411,14 @@ impl Val {
         }
     }
 
+    /// Turns a weak reference into a strong one, leaves other values as is.
+    pub(crate) fn upgrade(self) -> Result<Val, RuntimeError> {
+        match self {
+            Val::Weak(wk) => Ok(Val::Ref(wk.upgrade().ok_or(RuntimeError::Dangling)?)),
+            other => Ok(other),
+        }
+    }
+
     pub fn make_ref(&mut self) -> Val {
         match self {
             Val::Ref(rc) => Val::Ref(rc.clone()),
@@ -343,6 +432,17 @@ impl Val {
         }
     }
 
+    /// Equality as `==` sees it, which like the ordering promotes numbers to
+    /// floats. Otherwise values only equal ones of the same type.
+    pub fn equals(&self, other: &Val) -> bool {
+        match (self, other) {
+            (Self::Number(n), Self::Float(x)) | (Self::Float(x), Self::Number(n)) => {
+                f64::from(*n) == *x
+            }
+            _ => self == other,
+        }
+    }
+
     pub fn try_gt(&self, other: &Val) -> Result<Self, RuntimeError> {
         if self.partial_cmp(other).is_some() {
             Ok(Self::Bool(self > other))
@@ -391,12 +491,44 @@ impl Val {
         }
     }
 
+    pub fn try_and(&self, other: &Val) -> Result<Self, RuntimeError> {
+        self.logic(other, kwords::AND, |b1, b2| b1 && b2)
+    }
+
+    pub fn try_or(&self, other: &Val) -> Result<Self, RuntimeError> {
+        self.logic(other, kwords::OR, |b1, b2| b1 || b2)
+    }
+
+    /// Applies `f` to boolean operands, operands of any other type are
+    /// rejected.
+    fn logic(
+        &self,
+        other: &Val,
+        op: &str,
+        f: impl FnOnce(bool, bool) -> bool,
+    ) -> Result<Self, RuntimeError> {
+        self.apply_to_root(|v1| {
+            other.apply_to_root(|v2| match (v1, v2) {
+                (Val::Bool(b1), Val::Bool(b2)) => Ok(Val::Bool(f(*b1, *b2))),
+                _ => Err(RuntimeError::InvalidOp {
+                    lhs: v1.variant_name().into(),
+                    rhs: v2.variant_name().into(),
+                    op: op.into(),
+                }),
+            })
+        })??
+    }
+
     #[cfg(feature = "web")]
     pub fn convert_from_jv(jv: wasm_bindgen::JsValue) -> Self {
         if let Some(b) = jv.as_bool() {
             Val::Bool(b)
         } else if let Some(f) = jv.as_f64() {
-            Val::Number(f as i32)
+            if f.fract() == 0.0 && f >= i32::MIN as f64 && f <= i32::MAX as f64 {
+                Val::Number(f as i32)
+            } else {
+                Val::Float(f)
+            }
         } else if let Some(s) = jv.as_string() {
             s.as_str().into()
         } else if jv.is_function() {
@@ -419,16 +551,46 @@ impl Val {
     }
 }
 
-impl<'a, 'b> Add<&'b Val> for &'a Val {
+/// Operands of an arithmetic operation, promoted to floats unless both are
+/// integers.
+enum Operands {
+    Ints(i32, i32),
+    Floats(f64, f64),
+}
+
+impl Operands {
+    fn new(v1: &Val, v2: &Val) -> Result<Self, RuntimeError> {
+        match (v1, v2) {
+            (Val::Number(n1), Val::Number(n2)) => Ok(Operands::Ints(*n1, *n2)),
+            _ => Ok(Operands::Floats(v1.to_f64()?, v2.to_f64()?)),
+        }
+    }
+}
+
+impl Val {
+    /// Applies `int` to integer operands, which fails on overflow, or `float`
+    /// if either of the operands is a float.
+    fn arith(
+        &self,
+        other: &Val,
+        int: impl FnOnce(i32, i32) -> Option<i32>,
+        float: impl FnOnce(f64, f64) -> f64,
+    ) -> Result<Val, RuntimeError> {
+        let operands =
+            self.apply_to_root(|v1| other.apply_to_root(|v2| Operands::new(v1, v2)))???;
+
+        Ok(match operands {
+            Operands::Ints(n1, n2) => Val::Number(int(n1, n2).ok_or(RuntimeError::Overflow)?),
+            Operands::Floats(x1, x2) => Val::Float(float(x1, x2)),
+        })
+    }
+}
+
+impl<'b> Add<&'b Val> for &Val {
     type Output = Result<Val, RuntimeError>;
 
     fn add(self, other: &'b Val) -> Self::Output {
-        let n = self.apply_to_root(|v1| {
-            other.apply_to_root::<Result<_, RuntimeError>, _>(|v2| {
-                Ok(v1.as_number()? + v2.as_number()?)
-            })
-        })???;
-        Ok(Val::Number(n))
+        self.arith(other, i32::checked_add, |a, b| a + b)
     }
 }
 
@@ -440,16 +602,11 @@ impl Add for Val {
     }
 }
 
-impl<'a, 'b> Sub<&'b Val> for &'a Val {
+impl<'b> Sub<&'b Val> for &Val {
     type Output = Result<Val, RuntimeError>;
 
     fn sub(self, other: &'b Val) -> Self::Output {
-        let n = self.apply_to_root(|v1| {
-            other.apply_to_root::<Result<_, RuntimeError>, _>(|v2| {
-                Ok(v1.as_number()? - v2.as_number()?)
-            })
-        })???;
-        Ok(Val::Number(n))
+        self.arith(other, i32::checked_sub, |a, b| a - b)
     }
 }
 
@@ -461,16 +618,11 @@ impl Sub for Val {
     }
 }
 
-impl<'a, 'b> Mul<&'b Val> for &'a Val {
+impl<'b> Mul<&'b Val> for &Val {
     type Output = Result<Val, RuntimeError>;
 
     fn mul(self, other: &'b Val) -> Self::Output {
-        let n = self.apply_to_root(|v1| {
-            other.apply_to_root::<Result<_, RuntimeError>, _>(|v2| {
-                Ok(v1.as_number()? * v2.as_number()?)
-            })
-        })???;
-        Ok(Val::Number(n))
+        self.arith(other, i32::checked_mul, |a, b| a * b)
     }
 }
 
@@ -482,16 +634,18 @@ impl Mul for Val {
     }
 }
 
-impl<'a, 'b> Div<&'b Val> for &'a Val {
+impl<'b> Div<&'b Val> for &Val {
     type Output = Result<Val, RuntimeError>;
 
     fn div(self, other: &'b Val) -> Self::Output {
-        let n = self.apply_to_root(|v1| {
-            other.apply_to_root::<Result<_, RuntimeError>, _>(|v2| {
-                Ok(v1.as_number()? / v2.as_number()?)
-            })
-        })???;
-        Ok(Val::Number(n))
+        let int_div_by_zero = self.apply_to_root(|v1| {
+            other.apply_to_root(|v2| matches!((v1, v2), (Val::Number(_), Val::Number(0))))
+        })??;
+        if int_div_by_zero {
+            return Err(RuntimeError::DivisionByZero);
+        }
+
+        self.arith(other, i32::checked_div, |a, b| a / b)
     }
 }
 
@@ -503,20 +657,145 @@ impl Div for Val {
     }
 }
 
+impl<'b> Rem<&'b Val> for &Val {
+    type Output = Result<Val, RuntimeError>;
+
+    fn rem(self, other: &'b Val) -> Self::Output {
+        let int_div_by_zero = self.apply_to_root(|v1| {
+            other.apply_to_root(|v2| matches!((v1, v2), (Val::Number(_), Val::Number(0))))
+        })??;
+        if int_div_by_zero {
+            return Err(RuntimeError::DivisionByZero);
+        }
+
+        self.arith(other, i32::checked_rem, |a, b| a % b)
+    }
+}
+
+impl Rem for Val {
+    type Output = Result<Val, RuntimeError>;
+
+    fn rem(self, other: Self) -> Self::Output {
+        &self % &other
+    }
+}
+
+impl Neg for &Val {
+    type Output = Result<Val, RuntimeError>;
+
+    fn neg(self) -> Self::Output {
+        self.apply_to_root(|v| match v {
+            Val::Number(n) => n
+                .checked_neg()
+                .map(Val::Number)
+                .ok_or(RuntimeError::Overflow),
+            Val::Float(x) => Ok(Val::Float(-x)),
+            _ => Err(RuntimeError::InvalidOp {
+                lhs: String::new(),
+                rhs: v.variant_name().into(),
+                op: kwords::SUB.into(),
+            }),
+        })?
+    }
+}
+
+impl Neg for Val {
+    type Output = Result<Val, RuntimeError>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl Not for &Val {
+    type Output = Result<Val, RuntimeError>;
+
+    fn not(self) -> Self::Output {
+        self.apply_to_root(|v| match v {
+            Val::Bool(b) => Ok(Val::Bool(!b)),
+            _ => Err(RuntimeError::InvalidOp {
+                lhs: String::new(),
+                rhs: v.variant_name().into(),
+                op: kwords::NOT.into(),
+            }),
+        })?
+    }
+}
+
+impl Not for Val {
+    type Output = Result<Val, RuntimeError>;
+
+    fn not(self) -> Self::Output {
+        !&self
+    }
+}
+
+impl PartialEq for Val {
+    fn eq(&self, other: &Val) -> bool {
+        use Val::*;
+
+        match (self, other) {
+            (Number(n1), Number(n2)) => n1 == n2,
+            (Float(x1), Float(x2)) => x1 == x2,
+            (Char(c1), Char(c2)) => c1 == c2,
+            (Str(s1), Str(s2)) => s1 == s2,
+            // strings equal deques of their characters.
+            (Str(s), Deque(dq)) | (Deque(dq), Str(s)) => dq
+                .iter()
+                .map(|v| v.as_char().ok().copied())
+                .eq(s.chars().map(Some)),
+            (Bool(b1), Bool(b2)) => b1 == b2,
+            (Unit, Unit) => true,
+            (Break(v1), Break(v2)) => v1 == v2,
+            (Return(v1), Return(v2)) => v1 == v2,
+            (Deque(dq1), Deque(dq2)) => dq1 == dq2,
+            (Map(m1), Map(m2)) => m1 == m2,
+            (Func(f1), Func(f2)) => f1 == f2,
+            (Object(o1), Object(o2)) => o1 == o2,
+            (Ref(rc1), Ref(rc2)) => rc1 == rc2,
+            (Weak(wk1), Weak(wk2)) => wk1 == wk2,
+            (Named(n1), Named(n2)) => n1 == n2,
+            #[cfg(feature = "web")]
+            (JsValue(jv1), JsValue(jv2)) => jv1 == jv2,
+            // values of different types are never equal, each variant is listed
+            // so a new one has to be handled above.
+            (Number(_), _)
+            | (Float(_), _)
+            | (Char(_), _)
+            | (Str(_), _)
+            | (Bool(_), _)
+            | (Unit, _)
+            | (Break(_), _)
+            | (Return(_), _)
+            | (Deque(_), _)
+            | (Map(_), _)
+            | (Func(_), _)
+            | (Object(_), _)
+            | (Ref(_), _)
+            | (Weak(_), _)
+            | (Named(_), _) => false,
+            #[cfg(feature = "web")]
+            (JsValue(_), _) => false,
+        }
+    }
+}
+
 impl PartialOrd for Val {
     fn partial_cmp(&self, other: &Val) -> Option<Ordering> {
         match self {
-            Self::Number(n1) => {
-                let n2 = other.apply_to_root(|v| Some(*v.as_number().ok()?)).ok()??;
-                Some(n1.cmp(&n2))
+            Self::Number(_) | Self::Float(_) => {
+                match other.apply_to_root(|v| Operands::new(self, v)).ok()?.ok()? {
+                    Operands::Ints(n1, n2) => Some(n1.cmp(&n2)),
+                    Operands::Floats(x1, x2) => x1.partial_cmp(&x2),
+                }
             }
             _ => None,
         }
     }
 }
 
-impl<'a> From<&'a str> for Val {
+impl From<&str> for Val {
     fn from(s: &str) -> Val {
-        Val::Deque(Box::new(s.chars().map(|c| Self::Char(c)).collect()))
+        Val::Str(s.into())
     }
 }
@@ -39,6 +39,24 @@ impl View for Number {
     }
 }
 
+/// Integers and floats alike as floats, integers not being written back.
+#[derive(Default)]
+pub struct Float;
+
+impl View for Float {
+    type Output = f64;
+
+    fn view<T>(
+        val: &mut Val,
+        mut f: impl FnMut(&mut Self::Output) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        match val {
+            Val::Float(x) => f(x),
+            _ => f(&mut val.to_f64()?),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Char;
 
@@ -87,6 +105,8 @@ impl<V: View> View for Break<V> {
     }
 }
 
+/// Strings are immutable, viewing one as a deque turns it into a deque of its
+/// characters.
 #[derive(Default)]
 pub struct Deque;
 
@@ -97,10 +117,47 @@ impl View for Deque {
         val: &mut Val,
         mut f: impl FnMut(&mut Self::Output) -> Result<T, RuntimeError>,
     ) -> Result<T, RuntimeError> {
+        if let Val::Str(_) = val {
+            *val = Val::Deque(Box::new(val.to_deque()?));
+        }
+
         f(val.as_deque_mut()?)
     }
 }
 
+#[derive(Default)]
+pub struct Map;
+
+impl View for Map {
+    type Output = crate::val::Map;
+
+    fn view<T>(
+        val: &mut Val,
+        mut f: impl FnMut(&mut Self::Output) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        f(val.as_map_mut()?)
+    }
+}
+
+/// Elements of a deque, or characters of a string, changes to which are not
+/// written back to the string.
+#[derive(Default)]
+pub struct Items;
+
+impl View for Items {
+    type Output = VecDeque<Val>;
+
+    fn view<T>(
+        val: &mut Val,
+        mut f: impl FnMut(&mut Self::Output) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        match val {
+            Val::Str(_) => f(&mut val.to_deque()?),
+            _ => f(val.as_deque_mut()?),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct String;
 
@@ -111,20 +168,23 @@ impl View for String {
         val: &mut Val,
         mut f: impl FnMut(&mut Self::Output) -> Result<T, RuntimeError>,
     ) -> Result<T, RuntimeError> {
-        Deque::view(val, move |dq| {
-            let all_chars = dq.iter().all(|v| v.as_char().is_ok());
-            if all_chars {
-                let mut s = dq.iter().map(|v| *v.as_char().unwrap()).collect();
-                f(&mut s)
-            } else {
-                Err(RuntimeError::CastError {
-                    from: Val::Deque(Box::new(VecDeque::new()))
-                        .variant_name()
-                        .to_string(),
-                    to: "string".to_string(),
-                })
+        match val {
+            Val::Str(s) => f(&mut s.to_string()),
+            Val::Deque(dq) => {
+                let s = dq.iter().map(|v| v.as_char().copied()).collect();
+                match s {
+                    Ok(mut s) => f(&mut s),
+                    Err(_) => Err(RuntimeError::CastError {
+                        from: val.variant_name().to_string(),
+                        to: "🧵".to_string(),
+                    }),
+                }
             }
-        })
+            _ => Err(RuntimeError::CastError {
+                from: val.variant_name().to_string(),
+                to: "🧵".to_string(),
+            }),
+        }
     }
 }
 
@@ -155,7 +215,7 @@ impl<V: View> View for Ref<V> {
         let refrc = val.as_val_ref_mut()?;
         let mut borrow = refrc.borrow_mut();
 
-        <V as View>::view(&mut *borrow, f)
+        <V as View>::view(&mut borrow, f)
     }
 }
 
@@ -247,11 +307,16 @@ where
     Ok((results, tail))
 }
 
+/// Splits off the first `N` values. Counts in the error are relative to
+/// `vals`, calling a builtin rebases them onto all of its arguments.
 pub fn take_n<const N: usize>(
     vals: &mut [Val],
 ) -> Result<(&mut [Val; N], &mut [Val]), RuntimeError> {
     if vals.len() < N {
-        Err(RuntimeError::WrongArgsN)
+        Err(RuntimeError::WrongArgsN {
+            expected: N,
+            received: vals.len(),
+        })
     } else {
         let (requested, tail) = vals.split_at_mut(N);
         let requested = requested.try_into().unwrap();
@@ -264,46 +329,73 @@ pub fn test_consumed(tail: &[Val]) -> Result<(), RuntimeError> {
     if tail.is_empty() {
         Ok(())
     } else {
-        Err(RuntimeError::WrongArgsN)
+        Err(RuntimeError::WrongArgsN {
+            expected: 0,
+            received: tail.len(),
+        })
     }
 }
 
 pub trait DequeExt {
     fn try_get(&mut self, idx: i32) -> Result<&mut Val, RuntimeError>;
     fn try_remove(&mut self, idx: i32) -> Result<Val, RuntimeError>;
+    fn try_insert(&mut self, idx: i32, val: Val) -> Result<(), RuntimeError>;
+}
+
+/// Position of `idx` in a deque of `len` elements, negative indices counting
+/// from the back.
+fn relative_idx(idx: i32, len: usize) -> Result<usize, RuntimeError> {
+    let idx_rel = if idx >= 0 {
+        Some(idx as usize)
+    } else {
+        len.checked_sub(idx.unsigned_abs() as usize)
+    };
+
+    idx_rel
+        .filter(|&idx_rel| idx_rel < len)
+        .ok_or(RuntimeError::OutOfBounds { idx, len })
+}
+
+/// Position of the boundary `idx` in a sequence of `len` elements, which can
+/// also be right after the last one. Negative indices count from the back.
+pub fn relative_bound(idx: i32, len: usize) -> Result<usize, RuntimeError> {
+    let bound = if idx >= 0 {
+        Some(idx as usize)
+    } else {
+        len.checked_sub(idx.unsigned_abs() as usize)
+    };
+
+    bound
+        .filter(|&bound| bound <= len)
+        .ok_or(RuntimeError::OutOfBounds { idx, len })
+}
+
+/// Character at `idx` of `s`, negative indices counting from the back.
+pub fn char_at(s: &str, idx: i32) -> Result<char, RuntimeError> {
+    if s.is_ascii() {
+        let idx_rel = relative_idx(idx, s.len())?;
+        Ok(s.as_bytes()[idx_rel] as char)
+    } else {
+        let idx_rel = relative_idx(idx, s.chars().count())?;
+        Ok(s.chars().nth(idx_rel).expect("index checked"))
+    }
 }
 
 impl DequeExt for VecDeque<Val> {
     fn try_get(&mut self, idx: i32) -> Result<&mut Val, RuntimeError> {
-        let len = self.len();
-        let idx_rel = if idx >= 0 {
-            idx as usize
-        } else {
-            use std::ops::Sub;
-            len.sub((-idx) as usize)
-        };
-
-        self.get_mut(idx_rel)
-            .ok_or_else(|| RuntimeError::OutOfBounds {
-                idx: idx_rel as i32,
-                len,
-            })
+        let idx_rel = relative_idx(idx, self.len())?;
+        Ok(&mut self[idx_rel])
     }
 
     fn try_remove(&mut self, idx: i32) -> Result<Val, RuntimeError> {
-        let len = self.len();
-        let idx_rel = if idx >= 0 {
-            idx as usize
-        } else {
-            use std::ops::Sub;
-            len.sub((-idx) as usize)
-        };
+        let idx_rel = relative_idx(idx, self.len())?;
+        Ok(self.remove(idx_rel).expect("index checked"))
+    }
 
-        self.remove(idx_rel)
-            .ok_or_else(|| RuntimeError::OutOfBounds {
-                idx: idx_rel as i32,
-                len,
-            })
+    fn try_insert(&mut self, idx: i32, val: Val) -> Result<(), RuntimeError> {
+        let idx_rel = relative_bound(idx, self.len())?;
+        self.insert(idx_rel, val);
+        Ok(())
     }
 }
 
@@ -312,6 +404,42 @@ mod tests {
     use super::*;
     use std::{cell::RefCell, rc::Rc};
 
+    #[test]
+    fn deque_relative_idx() {
+        assert_eq!(relative_idx(1, 3), Ok(1));
+        assert_eq!(relative_idx(-1, 3), Ok(2));
+        assert_eq!(relative_idx(-3, 3), Ok(0));
+
+        for idx in [3, -4, i32::MAX, i32::MIN] {
+            let expected = RuntimeError::OutOfBounds { idx, len: 3 };
+            assert_eq!(relative_idx(idx, 3), Err(expected));
+        }
+    }
+
+    #[test]
+    fn deque_relative_bound() {
+        assert_eq!(relative_bound(3, 3), Ok(3));
+        assert_eq!(relative_bound(-3, 3), Ok(0));
+        assert_eq!(relative_bound(0, 0), Ok(0));
+
+        for idx in [4, -4] {
+            let expected = RuntimeError::OutOfBounds { idx, len: 3 };
+            assert_eq!(relative_bound(idx, 3), Err(expected));
+        }
+    }
+
+    #[test]
+    fn str_char_at() {
+        assert_eq!(char_at("abc", 1), Ok('b'));
+        assert_eq!(char_at("abc", -1), Ok('c'));
+        assert_eq!(char_at("żółw", 2), Ok('ł'));
+        assert_eq!(char_at("żółw", -4), Ok('ż'));
+        assert_eq!(
+            char_at("żółw", 4),
+            Err(RuntimeError::OutOfBounds { idx: 4, len: 4 })
+        );
+    }
+
     #[test]
     fn check_ref_ref_int() {
         let ref_ref_int_constructor = |i| {
@@ -26,6 +26,8 @@ fn val_to_jv(val: &Val, env: &Env) -> JsValue {
     match val {
         Val::Bool(b) => JsValue::from(*b),
         Val::Number(n) => JsValue::from(*n),
+        Val::Float(x) => JsValue::from(*x),
+        Val::Str(s) => JsValue::from_str(s),
         Val::Deque(dq) => dq_to_jv(dq.as_ref(), env),
         Val::Func(f) => {
             let mut env_own = env.shared();
@@ -189,4 +191,8 @@ impl Object for JsObj {
     fn name(&self) -> &str {
         &self.name
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
@@ -0,0 +1,576 @@
+use crate::error::TraceFrame;
+use crate::expr::binding_update::{BindingUpdate, Mode};
+use crate::expr::block::Block;
+use crate::expr::call::callee_name;
+use crate::expr::class::{Class, BASE};
+use crate::expr::for_expr::{For, Iterable};
+use crate::expr::func::{Arg, Func};
+use crate::expr::if_expr::If;
+use crate::expr::match_expr::Match;
+use crate::expr::try_expr::{Handler, Try};
+use crate::expr::{Expr, Op};
+use crate::val::Val;
+use crate::vm::{Catch, ClassSlots, Instr, Proto};
+use std::rc::Rc;
+
+/// Names declared so far in a block, with their slots.
+struct Scope {
+    start: u32,
+    names: Vec<(String, u32)>,
+}
+
+pub(crate) struct Compiler {
+    proto: Proto,
+    /// Innermost last. Empty at the top level of a program, where
+    /// declarations go to the `Env` instead.
+    scopes: Vec<Scope>,
+}
+
+impl Compiler {
+    pub(crate) fn script(expr: &Expr) -> Proto {
+        let mut compiler = Compiler {
+            proto: Proto::default(),
+            scopes: Vec::new(),
+        };
+        compiler.expr(expr);
+        compiler.emit(Instr::Return);
+
+        compiler.proto
+    }
+
+    pub(crate) fn function(func: &Func) -> Proto {
+        let mut compiler = Compiler {
+            proto: Proto::default(),
+            scopes: vec![Scope {
+                start: 0,
+                names: Vec::new(),
+            }],
+        };
+
+        for arg in &func.args {
+            compiler.declare(arg.name());
+        }
+        compiler.proto.args = func.args.clone().into();
+        compiler.proto.generator = func.generator;
+
+        for name in &func.captures {
+            let slot = compiler.declare(name);
+            compiler.proto.free.push((name.clone(), slot));
+        }
+
+        compiler.block(&func.body);
+        compiler.emit(Instr::Return);
+
+        compiler.proto
+    }
+
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.proto.code.push(instr);
+        self.proto.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.proto.code.len() as u32
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let here = self.here();
+        match &mut self.proto.code[at] {
+            Instr::Jump(target)
+            | Instr::JumpIfFalse(target)
+            | Instr::JumpIfBreak(target)
+            | Instr::ShortCircuit(_, target)
+            | Instr::Match(_, target)
+            | Instr::Next(_, target)
+            | Instr::Try(target) => *target = here,
+            instr => unreachable!("{:?} is not a jump", instr),
+        }
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        let names = &mut self.proto.names;
+        match names.iter().position(|n| n == name) {
+            Some(idx) => idx as u32,
+            None => {
+                names.push(name.to_string());
+                names.len() as u32 - 1
+            }
+        }
+    }
+
+    fn constant(&mut self, val: Val) -> u32 {
+        self.proto.consts.push(val);
+        self.proto.consts.len() as u32 - 1
+    }
+
+    fn next_slot(&self) -> u32 {
+        self.proto.slots.len() as u32
+    }
+
+    /// Slot of `name` in the innermost scope, reused if already declared there.
+    fn declare(&mut self, name: &str) -> u32 {
+        let next = self.next_slot();
+        let scope = self.scopes.last_mut().expect("no scope");
+        if let Some((_, slot)) = scope.names.iter().find(|(n, _)| n == name) {
+            return *slot;
+        }
+
+        scope.names.push((name.to_string(), next));
+        self.proto.slots.push(name.to_string());
+
+        next
+    }
+
+    fn resolve(&self, name: &str) -> Option<u32> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.names.iter())
+            .find(|(n, _)| n == name)
+            .map(|(_, slot)| *slot)
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Operation { lhs, rhs, op } => {
+                self.expr(lhs);
+                let short_circuit = matches!(op, Op::And | Op::Or)
+                    .then(|| self.emit(Instr::ShortCircuit(op.clone(), 0)));
+                self.expr(rhs);
+                self.emit(Instr::BinOp(op.clone()));
+                if let Some(at) = short_circuit {
+                    self.patch(at);
+                }
+            }
+            Expr::UnaryOperation { operand, op } => {
+                self.expr(operand);
+                self.emit(Instr::UnaryOp(op.clone()));
+            }
+            Expr::BindingUpdate(bu) => self.binding_update(bu),
+            Expr::MemberUpdate(mu) => {
+                self.expr(&mu.val);
+                let skip = self.emit(Instr::JumpIfBreak(0));
+                self.expr(&mu.object);
+                let name = self.name(&mu.member);
+                self.emit(Instr::SetMember(name));
+                self.emit(Instr::Unit);
+                self.patch(skip);
+            }
+            Expr::BindingUsage(usage) => match self.resolve(&usage.name) {
+                Some(slot) => {
+                    self.emit(Instr::Load(slot));
+                }
+                None => {
+                    let name = self.name(&usage.name);
+                    self.emit(Instr::LoadName(name));
+                }
+            },
+            Expr::Block(block) => self.block(block),
+            Expr::Class(class) => self.class(class),
+            Expr::If(if_e) => self.if_e(if_e),
+            Expr::Index(index) => {
+                self.expr(&index.root);
+                for ident in &index.idents {
+                    let name = self.name(ident);
+                    self.emit(Instr::Member(name));
+                }
+                self.emit(Instr::Upgrade);
+            }
+            Expr::Import(import_e) => {
+                self.expr(&import_e.path);
+                self.emit(Instr::Import);
+            }
+            Expr::Throw(throw_e) => {
+                self.block(&throw_e.body);
+                self.emit(Instr::Throw);
+            }
+            Expr::Break(break_e) => {
+                self.block(&break_e.body);
+                self.emit(Instr::Break);
+            }
+            Expr::Return(return_e) => {
+                self.block(&return_e.body);
+                self.emit(Instr::EarlyReturn);
+            }
+            Expr::Yield(yield_e) => {
+                self.block(&yield_e.body);
+                let skip = self.emit(Instr::JumpIfBreak(0));
+                self.emit(Instr::Yield);
+                self.patch(skip);
+            }
+            Expr::Map(map_e) => {
+                for (key, val) in &map_e.entries {
+                    self.expr(key);
+                    self.expr(val);
+                }
+                self.emit(Instr::MakeMap(map_e.entries.len() as u32));
+            }
+            Expr::For(for_e) => self.for_e(for_e),
+            Expr::While(while_e) => {
+                let start = self.here();
+                self.expr(&while_e.cond);
+                let exit = self.emit(Instr::JumpIfFalse(0));
+                self.block(&while_e.body);
+                self.emit(Instr::LoopBack(start));
+                let end = self.emit(Instr::Jump(0));
+
+                self.patch(exit);
+                self.emit(Instr::Unit);
+                self.patch(end);
+            }
+            Expr::Loop(loop_e) => {
+                let start = self.here();
+                self.block(&loop_e.body);
+                self.emit(Instr::LoopBack(start));
+            }
+            Expr::Func(func) => self.closure(func),
+            Expr::Call(call) => {
+                for arg in &call.args {
+                    self.expr(arg);
+                }
+                self.expr(&call.func);
+
+                self.proto.sites.push(TraceFrame {
+                    name: callee_name(&call.func),
+                    span: call.span,
+                });
+                let site = self.proto.sites.len() as u32 - 1;
+                let argc = call.args.len() as u32;
+                self.emit(Instr::Call { argc, site });
+            }
+            Expr::Literal(lit) => {
+                let idx = self.constant(lit.0.clone());
+                self.emit(Instr::Const(idx));
+            }
+            Expr::Ref(ref_e) => match self.resolve(&ref_e.ident) {
+                Some(slot) => {
+                    self.emit(Instr::Ref(slot));
+                }
+                None => {
+                    let name = self.name(&ref_e.ident);
+                    self.emit(Instr::RefName(name));
+                }
+            },
+            Expr::Try(try_e) => self.try_e(try_e),
+            Expr::Match(match_e) => self.match_e(match_e),
+            Expr::Named(named) => {
+                self.expr(&named.expr);
+                let name = self.name(&named.name);
+                self.emit(Instr::Named(name));
+            }
+        }
+    }
+
+    fn binding_update(&mut self, bu: &BindingUpdate) {
+        // a local function is declared before it's created so that it can
+        // capture itself.
+        if let (Mode::CreateLocal, Expr::Func(func), false) =
+            (&bu.mode, &bu.val, self.scopes.is_empty())
+        {
+            let slot = self.declare(&bu.name);
+            self.emit(Instr::Unit);
+            self.emit(Instr::Declare(slot));
+            self.closure(func);
+            self.emit(Instr::BindFunc(slot));
+            self.emit(Instr::Unit);
+
+            return;
+        }
+
+        self.expr(&bu.val);
+        let skip = self.emit(Instr::JumpIfBreak(0));
+
+        let instr = match bu.mode {
+            Mode::CreateLocal if self.scopes.is_empty() => Instr::DeclareName(self.name(&bu.name)),
+            Mode::CreateLocal => Instr::Declare(self.declare(&bu.name)),
+            Mode::CreateGlobal => Instr::DeclareGlobal(self.name(&bu.name)),
+            Mode::Set => match self.resolve(&bu.name) {
+                Some(slot) => Instr::Set(slot),
+                None => Instr::SetName(self.name(&bu.name)),
+            },
+        };
+        self.emit(instr);
+        self.emit(Instr::Unit);
+
+        self.patch(skip);
+    }
+
+    /// Compiles the expressions of a block in a new scope, leaving the scope
+    /// open. A break in any but the last expression skips the rest.
+    fn scoped(&mut self, block: &Block) {
+        self.scopes.push(Scope {
+            start: self.next_slot(),
+            names: Vec::new(),
+        });
+        self.sequence(block);
+    }
+
+    /// Compiles the expressions of a block in the current scope, leaving the
+    /// value of the last one.
+    fn sequence(&mut self, block: &Block) {
+        let mut skips = Vec::new();
+        for (k, expr) in block.exprs.iter().enumerate() {
+            self.expr(expr);
+            if k != block.exprs.len() - 1 {
+                skips.push(self.emit(Instr::JumpIfBreak(0)));
+                self.emit(Instr::Pop);
+            }
+        }
+
+        for skip in skips {
+            self.patch(skip);
+        }
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().expect("no scope");
+        let end = self.next_slot();
+        if scope.start != end {
+            self.emit(Instr::Clear(scope.start, end));
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        if block.exprs.is_empty() {
+            self.emit(Instr::Unit);
+            return;
+        }
+
+        self.scoped(block);
+        self.end_scope();
+    }
+
+    fn class(&mut self, class: &Class) {
+        let block = &class.body;
+        if class.base.is_none() && block.exprs.is_empty() {
+            self.proto.classes.push(ClassSlots::default());
+            self.emit(Instr::MakeClass(self.proto.classes.len() as u32 - 1));
+            return;
+        }
+
+        if let Some(base) = &class.base {
+            self.expr(base);
+            self.emit(Instr::Base);
+        }
+
+        self.scopes.push(Scope {
+            start: self.next_slot(),
+            names: Vec::new(),
+        });
+        let base = class.base.as_ref().map(|_| {
+            let slot = self.declare(BASE);
+            self.emit(Instr::Declare(slot));
+            slot
+        });
+        if !block.exprs.is_empty() {
+            self.sequence(block);
+            self.emit(Instr::Pop);
+        }
+
+        let members = self.scopes.last().expect("no scope").names.iter();
+        let members = members
+            .filter(|(_, slot)| Some(*slot) != base)
+            .cloned()
+            .collect();
+        self.proto.classes.push(ClassSlots { members, base });
+        self.emit(Instr::MakeClass(self.proto.classes.len() as u32 - 1));
+
+        self.end_scope();
+    }
+
+    fn if_e(&mut self, if_e: &If) {
+        let mut ends = Vec::new();
+
+        let branches = std::iter::once((&if_e.cond, &if_e.body))
+            .chain(if_e.elifs.iter().map(|(cond, body)| (cond, body)));
+        for (cond, body) in branches {
+            self.expr(cond);
+            let next = self.emit(Instr::JumpIfFalse(0));
+            self.block(body);
+            ends.push(self.emit(Instr::Jump(0)));
+            self.patch(next);
+        }
+
+        match &if_e.body_else {
+            Some(body) => self.block(body),
+            None => {
+                self.emit(Instr::Unit);
+            }
+        }
+
+        for end in ends {
+            self.patch(end);
+        }
+    }
+
+    fn try_e(&mut self, try_e: &Try) {
+        let finally_block = match &try_e.finally_block {
+            Some(body) => body,
+            None => return self.try_except(try_e),
+        };
+
+        // errors raised by the handlers run the finally block too, after
+        // which they're raised again.
+        let on_error = self.emit(Instr::Try(0));
+        self.try_except(try_e);
+        self.emit(Instr::EndTry);
+        self.block(finally_block);
+        self.emit(Instr::Pop);
+        let end = self.emit(Instr::Jump(0));
+
+        self.patch(on_error);
+        self.block(finally_block);
+        self.emit(Instr::Pop);
+        self.emit(Instr::Throw);
+
+        self.patch(end);
+    }
+
+    fn try_except(&mut self, try_e: &Try) {
+        let handler = self.emit(Instr::Try(0));
+        self.block(&try_e.try_block);
+        self.emit(Instr::EndTry);
+        let mut ends = vec![self.emit(Instr::Jump(0))];
+
+        self.patch(handler);
+        self.proto.catches.push(Catch::default());
+        let idx = self.proto.catches.len() - 1;
+        self.emit(Instr::Catch(idx as u32));
+
+        for (ty, handler) in &try_e.except_blocks {
+            let start = self.here();
+            self.proto.catches[idx].excepts.push((ty.clone(), start));
+            self.handler(handler);
+            ends.push(self.emit(Instr::Jump(0)));
+        }
+
+        if let Some(handler) = &try_e.except_any_block {
+            self.proto.catches[idx].any = Some(self.here());
+            self.handler(handler);
+        }
+
+        for end in ends {
+            self.patch(end);
+        }
+    }
+
+    /// Compiles an except clause, which starts with the error on the stack.
+    fn handler(&mut self, handler: &Handler) {
+        let name = match &handler.binding {
+            Some(name) => name,
+            None => {
+                self.emit(Instr::Pop);
+                return self.block(&handler.body);
+            }
+        };
+
+        self.scopes.push(Scope {
+            start: self.next_slot(),
+            names: Vec::new(),
+        });
+        let slot = self.declare(name);
+        self.emit(Instr::Declare(slot));
+        self.block(&handler.body);
+        self.end_scope();
+    }
+
+    fn for_e(&mut self, for_e: &For) {
+        match &for_e.iterable {
+            Iterable::Items(expr) => {
+                self.expr(expr);
+                self.emit(Instr::Iter);
+            }
+            Iterable::Range(start, end) => {
+                self.expr(start);
+                self.expr(end);
+                self.emit(Instr::IterRange);
+            }
+        }
+
+        self.scopes.push(Scope {
+            start: self.next_slot(),
+            names: Vec::new(),
+        });
+        let slot = self.declare(&for_e.var);
+        let start = self.here();
+        let exhausted = self.emit(Instr::Next(slot, 0));
+        self.block(&for_e.body);
+        self.end_scope();
+
+        // a break leaves the iterator behind.
+        self.emit(Instr::LoopBack(start));
+        self.emit(Instr::EndIter);
+        let end = self.emit(Instr::Jump(0));
+
+        self.patch(exhausted);
+        self.emit(Instr::Unit);
+        self.patch(end);
+    }
+
+    fn match_e(&mut self, match_e: &Match) {
+        self.expr(&match_e.val);
+
+        // a break or return in the matched value skips the arms.
+        let mut ends = vec![self.emit(Instr::JumpIfBreak(0))];
+        for arm in &match_e.arms {
+            self.scopes.push(Scope {
+                start: self.next_slot(),
+                names: Vec::new(),
+            });
+            let names = arm.pattern.bindings();
+            let slots = names.into_iter().map(|name| self.declare(name)).collect();
+            self.proto.patterns.push((arm.pattern.clone(), slots));
+            let idx = self.proto.patterns.len() as u32 - 1;
+
+            let mut fails = vec![self.emit(Instr::Match(idx, 0))];
+            if let Some(guard) = &arm.guard {
+                self.expr(guard);
+                fails.push(self.emit(Instr::JumpIfFalse(0)));
+            }
+            self.emit(Instr::Pop);
+            self.block(&arm.body);
+            self.end_scope();
+            ends.push(self.emit(Instr::Jump(0)));
+
+            for fail in fails {
+                self.patch(fail);
+            }
+        }
+        self.emit(Instr::NoMatch);
+
+        for end in ends {
+            self.patch(end);
+        }
+    }
+
+    fn closure(&mut self, func: &Func) {
+        for arg in &func.args {
+            if let Arg::Default(_, default) = arg {
+                self.expr(default);
+            }
+        }
+
+        // globals stay visible in function bodies, so only slots need to be
+        // captured.
+        let captures = func
+            .captures
+            .iter()
+            .filter_map(|name| Some((name.clone(), self.resolve(name)?)))
+            .collect();
+
+        // the code 🪞 runs looks up locals by name, which only the
+        // tree-walker keeps them by.
+        if func.evals && !func.generator {
+            self.proto.funcs.push((func.clone(), captures));
+            self.emit(Instr::Interpreted(self.proto.funcs.len() as u32 - 1));
+            return;
+        }
+
+        let mut proto = Compiler::function(func);
+        proto.captures = captures;
+
+        self.proto.protos.push(Rc::new(proto));
+        self.emit(Instr::Closure(self.proto.protos.len() as u32 - 1));
+    }
+}
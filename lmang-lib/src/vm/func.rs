@@ -0,0 +1,82 @@
+use crate::env::Env;
+use crate::expr::func::{
+    bind_args, positional, returned, unshare, weaken_capture, Captured, Parent,
+};
+use crate::val::{Callee, Val};
+use crate::vm::{generator, run, Proto};
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// Compiled counterpart of `FuncVal`.
+#[derive(Clone, Debug)]
+pub(crate) struct VmFunc {
+    proto: Rc<Proto>,
+    defaults: Rc<[Val]>,
+    pub(crate) parent: Option<Parent>,
+}
+
+impl VmFunc {
+    pub(crate) fn new(proto: Rc<Proto>, defaults: Vec<Val>, captured: Captured) -> Self {
+        VmFunc {
+            proto,
+            defaults: defaults.into(),
+            parent: if captured.is_empty() {
+                None
+            } else {
+                Some(Rc::new(captured))
+            },
+        }
+    }
+
+    pub(crate) fn weaken_capture(&mut self, name: &str) -> Option<Rc<RefCell<Val>>> {
+        weaken_capture(&mut self.parent, name)
+    }
+}
+
+impl Callee for VmFunc {
+    fn call(&self, args: &mut [Val], env: &mut Env) -> Result<Val, Val> {
+        env.check_timeout()?;
+
+        let proto = &self.proto;
+        let mut slots = Vec::with_capacity(proto.slots.len());
+        if positional(&proto.args, args) {
+            slots.extend(args.iter().cloned().map(Some));
+        } else {
+            let vals = bind_args(&proto.args, &self.defaults, args)?;
+            slots.extend(vals.into_iter().map(Some));
+        }
+        slots.resize(proto.slots.len(), None);
+
+        if let Some(parent) = &self.parent {
+            for (name, slot) in &proto.free {
+                slots[*slot as usize] = parent.get(name).cloned();
+            }
+        }
+
+        if proto.generator {
+            return Ok(generator::start(proto.clone(), slots));
+        }
+
+        let val = env.isolated(|env| run(proto, slots, env))?;
+        Ok(unshare(returned(val)?, &self.parent))
+    }
+
+    fn clone_box(&self) -> Box<dyn Callee> {
+        Box::new(self.clone())
+    }
+
+    fn dyn_debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use std::fmt::Debug;
+        self.fmt(f)
+    }
+
+    fn dyn_display(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "user-defined")
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,98 @@
+//! Generators, which run the body of a function a bit at a time, up to each
+//! value it yields.
+
+use crate::builtins::{FnState, RustFn, RustObj};
+use crate::env::Env;
+use crate::expr::for_expr::{done, Iter, NEXT};
+use crate::expr::func::returned;
+use crate::val::view::test_consumed;
+use crate::val::Val;
+use crate::vm::{Exit, Handler, Machine, Proto};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Where the body stopped, kept between values.
+struct Suspended {
+    slots: Vec<Option<Val>>,
+    stack: Vec<Val>,
+    handlers: Vec<Handler>,
+    iters: Vec<Iter>,
+    pc: usize,
+}
+
+struct Generator {
+    proto: Rc<Proto>,
+    /// `None` once the body is done, and while it runs.
+    suspended: Option<Suspended>,
+}
+
+/// Iterator over the values yielded by the body of `proto`, with its
+/// arguments and captures already in `slots`.
+pub(super) fn start(proto: Rc<Proto>, slots: Vec<Option<Val>>) -> Val {
+    let suspended = Suspended {
+        slots,
+        stack: Vec::new(),
+        handlers: Vec::new(),
+        iters: Vec::new(),
+        pc: 0,
+    };
+    let state = Rc::new(RefCell::new(Generator {
+        proto,
+        suspended: Some(suspended),
+    }));
+
+    Val::from_obj(RustObj::new(
+        "generator",
+        vec![RustFn::stateful(NEXT, next, &state)],
+    ))
+}
+
+/// Runs the body up to the next value it yields, or returns `🏁` once it's
+/// done.
+fn next(args: &mut [Val], env: &mut Env, state: FnState) -> Result<Val, Val> {
+    test_consumed(args)?;
+    env.check_timeout()?;
+
+    // the body may resume its own generator, so nothing stays borrowed while
+    // it runs.
+    let (proto, suspended) = {
+        let mut borrow = state.0.borrow_mut();
+        let gen: &mut Generator = borrow.downcast_mut::<Generator>().unwrap();
+        match gen.suspended.take() {
+            Some(suspended) => (gen.proto.clone(), suspended),
+            None => return Ok(done()),
+        }
+    };
+
+    let mut machine = Machine {
+        proto: &proto,
+        slots: suspended.slots,
+        stack: suspended.stack,
+        handlers: suspended.handlers,
+        iters: suspended.iters,
+        pc: suspended.pc,
+        trace_len: 0,
+    };
+    match env.isolated(|env| machine.run(env))? {
+        Exit::Yield(val) => {
+            // the yield itself evaluates to a unit.
+            machine.stack.push(Val::Unit);
+
+            let mut borrow = state.0.borrow_mut();
+            let gen: &mut Generator = borrow.downcast_mut::<Generator>().unwrap();
+            gen.suspended = Some(Suspended {
+                slots: machine.slots,
+                stack: machine.stack,
+                handlers: machine.handlers,
+                iters: machine.iters,
+                pc: machine.pc,
+            });
+
+            Ok(val)
+        }
+        Exit::Return(val) => {
+            returned(val)?;
+            Ok(done())
+        }
+    }
+}
@@ -0,0 +1,1172 @@
+//! Bytecode backend, an alternative to evaluating the syntax tree directly.
+//!
+//! A [`Program`] is compiled once from a parsed [`Expr`] and behaves like the
+//! tree-walking interpreter, except that names declared in blocks and function
+//! arguments live in numbered slots of a frame instead of hash maps. Names
+//! which can't be resolved at compile time are looked up in the `Env` by name.
+
+mod compiler;
+mod func;
+mod generator;
+
+use crate::env::{Env, Eval};
+use crate::error::{RuntimeError, TraceFrame, Traced};
+use crate::expr::call;
+use crate::expr::class::{self, ClassObject};
+use crate::expr::for_expr::{self, Iter};
+use crate::expr::func::{Arg, Captured, Func, FuncVal};
+use crate::expr::import;
+use crate::expr::index;
+use crate::expr::match_expr::Pattern;
+use crate::expr::try_expr::error_type;
+use crate::expr::{Expr, Op, UnaryOp};
+use crate::val::{DynFunc, Key, Val};
+use std::rc::Rc;
+
+pub(crate) use compiler::Compiler;
+pub(crate) use func::VmFunc;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Instr {
+    /// Push a constant.
+    Const(u32),
+    Unit,
+    Pop,
+    /// Push the value of a slot.
+    Load(u32),
+    /// Push the value of a name looked up in the `Env`.
+    LoadName(u32),
+    /// Pop a value into a slot, replacing what was there.
+    Declare(u32),
+    /// Pop a value into the innermost `Env` frame.
+    DeclareName(u32),
+    /// Pop a value into a global.
+    DeclareGlobal(u32),
+    /// Pop a value into a slot, writing through references.
+    Set(u32),
+    SetName(u32),
+    /// Push a reference to a slot, turning its value into a reference first.
+    Ref(u32),
+    RefName(u32),
+    /// Pop a function into a slot declared ahead of it, so it can call itself.
+    BindFunc(u32),
+    /// Forget the values of slots in a range, run as blocks end.
+    Clear(u32, u32),
+    BinOp(Op),
+    UnaryOp(UnaryOp),
+    Named(u32),
+    /// Replace an object with one of its members.
+    Member(u32),
+    /// Pop an object and then a value to set one of its members to.
+    SetMember(u32),
+    Upgrade,
+    /// Pop the callee and then its arguments, push the result.
+    Call {
+        argc: u32,
+        site: u32,
+    },
+    /// Pop the values of the defaults of a function and create it.
+    Closure(u32),
+    /// Like `Closure`, for a function evaluating its body directly.
+    Interpreted(u32),
+    /// Replace the base of a class with the object it derives from.
+    Base,
+    MakeClass(u32),
+    /// Pop the given number of keys, each followed by its value, into a map.
+    MakeMap(u32),
+    Break,
+    /// Wrap the value on top of the stack in a return, unwound like a break
+    /// up to the end of the function.
+    EarlyReturn,
+    Jump(u32),
+    JumpIfFalse(u32),
+    /// Jump if the value on top of the stack decides a logical operation,
+    /// replacing it with the result.
+    ShortCircuit(Op, u32),
+    /// Jump if the value on top of the stack is a break or a return, keeping
+    /// it.
+    JumpIfBreak(u32),
+    /// Pop the result of a loop body, unwrap it if it's a break, keep it if
+    /// it's a return, or jump back.
+    LoopBack(u32),
+    /// Pop the items of a deque, string or map to loop over.
+    Iter,
+    /// Pop the end and then the start of a range of numbers to loop over.
+    IterRange,
+    /// Put the next value of the innermost loop into a slot, or drop the
+    /// loop's values and jump once there are none left.
+    Next(u32, u32),
+    /// Drop the values of the innermost loop.
+    EndIter,
+    /// Errors raised until the matching `EndTry` jump to the given handler.
+    Try(u32),
+    EndTry,
+    /// Jump to the block handling the error on top of the stack, or pop and
+    /// raise it again.
+    Catch(u32),
+    /// Pop a value and raise it as an error.
+    Throw,
+    /// Replace the path of a module with the module.
+    Import,
+    /// Bind the slots of a pattern if the value on top of the stack matches
+    /// it, otherwise jump.
+    Match(u32, u32),
+    /// Pop a value no pattern matched and raise an error.
+    NoMatch,
+    /// Pop a value and hand it to whoever resumed the generator, pushing a
+    /// unit once it's resumed again.
+    Yield,
+    Return,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Catch {
+    excepts: Vec<(String, u32)>,
+    any: Option<u32>,
+}
+
+/// Slots of the members of a class, and of its base if it derives from one.
+#[derive(Debug, Default)]
+pub(crate) struct ClassSlots {
+    members: Vec<(String, u32)>,
+    base: Option<u32>,
+}
+
+/// Code of the whole program or of a single function.
+#[derive(Debug, Default)]
+pub(crate) struct Proto {
+    code: Vec<Instr>,
+    consts: Vec<Val>,
+    names: Vec<String>,
+    /// Name of every slot, used when a slot is read before being set.
+    slots: Vec<String>,
+    /// Parameters, which take the leading slots.
+    args: Rc<[Arg]>,
+    /// Slots filled from the bindings captured by the function.
+    free: Vec<(String, u32)>,
+    /// Slots of the defining function captured when the function is created.
+    captures: Vec<(String, u32)>,
+    protos: Vec<Rc<Proto>>,
+    /// Functions using 🪞, left to the tree-walker, with the slots they
+    /// capture.
+    funcs: Vec<(Func, Vec<(String, u32)>)>,
+    sites: Vec<TraceFrame>,
+    classes: Vec<ClassSlots>,
+    catches: Vec<Catch>,
+    /// Patterns with the slots of their bindings.
+    patterns: Vec<(Pattern, Vec<u32>)>,
+    /// Whether calls make a generator running the code instead of running
+    /// it right away.
+    generator: bool,
+}
+
+/// A program compiled to bytecode.
+#[derive(Debug)]
+pub struct Program {
+    code: Code,
+}
+
+#[derive(Debug)]
+enum Code {
+    Compiled(Box<Proto>),
+    /// A program using 🪞 outside of functions, whose locals the code it runs
+    /// looks up by name. The tree-walker evaluates it instead.
+    Interpreted(Expr),
+}
+
+impl Program {
+    pub fn compile(expr: &Expr) -> Self {
+        let mut expr = expr.clone();
+        let code = if expr.calls_eval() {
+            Code::Interpreted(expr)
+        } else {
+            Code::Compiled(Box::new(compiler::Compiler::script(&expr)))
+        };
+
+        Program { code }
+    }
+}
+
+impl Eval for Program {
+    fn eval(&self, env: &mut Env) -> Result<Val, Val> {
+        match &self.code {
+            Code::Compiled(proto) => run(proto, vec![None; proto.slots.len()], env),
+            Code::Interpreted(expr) => env.eval(expr),
+        }
+    }
+}
+
+struct Handler {
+    pc: usize,
+    stack_len: usize,
+    iters_len: usize,
+    trace_len: usize,
+}
+
+struct Machine<'p> {
+    proto: &'p Proto,
+    slots: Vec<Option<Val>>,
+    stack: Vec<Val>,
+    handlers: Vec<Handler>,
+    /// Values of the loops being run, innermost last.
+    iters: Vec<Iter>,
+    pc: usize,
+    /// Frames recorded before the handler being entered was installed.
+    trace_len: usize,
+}
+
+/// How a machine stopped running.
+enum Exit {
+    Return(Val),
+    Yield(Val),
+}
+
+pub(crate) fn run(proto: &Proto, slots: Vec<Option<Val>>, env: &mut Env) -> Result<Val, Val> {
+    let mut machine = Machine {
+        proto,
+        slots,
+        stack: Vec::new(),
+        handlers: Vec::new(),
+        iters: Vec::new(),
+        pc: 0,
+        trace_len: 0,
+    };
+
+    match machine.run(env)? {
+        Exit::Return(val) => Ok(val),
+        Exit::Yield(_) => Err(RuntimeError::YieldOutsideGenerator.into()),
+    }
+}
+
+/// Result of `op` on two numbers, computed without the look through
+/// references and operator overloads `Op::eval` does. `None` leaves the
+/// operations that can fail to it.
+fn int_op(op: &Op, n1: i32, n2: i32) -> Option<Val> {
+    Some(match op {
+        Op::Add => Val::Number(n1.checked_add(n2)?),
+        Op::Sub => Val::Number(n1.checked_sub(n2)?),
+        Op::Mul => Val::Number(n1.checked_mul(n2)?),
+        Op::Greater => Val::Bool(n1 > n2),
+        Op::GreaterEq => Val::Bool(n1 >= n2),
+        Op::Less => Val::Bool(n1 < n2),
+        Op::LessEq => Val::Bool(n1 <= n2),
+        Op::Eq | Op::FuzzyEq => Val::Bool(n1 == n2),
+        Op::NotEq => Val::Bool(n1 != n2),
+        _ => return None,
+    })
+}
+
+impl Machine<'_> {
+    /// Runs the code until it returns or yields.
+    fn run(&mut self, env: &mut Env) -> Result<Exit, Val> {
+        loop {
+            match self.step(env) {
+                Ok(None) => {}
+                Ok(Some(exit)) => return Ok(exit),
+                Err(err) => match self.handlers.pop() {
+                    Some(handler) => {
+                        self.stack.truncate(handler.stack_len);
+                        self.iters.truncate(handler.iters_len);
+                        self.stack.push(err);
+                        self.pc = handler.pc;
+                        self.trace_len = handler.trace_len;
+                    }
+                    None => return Err(err),
+                },
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Val {
+        self.stack.pop().expect("stack empty")
+    }
+
+    fn slot_name(&self, slot: u32) -> &str {
+        &self.proto.slots[slot as usize]
+    }
+
+    /// Pops the values of the defaults of a function taking `args`.
+    fn defaults(&mut self, args: &[Arg]) -> Vec<Val> {
+        let defaults_n = args
+            .iter()
+            .filter(|arg| matches!(arg, Arg::Default(..)))
+            .count();
+        self.stack.split_off(self.stack.len() - defaults_n)
+    }
+
+    /// References to the slots a function captures.
+    fn captured(&mut self, captures: &[(String, u32)]) -> Captured {
+        captures
+            .iter()
+            .filter_map(|(name, slot)| {
+                let val = self.slots[*slot as usize].as_mut()?;
+                Some((name.clone(), val.make_ref()))
+            })
+            .collect()
+    }
+
+    // inlined into the dispatch loop, which most of the time is spent in.
+    #[inline(always)]
+    fn step(&mut self, env: &mut Env) -> Result<Option<Exit>, Val> {
+        let proto = self.proto;
+        let instr = &proto.code[self.pc];
+        self.pc += 1;
+
+        match instr {
+            Instr::Const(idx) => self.stack.push(proto.consts[*idx as usize].clone()),
+            Instr::Unit => self.stack.push(Val::Unit),
+            Instr::Pop => {
+                self.pop();
+            }
+            Instr::Load(slot) => {
+                let val = match &self.slots[*slot as usize] {
+                    Some(val) => val.clone(),
+                    None => env.get_binding(self.slot_name(*slot))?,
+                };
+                self.stack.push(val.upgrade()?);
+            }
+            Instr::LoadName(name) => {
+                let val = env.get_binding(&proto.names[*name as usize])?;
+                self.stack.push(val.upgrade()?);
+            }
+            Instr::Declare(slot) => {
+                let val = self.pop();
+                self.slots[*slot as usize] = Some(val);
+            }
+            Instr::DeclareName(name) => {
+                let val = self.pop();
+                env.store_binding(proto.names[*name as usize].clone(), val);
+            }
+            Instr::DeclareGlobal(name) => {
+                let val = self.pop();
+                env.store_global(proto.names[*name as usize].clone(), val);
+            }
+            Instr::Set(slot) => {
+                let new_val = self.pop();
+                match &mut self.slots[*slot as usize] {
+                    Some(Val::Ref(rc)) => *rc.borrow_mut() = new_val,
+                    Some(val) => *val = new_val,
+                    None => env.set_binding(self.slot_name(*slot), new_val)?,
+                }
+            }
+            Instr::SetName(name) => {
+                let new_val = self.pop();
+                env.set_binding(&proto.names[*name as usize], new_val)?;
+            }
+            Instr::Ref(slot) => {
+                let val_ref = match &mut self.slots[*slot as usize] {
+                    Some(val) => val.make_ref(),
+                    None => env.take_ref(self.slot_name(*slot))?,
+                };
+                self.stack.push(val_ref);
+            }
+            Instr::RefName(name) => {
+                let val_ref = env.take_ref(&proto.names[*name as usize])?;
+                self.stack.push(val_ref);
+            }
+            Instr::BindFunc(slot) => {
+                let mut val = self.pop();
+                let name = self.slot_name(*slot);
+                let own_cell = match &mut val {
+                    Val::Func(DynFunc(df)) => {
+                        let any = df.as_any_mut();
+                        match any.downcast_mut::<VmFunc>() {
+                            Some(func) => func.weaken_capture(name),
+                            None => any
+                                .downcast_mut::<FuncVal>()
+                                .and_then(|func| func.weaken_capture(name)),
+                        }
+                    }
+                    _ => None,
+                };
+
+                match own_cell {
+                    Some(rc) => *rc.borrow_mut() = val,
+                    None => self.slots[*slot as usize] = Some(val),
+                }
+            }
+            Instr::Clear(from, to) => {
+                for slot in &mut self.slots[*from as usize..*to as usize] {
+                    *slot = None;
+                }
+            }
+            Instr::BinOp(op) => {
+                let rhs = self.pop();
+                let lhs = self.stack.last_mut().expect("stack empty");
+                let result = match (&*lhs, &rhs) {
+                    (Val::Number(n1), Val::Number(n2)) => int_op(op, *n1, *n2),
+                    _ => None,
+                };
+                *lhs = match result {
+                    Some(result) => result,
+                    None => op.eval(lhs, &rhs, env)?,
+                };
+            }
+            Instr::UnaryOp(op) => {
+                let val = self.pop();
+                self.stack.push(op.apply(&val)?);
+            }
+            Instr::Named(name) => {
+                let val = self.pop();
+                let name = proto.names[*name as usize].clone();
+                self.stack.push(Val::Named((name, Box::new(val))));
+            }
+            Instr::Member(name) => {
+                let val = self.pop();
+                self.stack
+                    .push(index::member(val, &proto.names[*name as usize])?);
+            }
+            Instr::SetMember(name) => {
+                let object = self.pop();
+                let val = self.pop();
+                index::set_member(object, &proto.names[*name as usize], val)?;
+            }
+            Instr::Upgrade => {
+                let val = self.pop();
+                self.stack.push(val.upgrade()?);
+            }
+            Instr::Call { argc, site } => {
+                let func = self.pop();
+                let start = self.stack.len() - *argc as usize;
+                let site = &proto.sites[*site as usize];
+                let result = call::call_val(&func, &mut self.stack[start..], env, || site.clone())?;
+                self.stack.truncate(start);
+                self.stack.push(result.upgrade()?);
+            }
+            Instr::Closure(idx) => {
+                let inner = proto.protos[*idx as usize].clone();
+                let defaults = self.defaults(&inner.args);
+                let captured = self.captured(&inner.captures);
+
+                self.stack
+                    .push(Val::from_func(VmFunc::new(inner, defaults, captured)));
+            }
+            Instr::Interpreted(idx) => {
+                let (func, captures) = &proto.funcs[*idx as usize];
+                let defaults = self.defaults(&func.args);
+                let captured = self.captured(captures);
+
+                self.stack.push(func.interpreted(defaults, captured));
+            }
+            Instr::Base => {
+                let base = self.pop();
+                self.stack.push(class::base_object(base, env)?);
+            }
+            Instr::MakeClass(idx) => {
+                let class_slots = &proto.classes[*idx as usize];
+                let members = class_slots
+                    .members
+                    .iter()
+                    .filter_map(|(name, slot)| {
+                        Some((name.clone(), self.slots[*slot as usize].take()?))
+                    })
+                    .collect();
+                let base = class_slots
+                    .base
+                    .and_then(|slot| self.slots[slot as usize].take());
+
+                let class_obj = match base {
+                    Some(base) => ClassObject::derive(base, members)?,
+                    None => ClassObject::new(members)?,
+                };
+                self.stack.push(Val::from_obj(class_obj));
+            }
+            Instr::MakeMap(n) => {
+                let mut entries = self
+                    .stack
+                    .split_off(self.stack.len() - 2 * *n as usize)
+                    .into_iter();
+                let mut map = crate::val::Map::default();
+                while let (Some(key), Some(val)) = (entries.next(), entries.next()) {
+                    map.insert(Key::try_from(&key)?, val);
+                }
+
+                self.stack.push(Val::Map(Box::new(map)));
+            }
+            Instr::Break => {
+                let val = self.pop();
+                self.stack.push(Val::Break(Box::new(val)));
+            }
+            Instr::EarlyReturn => {
+                let val = self.pop();
+                self.stack.push(Val::Return(Box::new(val)));
+            }
+            Instr::Jump(target) => self.pc = *target as usize,
+            Instr::JumpIfFalse(target) => {
+                if !*self.pop().as_bool()? {
+                    self.pc = *target as usize;
+                }
+            }
+            Instr::ShortCircuit(op, target) => {
+                let lhs = self.stack.last().expect("stack empty");
+                if let Some(result) = op.short_circuit(lhs)? {
+                    *self.stack.last_mut().expect("stack empty") = result;
+                    self.pc = *target as usize;
+                }
+            }
+            Instr::JumpIfBreak(target) => {
+                if let Some(Val::Break(_) | Val::Return(_)) = self.stack.last() {
+                    self.pc = *target as usize;
+                }
+            }
+            Instr::LoopBack(target) => match self.pop() {
+                Val::Break(val) => self.stack.push(*val),
+                ret @ Val::Return(_) => self.stack.push(ret),
+                _ => {
+                    env.check_timeout()?;
+                    self.pc = *target as usize;
+                }
+            },
+            Instr::Iter => {
+                let val = self.pop();
+                self.iters.push(for_expr::items(&val)?);
+            }
+            Instr::IterRange => {
+                let end = self.pop();
+                let start = self.pop();
+                self.iters.push(for_expr::range(&start, &end)?);
+            }
+            Instr::Next(slot, target) => match self.iters.last_mut().expect("no loop").next(env)? {
+                Some(val) => self.slots[*slot as usize] = Some(val),
+                None => {
+                    self.iters.pop();
+                    self.pc = *target as usize;
+                }
+            },
+            Instr::EndIter => {
+                self.iters.pop();
+            }
+            Instr::Try(target) => self.handlers.push(Handler {
+                pc: *target as usize,
+                stack_len: self.stack.len(),
+                iters_len: self.iters.len(),
+                trace_len: env.trace_len(),
+            }),
+            Instr::EndTry => {
+                self.handlers.pop();
+            }
+            Instr::Catch(idx) => {
+                let err = self.stack.last().expect("stack empty");
+                let catch = &proto.catches[*idx as usize];
+
+                let typed = error_type(err).and_then(|ty| {
+                    catch
+                        .excepts
+                        .iter()
+                        .find(|(name, _)| *name == ty)
+                        .map(|(_, target)| *target)
+                });
+
+                match typed.or(catch.any) {
+                    Some(target) => {
+                        let err = self.pop();
+                        let trace = env.split_trace(self.trace_len);
+                        self.stack.push(Traced::caught(err, trace));
+                        self.pc = target as usize;
+                    }
+                    None => return Err(self.pop()),
+                }
+            }
+            Instr::Throw => return Err(self.pop()),
+            Instr::Import => {
+                let path = self.pop();
+                self.stack.push(import::import(env, path)?);
+            }
+            Instr::Match(idx, target) => {
+                let (pattern, slots) = &proto.patterns[*idx as usize];
+                let val = self.stack.last().expect("stack empty");
+                let mut bound = Vec::new();
+                if pattern.matches(val, &mut bound)? {
+                    for (slot, val) in slots.iter().zip(bound) {
+                        self.slots[*slot as usize] = Some(val);
+                    }
+                } else {
+                    self.pc = *target as usize;
+                }
+            }
+            Instr::NoMatch => {
+                let val = self.pop();
+                return Err(RuntimeError::NoMatch(val.to_string()).into());
+            }
+            Instr::Yield => return Ok(Some(Exit::Yield(self.pop()))),
+            Instr::Return => return Ok(Some(Exit::Return(self.pop()))),
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RuntimeError;
+
+    fn eval_both(code: &str) -> (Result<Val, Val>, Result<Val, Val>) {
+        let (_, expr) = Expr::parse(code).unwrap();
+        let tree_walk = Env::test().eval(&expr);
+        let vm = Env::test().eval(&Program::compile(&expr));
+
+        (tree_walk, vm)
+    }
+
+    fn assert_same(code: &str, expected: Result<Val, Val>) {
+        let root = |res: Result<Val, Val>| res.map(|v| v.apply_to_root(|v| v.clone()).unwrap());
+        let (tree_walk, vm) = eval_both(code);
+
+        assert_eq!(root(tree_walk), expected);
+        assert_eq!(root(vm), expected);
+    }
+
+    #[test]
+    fn vm_locals() {
+        assert_same(
+            "📦 👶 a = 2 💪 👶 b = a * 3 💪 📦 👶 a = 1 💪 ♻️ b = b + a 🧑‍🦲 💪 b + a 🧑‍🦲",
+            Ok(Val::Number(9)),
+        );
+    }
+
+    #[test]
+    fn vm_loop_break() {
+        assert_same(
+            "📦
+                👶 i = 0 💪
+                🔁 ♻️ i = i + 1 💪 ❓ i == 10 💔 i * 2 🧑‍🦲 🧑‍🦲 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Number(20)),
+        );
+    }
+
+    #[test]
+    fn vm_logic_ops() {
+        assert_same(
+            "📦
+                👶 n = 0 💪
+                👶 bump = 🧰 ➡️ ♻️ n = n + 1 💪 🙆‍♀️ 🧑‍🦲 💪
+                👶 a = 🙅‍♀️ 🤝 📞 bump 💪
+                👶 b = 🙆‍♀️ 🔀 📞 bump 💪
+                👶 c = 🙆‍♀️ 🤝 📞 bump 💪
+                👶 d = 🚫 a 🤝 b 🤝 c 💪
+                👶 e = - n % 7 💪
+                ❓ d 🤝 n != 0 e 🧑‍🦲 😡 0 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Number(-1)),
+        );
+        assert_same(
+            "🙅‍♀️ 🔀 1",
+            Err(RuntimeError::InvalidOp {
+                lhs: "🤨".into(),
+                op: "🔀".into(),
+                rhs: "🔢".into(),
+            }
+            .into()),
+        );
+        assert_same(
+            "1 🤝 1 / 0",
+            Err(RuntimeError::InvalidOp {
+                lhs: "🔢".into(),
+                op: "🤝".into(),
+                rhs: "".into(),
+            }
+            .into()),
+        );
+    }
+
+    #[test]
+    fn vm_number_ops() {
+        assert_same("📦 7 - 2 * 3 🧑‍🦲", Ok(Val::Number(1)));
+        assert_same("📦 1 + 2.5 🧑‍🦲", Ok(Val::Float(3.5)));
+        assert_same("📦 👶 a = 3 < 4 💪 a 🤝 4 >= 4 🧑‍🦲", Ok(Val::Bool(true)));
+        assert_same("📦 2147483647 + 1 🧑‍🦲", Err(RuntimeError::Overflow.into()));
+        assert_same("📦 1 / 0 🧑‍🦲", Err(RuntimeError::DivisionByZero.into()));
+    }
+
+    #[test]
+    fn vm_float_equality() {
+        assert_same("📦 1.0 == 1 🧑‍🦲", Ok(Val::Bool(true)));
+        assert_same("📦 1 != 1.5 🧑‍🦲", Ok(Val::Bool(true)));
+    }
+
+    #[test]
+    fn vm_map_literal() {
+        let mut expected = crate::val::Map::default();
+        expected.insert(Key::Number(1), Val::from("b"));
+        expected.insert(Key::Str("a".into()), Val::Number(6));
+
+        assert_same(
+            "📦 👶 k = 🧵a🧵 💪 🗺️ 1 ➡️ 🧵a🧵 💪 k ➡️ 2 * 3 💪 1 ➡️ 🧵b🧵 🧑‍🦲 🧑‍🦲",
+            Ok(Val::Map(Box::new(expected))),
+        );
+        assert_same(
+            "🗺️ 🗺️🧑‍🦲 ➡️ 1 🧑‍🦲",
+            Err(RuntimeError::CastError {
+                from: "🗺️".into(),
+                to: "🔑".into(),
+            }
+            .into()),
+        );
+    }
+
+    #[test]
+    fn vm_if_chain() {
+        assert_same(
+            "📦
+                👶 f = 🧰 x ➡️ ❓ x < 0 1 🧑‍🦲 😠 x < 10 20 🧑‍🦲 😡 300 🧑‍🦲 🧑‍🦲 💪
+                👶 a = 📞 f 📦 0 - 5 🧑‍🦲 💪
+                👶 b = 📞 f 5 💪
+                👶 c = 📞 f 50 💪
+                a + b + c
+            🧑‍🦲",
+            Ok(Val::Number(321)),
+        );
+    }
+
+    #[test]
+    fn vm_recursion() {
+        assert_same(
+            "📦
+                👶 fib = 🧰 n ➡️
+                    ❓ n < 2 n 🧑‍🦲 😡
+                        👶 a = 📞 fib 📦 n - 1 🧑‍🦲 💪
+                        👶 b = 📞 fib 📦 n - 2 🧑‍🦲 💪
+                        a + b
+                    🧑‍🦲
+                🧑‍🦲 💪
+                📞 fib 15
+            🧑‍🦲",
+            Ok(Val::Number(610)),
+        );
+    }
+
+    #[test]
+    fn vm_closure_counter() {
+        assert_same(
+            "📦
+                👶 counter = 🧰 ➡️ 👶 n = 0 💪 🧰 ➡️ ♻️ n = n + 1 💪 n 🧑‍🦲 🧑‍🦲 💪
+                👶 c1 = 📞 counter 💪
+                👶 c2 = 📞 counter 💪
+                📞 c1 💪 📞 c1 💪
+                👶 a = 📞 c1 💪
+                👶 b = 📞 c2 💪
+                b + 📦 a * 10 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Number(31)),
+        );
+
+        let (tree_walk, vm) = eval_both(
+            "📦
+                👶 counter = 🧰 ➡️ 👶 n = 0 💪 🧰 ➡️ ♻️ n = n + 1 💪 n 🧑‍🦲 🧑‍🦲 💪
+                👶 c = 📞 counter 💪
+                👶 first = 📞 c 💪
+                ♻️ first = 100 💪
+                📞 c
+            🧑‍🦲",
+        );
+        assert_eq!(tree_walk, Ok(Val::Number(2)));
+        assert_eq!(vm, tree_walk);
+    }
+
+    #[test]
+    fn vm_no_dynamic_scope() {
+        assert_same(
+            "📦
+                👶 get_k = 🧰 ➡️ k 🧑‍🦲 💪
+                👶 apply = 🧰 f ➡️ 👶 k = 100 💪 📞 f 🧑‍🦲 💪
+                📞 apply get_k
+            🧑‍🦲",
+            Err(RuntimeError::NoBinding("k".into()).into()),
+        );
+    }
+
+    #[test]
+    fn vm_variadic() {
+        assert_same(
+            "📦 👶 f = 🧰 a 👨‍👨‍👦rest ➡️ rest 🧑‍🦲 💪 📞 f 1 2 3 🧑‍🦲",
+            Ok(Val::Deque(Box::new(
+                [Val::Number(2), Val::Number(3)].into(),
+            ))),
+        );
+        assert_same(
+            "📦 👶 f = 🧰 a b ➡️ a 🧑‍🦲 💪 📞 f 1 2 3 🧑‍🦲",
+            Err(RuntimeError::WrongArgsN {
+                expected: 2,
+                received: 3,
+            }
+            .into()),
+        );
+    }
+
+    #[test]
+    fn vm_default_named_args() {
+        assert_same(
+            "📦
+                👶 n = 1 💪
+                👶 f = 🧰 a b = n 🗺️opts ➡️ 🗺️ 1 ➡️ a 💪 2 ➡️ b 💪 3 ➡️ opts 🧑‍🦲 🧑‍🦲 💪
+                ♻️ n = 2 💪
+                👶 x = 📞 f 5 💪
+                👶 y = 📞 f c: 4 b: 3 a: 2 💪
+                🗺️ 1 ➡️ x 💪 2 ➡️ y 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Map(Box::new(
+                [
+                    (
+                        Key::Number(1),
+                        Val::Map(Box::new(
+                            [
+                                (Key::Number(1), Val::Number(5)),
+                                (Key::Number(2), Val::Number(1)),
+                                (Key::Number(3), Val::Map(Box::default())),
+                            ]
+                            .into_iter()
+                            .collect(),
+                        )),
+                    ),
+                    (
+                        Key::Number(2),
+                        Val::Map(Box::new(
+                            [
+                                (Key::Number(1), Val::Number(2)),
+                                (Key::Number(2), Val::Number(3)),
+                                (
+                                    Key::Number(3),
+                                    Val::Map(Box::new(
+                                        [(Key::Str("c".into()), Val::Number(4))]
+                                            .into_iter()
+                                            .collect(),
+                                    )),
+                                ),
+                            ]
+                            .into_iter()
+                            .collect(),
+                        )),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ))),
+        );
+        assert_same(
+            "📦 👶 f = 🧰 a b ➡️ a 🧑‍🦲 💪 📞 f b: 1 🧑‍🦲",
+            Err(RuntimeError::WrongArgsN {
+                expected: 2,
+                received: 1,
+            }
+            .into()),
+        );
+    }
+
+    #[test]
+    fn vm_globals() {
+        assert_same(
+            "📦 👶 f = 🧰 ➡️ 🌍 g = 7 🧑‍🦲 💪 📞 f 💪 📞 🧰 ➡️ g 🧑‍🦲 🧑‍🦲",
+            Ok(Val::Number(7)),
+        );
+    }
+
+    #[test]
+    fn vm_class() {
+        assert_same(
+            "📦
+                👶 obj = 🧑‍🏫
+                    👶 x = 1 💪
+                    👶 inc = 🧰 ➡️ ♻️ x = x + 1 🧑‍🦲 💪
+                    👶 get = 🧰 ➡️ x 🧑‍🦲
+                🧑‍🦲 💪
+                📞 obj🪆inc 💪 📞 obj🪆inc 💪
+                📞 obj🪆get
+            🧑‍🦲",
+            Ok(Val::Number(3)),
+        );
+    }
+
+    #[test]
+    fn vm_overloaded_ops() {
+        assert_same(
+            "📦
+                👶 Frac = 🧰 n d ➡️ 🧑‍🏫
+                    👶 n = n 💪
+                    👶 d = d 💪
+                    👶 ✖️ = 🧰 a b ➡️ 📞 Frac 📦 a🪆n * b🪆n 🧑‍🦲 📦 a🪆d * b🪆d 🧑‍🦲 🧑‍🦲 💪
+                    👶 ◀️ = 🧰 a b ➡️
+                        👶 l = a🪆n * b🪆d 💪
+                        👶 r = b🪆n * a🪆d 💪
+                        l < r
+                    🧑‍🦲
+                🧑‍🦲 🧑‍🦲 💪
+                👶 half = 📞 Frac 1 2 💪
+                👶 third = 📞 Frac 1 3 💪
+                👶 sixth = half * third 💪
+                ❓ sixth < third sixth🪆d 🧑‍🦲 😡 0 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Number(6)),
+        );
+    }
+
+    #[test]
+    fn vm_generators() {
+        assert_same(
+            "📦
+                👶 step = 3 💪
+                👶 multiples = 🧰 n ➡️
+                    👶 i = 0 💪
+                    🔁
+                        ♻️ i = i + step 💪
+                        ❓ i > n 🔙 i 🧑‍🦲 🧑‍🦲 💪
+                        👩‍🚒 🐣 i 🧑‍🦲 🧑‍🦲 🤡 e ➡️ e 🧑‍🦲
+                    🧑‍🦲
+                🧑‍🦲 💪
+                👶 s = 0 💪
+                👶 g = 📞 multiples 10 💪
+                🔂 i 📥 g ♻️ s = s + i 🧑‍🦲 💪
+                s
+            🧑‍🦲",
+            Ok(Val::Number(18)),
+        );
+        // yielded units don't end the loop.
+        assert_same(
+            "📦
+                👶 gen = 🧰 ➡️ 🐣 1 🧑‍🦲 💪 🐣 📦🧑‍🦲 💪 🐣 2 🧑‍🦲 🧑‍🦲 💪
+                👶 g = 📞 gen 💪
+                👶 n = 0 💪
+                🔂 x 📥 g ♻️ n = n + 1 🧑‍🦲 💪
+                n
+            🧑‍🦲",
+            Ok(Val::Number(3)),
+        );
+        assert_same(
+            "📦 🐣 1 🧑‍🦲 🧑‍🦲",
+            Err(RuntimeError::YieldOutsideGenerator.into()),
+        );
+    }
+
+    #[test]
+    fn vm_member_update() {
+        assert_same(
+            "📦
+                👶 outer = 🧑‍🏫
+                    👶 inner = 🧑‍🏫 👶 x = 1 🧑‍🦲 💪
+                    👶 get = 🧰 ➡️ inner🪆x 🧑‍🦲
+                🧑‍🦲 💪
+                ♻️ outer🪆inner🪆x = 📦 outer🪆inner🪆x + 6 🧑‍🦲 💪
+                📞 outer🪆get
+            🧑‍🦲",
+            Ok(Val::Number(7)),
+        );
+        assert_same(
+            "📦 👶 n = 1 💪 ♻️ n🪆x = 2 🧑‍🦲",
+            Err(RuntimeError::CastError {
+                from: "🔢".into(),
+                to: "🧑‍🏫".into(),
+            }
+            .into()),
+        );
+    }
+
+    #[test]
+    fn vm_class_derive() {
+        assert_same(
+            "📦
+                👶 Counter = 🧰 ➡️ 🧑‍🏫
+                    👶 n = 0 💪
+                    👶 step = 1 💪
+                    👶 inc = 🧰 ➡️ ♻️ n = n + step 🧑‍🦲 💪
+                    👶 get = 🧰 ➡️ n 🧑‍🦲
+                🧑‍🦲 🧑‍🦲 💪
+                👶 c = 🧑‍🏫 🧬 Counter
+                    👶 step = 10 💪
+                    👶 get = 🧰 ➡️ 1000 + 📞 👴🪆get 🧑‍🦲
+                🧑‍🦲 💪
+                👶 d = 🧑‍🏫 🧬 c 🧑‍🦲 💪
+                📞 c🪆inc 💪 📞 c🪆inc 💪 📞 d🪆inc 💪
+                📞 d🪆get
+            🧑‍🦲",
+            Ok(Val::Number(1010)),
+        );
+        assert_same(
+            "🧑‍🏫 🧬 🙆‍♀️ 🧑‍🦲",
+            Err(RuntimeError::CastError {
+                from: "🤨".into(),
+                to: "🧑‍🏫".into(),
+            }
+            .into()),
+        );
+    }
+
+    #[test]
+    fn vm_try() {
+        assert_same(
+            "📦
+                👶 r = 👩‍🚒 📞 🧰 ➡️ x 🧑‍🦲 🧑‍🦲 🤡 CastError 1 🧑‍🦲 🤡 NoBinding 2 🧑‍🦲 💪
+                👶 s = 👩‍🚒 x 🧑‍🦲 🤡 3 🧑‍🦲 💪
+                s + 📦 r * 10 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Number(23)),
+        );
+        assert_same(
+            "👩‍🚒 x 🧑‍🦲 🤡 CastError 1 🧑‍🦲",
+            Err(RuntimeError::NoBinding("x".into()).into()),
+        );
+    }
+
+    #[test]
+    fn vm_throw() {
+        assert_same(
+            "📦
+                👶 Oops = 🧰 ➡️ 🧑‍🏫 👶 type = 🧵Oops🧵 🧑‍🦲 🧑‍🦲 💪
+                👶 r = 👩‍🚒 🧨 📞 Oops 🧑‍🦲 🧑‍🦲 🤡 NoBinding 1 🧑‍🦲 🤡 Oops 2 🧑‍🦲 💪
+                👶 s = 👩‍🚒 🧨 5 🧑‍🦲 🧑‍🦲 🤡 Oops 3 🧑‍🦲 🤡 4 🧑‍🦲 💪
+                s + 📦 r * 10 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Number(24)),
+        );
+        assert_same("🧨 🧵oops🧵 🧑‍🦲", Err(Val::from("oops")));
+    }
+
+    #[test]
+    fn vm_return() {
+        assert_same(
+            "📦
+                👶 find = 🧰 s c ➡️
+                    👶 i = 0 💪
+                    🔂 x 📥 s
+                        ❓ x == c 🔙 i 🧑‍🦲 🧑‍🦲 💪
+                        ♻️ i = i + 1
+                    🧑‍🦲 💪
+                    - 1
+                🧑‍🦲 💪
+                👶 a = 📞 find 🧵abc🧵 🔡c🔡 💪
+                👶 b = 📞 find 🧵abc🧵 🔡d🔡 💪
+                b + a * 10
+            🧑‍🦲",
+            Ok(Val::Number(19)),
+        );
+        assert_same(
+            "📞 🧰 ➡️ 👶 a = 💔 1 🧑‍🦲 💪 a 🧑‍🦲 🧑‍🦲",
+            Err(RuntimeError::BreakOutsideLoop.into()),
+        );
+    }
+
+    #[test]
+    fn vm_try_binding_finally() {
+        assert_same(
+            "📦
+                👶 n = 0 💪
+                👶 a = 👩‍🚒 y 🧑‍🦲 🤡 NoBinding e ➡️ e🪆binding 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲 💪
+                👶 b = 🔁 👩‍🚒 💔 2 🧑‍🦲 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲 🧑‍🦲 💪
+                👶 c = 👩‍🚒
+                    👩‍🚒 🧨 3 🧑‍🦲 🧑‍🦲 🤡 e ➡️ 🧨 e + 1 🧑‍🦲 🧑‍🦲 🧹 ♻️ n = n + 1 🧑‍🦲
+                🧑‍🦲 🤡 e ➡️ e 🧑‍🦲 💪
+                🗺️ 1 ➡️ a 💪 2 ➡️ b 💪 3 ➡️ c 💪 4 ➡️ n 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Map(Box::new(
+                [
+                    (Key::Number(1), Val::from("y")),
+                    (Key::Number(2), Val::Number(2)),
+                    (Key::Number(3), Val::Number(4)),
+                    (Key::Number(4), Val::Number(3)),
+                ]
+                .into_iter()
+                .collect(),
+            ))),
+        );
+    }
+
+    #[test]
+    fn vm_for_while() {
+        assert_same(
+            "📦
+                👶 n = 0 💪
+                🔂 c 📥 🧵abcb🧵 ❓ c == 🔡b🔡 ♻️ n = n + 1 🧑‍🦲 🧑‍🦲 💪
+                👶 first = 🔂 i 📥 0 ⏩ 10 ❓ 10 < i * i 💔 i 🧑‍🦲 🧑‍🦲 🧑‍🦲 💪
+                👶 caught = 👩‍🚒 🔂 i 📥 0 ⏩ 3 🧨 i 🧑‍🦲 🧑‍🦲 🧑‍🦲 🤡 e ➡️ e 🧑‍🦲 💪
+                👶 k = 1 💪
+                ⏳ k < 100 ♻️ k = k * 3 🧑‍🦲 💪
+                🗺️ 1 ➡️ n 💪 2 ➡️ first 💪 3 ➡️ caught 💪 4 ➡️ k 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Map(Box::new(
+                [
+                    (Key::Number(1), Val::Number(2)),
+                    (Key::Number(2), Val::Number(4)),
+                    (Key::Number(3), Val::Number(0)),
+                    (Key::Number(4), Val::Number(243)),
+                ]
+                .into_iter()
+                .collect(),
+            ))),
+        );
+        assert_same(
+            "📦
+                👶 s = 0 💪
+                🔂 e 📥 🗺️ 2 ➡️ 20 💪 1 ➡️ 10 🧑‍🦲
+                    🧩 e 👉 😵‍💫 k 💪 v 🧑‍🦲 ➡️ ♻️ s = v + k + s * 100 🧑‍🦲 🧑‍🦲
+                🧑‍🦲 💪
+                s
+            🧑‍🦲",
+            Ok(Val::Number(1122)),
+        );
+        assert_same(
+            "🔂 i 📥 1 ⏩ 🔡a🔡 🧑‍🦲",
+            Err(RuntimeError::CastError {
+                from: "🔡".into(),
+                to: "🔢".into(),
+            }
+            .into()),
+        );
+    }
+
+    #[test]
+    fn vm_match() {
+        assert_same(
+            "📦
+                👶 sum = 🧰 s ➡️
+                    🧩 s
+                        👉 😵‍💫🧑‍🦲 ➡️ 0 🧑‍🦲
+                        👉 😵‍💫 h 💪 👨‍👨‍👦t 🧑‍🦲 ❓ h == 🔡x🔡 ➡️ 10 + 📞 sum t 🧑‍🦲
+                        👉 😵‍💫 _ 💪 👨‍👨‍👦t 🧑‍🦲 ➡️ 1 + 📞 sum t 🧑‍🦲
+                    🧑‍🦲
+                🧑‍🦲 💪
+                👶 p = 🧑‍🏫 👶 x = 🧵xax🧵 🧑‍🦲 💪
+                🧩 p 👉 🧑‍🏫 x:y 🧑‍🦲 ➡️ 📞 sum y 🧑‍🦲 🧑‍🦲
+            🧑‍🦲",
+            Ok(Val::Number(21)),
+        );
+        assert_same(
+            "📦
+                👶 n = 0 💪
+                👶 found = 🔁
+                    ♻️ n = n + 1 💪
+                    🧩 n 👉 3 ➡️ 💔 n * 10 🧑‍🦲 🧑‍🦲 👉 _ ➡️ 🧑‍🦲 🧑‍🦲
+                🧑‍🦲 💪
+                👶 early = 🔁 🧩 💔 5 🧑‍🦲 👉 _ ➡️ 0 🧑‍🦲 🧑‍🦲 🧑‍🦲 💪
+                👶 f = 🧰 x ➡️ 🧩 x 👉 0 ➡️ 🔙 0 🧑‍🦲 🧑‍🦲 👉 _ ➡️ 🧑‍🦲 🧑‍🦲 💪 x 🧑‍🦲 💪
+                👶 a = 📞 f 4 💪
+                found + early + a
+            🧑‍🦲",
+            Ok(Val::Number(39)),
+        );
+        assert_same(
+            "🧩 1 👉 2 ➡️ 🧑‍🦲 🧑‍🦲",
+            Err(RuntimeError::NoMatch("1".into()).into()),
+        );
+    }
+
+    #[test]
+    fn vm_ref() {
+        assert_same(
+            "📦 👶 a = 1 💪 👶 r = 🔖a 💪 ♻️ r = 5 💪 a 🧑‍🦲",
+            Ok(Val::Number(5)),
+        );
+    }
+
+    #[test]
+    fn vm_trace() {
+        let (tree_walk, vm) = eval_both(
+            "📦
+                👶 f = 🧰 ➡️ x 🧑‍🦲 💪
+                📞 🧰 ➡️ 📞 f 🧑‍🦲
+            🧑‍🦲",
+        );
+
+        assert!(tree_walk.is_err());
+        assert_eq!(vm, tree_walk);
+    }
+}
@@ -1,4 +1,4 @@
-use lmang_lib::{builtins::Builtins, env::Env, expr::Expr, system, val::Val};
+use lmang_lib::{builtins::Builtins, env::Env, expr::Expr, system, val::Val, vm::Program};
 
 #[cfg(feature = "mimalloc")]
 #[global_allocator]
@@ -6,22 +6,44 @@ static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 pub struct ExecResult {
     pub stdout: String,
+    #[allow(dead_code)]
     pub return_val: Result<Val, Val>,
 }
 
+#[allow(dead_code)]
+pub enum Engine {
+    TreeWalk,
+    Vm,
+}
+
+#[allow(dead_code)]
 pub fn test_exec(path: String, args: &[String], stdin: &[String]) -> ExecResult {
-    let code = std::fs::read_to_string(path).unwrap();
+    test_exec_with(Engine::TreeWalk, path, args, stdin)
+}
+
+pub fn test_exec_with(
+    engine: Engine,
+    path: String,
+    args: &[String],
+    stdin: &[String],
+) -> ExecResult {
+    let code = std::fs::read_to_string(&path).unwrap();
 
-    let (_, expr) = Expr::new(&code).unwrap();
+    let (_, mut expr) = Expr::parse(&code).unwrap();
 
     let mut env = Env::new();
+    env.set_main_file(&path);
     let (system, system_out) = system::Test::new(args, stdin);
     env.eval(&Builtins::new(system)).unwrap();
-    let val = env.eval(&expr);
+    expr.resolve(&env).unwrap();
+    let val = match engine {
+        Engine::TreeWalk => env.eval(&expr),
+        Engine::Vm => env.eval(&Program::compile(&expr)),
+    };
 
     let borrow = system_out.stdout.borrow();
     ExecResult {
         stdout: borrow.to_string(),
-        return_val: val.map(|v| v),
+        return_val: val,
     }
 }
@@ -0,0 +1,11 @@
+mod test_exec_common;
+
+use test_exec_common::test_exec;
+
+#[test]
+fn modules_stdout() {
+    let lmang_prog = "./examples/modules.🆖".to_string();
+    let result = test_exec(lmang_prog, &[], &[]);
+
+    assert_eq!(result.stdout, "Hello, modules\nHello, modules\n");
+}
@@ -0,0 +1,36 @@
+mod test_exec_common;
+
+use test_exec_common::{test_exec_with, Engine};
+
+fn example_input(name: &str) -> (Vec<String>, Vec<String>) {
+    match name {
+        "brainfuck.🆖" => (vec!["./examples/brainfuck/hello.b".to_string()], vec![]),
+        "cat.🆖" => (vec![], vec!["a\n".to_string(), "b\n".to_string()]),
+        "fmt.🆖" => (vec!["./examples/adder_class.🆖".to_string()], vec![]),
+        _ => (vec![], vec![]),
+    }
+}
+
+#[test]
+fn examples_same_as_tree_walk() {
+    let mut checked = 0;
+    for entry in std::fs::read_dir("./examples").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("🆖") {
+            continue;
+        }
+
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let (args, stdin) = example_input(name);
+        let path = path.to_str().unwrap().to_string();
+
+        let tree_walk = test_exec_with(Engine::TreeWalk, path.clone(), &args, &stdin);
+        let vm = test_exec_with(Engine::Vm, path, &args, &stdin);
+
+        assert_eq!(vm.stdout, tree_walk.stdout, "stdout of {}", name);
+        assert_eq!(vm.return_val, tree_walk.return_val, "result of {}", name);
+        checked += 1;
+    }
+
+    assert!(checked >= 5);
+}